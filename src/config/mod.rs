@@ -1,7 +1,6 @@
 use std::{collections::HashMap, iter::FromIterator};
 use std::fs::File;
 use std::io::prelude::*;
-use toml;
 use serde::Deserialize;
 
 use crate::game::actions::PlayerAction;
@@ -20,7 +19,7 @@ macro_rules! map {
     };
 }
 
-const MAPPABLE_KEYCODES: &[(VirtualKeyCode, &'static str)] = &[
+const MAPPABLE_KEYCODES: &[(VirtualKeyCode, &str)] = &[
     (VirtualKeyCode::Key1, "Key1"),
     (VirtualKeyCode::Key2, "Key2"),
     (VirtualKeyCode::Key3, "Key3"),
@@ -133,6 +132,10 @@ const MAPPABLE_KEYCODES: &[(VirtualKeyCode, &'static str)] = &[
 
 #[derive(Deserialize)]
 pub struct Config {
+    // Resolution of the 3D scene relative to the window
+    pub render_scale: Option<f32>,
+    // Screenshots are taken at this multiple of the window resolution
+    pub screenshot_scale: Option<u32>,
     pub controls: HashMap<String, String>,
 }
 
@@ -144,9 +147,15 @@ impl Default for Config {
             "A".into() => "Left".into(),
             "D".into() => "Right".into(),
             "Space".into() => "Jump".into(),
-            "LControl".into() => "Crouch".into()
+            "LControl".into() => "Crouch".into(),
+            "F12".into() => "Screenshot".into(),
+            "F3".into() => "ToggleDebugDraw".into(),
+            "E".into() => "Use".into(),
+            "F5".into() => "ReloadConfig".into()
         );
         Config {
+            render_scale: None,
+            screenshot_scale: None,
             controls
         }
     }
@@ -171,6 +180,14 @@ impl Config {
         Config::default()
     }
 
+    pub fn render_scale(&self) -> f32 {
+        self.render_scale.unwrap_or(1.0).clamp(0.1, 4.0)
+    }
+
+    pub fn screenshot_scale(&self) -> u32 {
+        self.screenshot_scale.unwrap_or(1).clamp(1, 8)
+    }
+
     pub fn map_to_action(&self, input: InputEvent) -> Option<PlayerAction> {
         match input {
             //Esc
@@ -188,6 +205,10 @@ impl Config {
                         Some("Crouch") => Some(PlayerAction::Crouch(pressed)),
                         Some("Jump") => Some(PlayerAction::Jump(pressed)),
                         Some("Boost") => Some(PlayerAction::Boost(pressed)),
+                        Some("Screenshot") if !pressed => Some(PlayerAction::Screenshot),
+                        Some("ReloadConfig") if !pressed => Some(PlayerAction::ReloadConfig),
+                        Some("ToggleDebugDraw") if !pressed => Some(PlayerAction::ToggleDebugDraw),
+                        Some("Use") if !pressed => Some(PlayerAction::Use),
                         _ => None,
                     }
                 }
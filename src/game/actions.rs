@@ -9,5 +9,11 @@ pub enum PlayerAction {
     Boost(bool),
     Crouch(bool),
     AimDelta(f32, f32),
+    Screenshot,
+    // Read the config file again, for new controls or render scale
+    ReloadConfig,
+    ToggleDebugDraw,
+    // Take control of what the player is looking at, or let go of what they're controlling
+    Use,
     Quit,
 }
\ No newline at end of file
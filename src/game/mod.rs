@@ -6,23 +6,38 @@ mod physics;
 use state::game_state::GameState;
 use tags::{Map, Placement};
 use actions::PlayerAction;
+use crate::util::debug_draw::DebugDraw;
 
 pub struct Game {
     pub map: Map,
-    pub state: GameState,
+    pub state: Box<GameState>,
+    // Redrawn every update while enabled
+    pub debug_draw: DebugDraw,
 }
 
 impl Game {
     pub fn load_map(map_path: &str) -> Game {
         let map = Map::load(map_path);
-        let mut game = Game {
+        Game {
             state: GameState::init(&map),
             map,
-        };
-        game
+            debug_draw: DebugDraw::new(),
+        }
     }
 
     pub fn update(&mut self, actions: &[PlayerAction]) -> bool {
-        self.state.update(&self.map, actions)
+        if actions.iter().any(|action| matches!(action, PlayerAction::ToggleDebugDraw)) {
+            self.debug_draw.toggle();
+        }
+        self.debug_draw.clear();
+        let keep_running = self.state.update(&self.map, actions);
+        self.state.draw_physics(&self.map, &mut self.debug_draw);
+        keep_running
+    }
+
+    pub fn advance_to_tick(&mut self, tick: u32) {
+        self.state.advance_to_tick(&self.map, tick);
+        self.debug_draw.clear();
+        self.state.draw_physics(&self.map, &mut self.debug_draw);
     }
 }
\ No newline at end of file
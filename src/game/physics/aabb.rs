@@ -0,0 +1,44 @@
+use cgmath::Vector3;
+
+use super::convex::Convex;
+
+/// A box aligned to the world axes, which is cheaper to build than an `OrientedBox`.
+#[derive(Copy, Clone, Debug)]
+pub struct Aabb {
+  pub half_extents: Vector3<f32>,
+  pub position: Vector3<f32>,
+  pub velocity: Vector3<f32>,
+}
+
+impl Aabb {
+  pub fn min(&self) -> Vector3<f32> {
+    self.position - self.half_extents
+  }
+
+  pub fn max(&self) -> Vector3<f32> {
+    self.position + self.half_extents
+  }
+}
+
+pub(super) fn box_corner(half_extents: Vector3<f32>, direction: Vector3<f32>) -> Vector3<f32> {
+  let pick = |extent: f32, d: f32| if d >= 0.0 { extent } else { -extent };
+  Vector3::new(
+    pick(half_extents.x, direction.x),
+    pick(half_extents.y, direction.y),
+    pick(half_extents.z, direction.z),
+  )
+}
+
+impl Convex for Aabb {
+  fn core_support(&self, direction: Vector3<f32>) -> Vector3<f32> {
+    self.position + box_corner(self.half_extents, direction)
+  }
+
+  fn center(&self) -> Vector3<f32> {
+    self.position
+  }
+
+  fn velocity(&self) -> Vector3<f32> {
+    self.velocity
+  }
+}
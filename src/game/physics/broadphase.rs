@@ -0,0 +1,163 @@
+use cgmath::Vector3;
+
+use super::collision_filter::CollisionFilter;
+use crate::util::saltybuffer::{SaltyId, NONE};
+
+#[derive(Copy, Clone, Debug)]
+struct Proxy {
+  id: SaltyId,
+  min: Vector3<f32>,
+  max: Vector3<f32>,
+  filter: CollisionFilter,
+  // Position in the sorted order
+  rank: u16,
+}
+
+impl Default for Proxy {
+  fn default() -> Proxy {
+    Proxy {
+      id: NONE,
+      min: Vector3::new(0.0, 0.0, 0.0),
+      max: Vector3::new(0.0, 0.0, 0.0),
+      filter: CollisionFilter::default(),
+      rank: 0,
+    }
+  }
+}
+
+/// Sweep and prune over the X axis. Boxes are keyed by the `SaltyId` slot of their owner, and
+/// kept sorted by their minimum X as they move. Objects move little each tick, so re-sorting
+/// one after an update is usually a swap or two rather than a full sort. Pairs whose collision
+/// filters rule each other out are never reported.
+#[derive(Copy, Clone)]
+pub struct Broadphase<const N: usize> {
+  proxies: [Proxy; N],
+  // Slots of live proxies ordered by min.x
+  sorted: [u16; N],
+  len: usize,
+}
+
+impl<const N: usize> Default for Broadphase<N> {
+  fn default() -> Self {
+    Broadphase::new()
+  }
+}
+
+impl<const N: usize> Broadphase<N> {
+  pub fn new() -> Broadphase<N> {
+    Broadphase {
+      proxies: [Proxy::default(); N],
+      sorted: [0; N],
+      len: 0,
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  pub fn contains(&self, id: SaltyId) -> bool {
+    id.is_some() && self.proxies[id.index()].id == id
+  }
+
+  /// Inserts or moves the box for an id.
+  pub fn update(&mut self, id: SaltyId, min: Vector3<f32>, max: Vector3<f32>, filter: CollisionFilter) {
+    let slot = id.index();
+    if self.proxies[slot].id != id {
+      if self.proxies[slot].id.is_some() {
+        //a stale id in the same slot
+        self.remove(self.proxies[slot].id);
+      }
+      self.sorted[self.len] = slot as u16;
+      self.proxies[slot].rank = self.len as u16;
+      self.len += 1;
+    }
+    let proxy = &mut self.proxies[slot];
+    proxy.id = id;
+    proxy.min = min;
+    proxy.max = max;
+    proxy.filter = filter;
+
+    //insertion sort just this proxy into place
+    let mut rank = proxy.rank as usize;
+    while rank > 0 && self.min_x(rank - 1) > min.x {
+      self.swap(rank - 1, rank);
+      rank -= 1;
+    }
+    while rank + 1 < self.len && self.min_x(rank + 1) < min.x {
+      self.swap(rank, rank + 1);
+      rank += 1;
+    }
+  }
+
+  pub fn remove(&mut self, id: SaltyId) {
+    if !self.contains(id) {
+      return;
+    }
+    let slot = id.index();
+    let rank = self.proxies[slot].rank as usize;
+    for i in rank..self.len - 1 {
+      self.sorted[i] = self.sorted[i + 1];
+      self.proxies[self.sorted[i] as usize].rank = i as u16;
+    }
+    self.len -= 1;
+    self.proxies[slot] = Proxy::default();
+  }
+
+  fn min_x(&self, rank: usize) -> f32 {
+    self.proxies[self.sorted[rank] as usize].min.x
+  }
+
+  fn swap(&mut self, a: usize, b: usize) {
+    self.sorted.swap(a, b);
+    self.proxies[self.sorted[a] as usize].rank = a as u16;
+    self.proxies[self.sorted[b] as usize].rank = b as u16;
+  }
+
+  /// Calls `visit` with every pair of ids whose boxes overlap.
+  pub fn pairs(&self, mut visit: impl FnMut(SaltyId, SaltyId)) {
+    for i in 0..self.len {
+      let a = &self.proxies[self.sorted[i] as usize];
+      for j in i + 1..self.len {
+        let b = &self.proxies[self.sorted[j] as usize];
+        //sorted by min.x, so nothing further along can overlap either
+        if b.min.x > a.max.x {
+          break;
+        }
+        if overlaps_yz(a, b.min, b.max) && a.filter.collides(&b.filter) {
+          visit(a.id, b.id);
+        }
+      }
+    }
+  }
+
+  /// Calls `visit` with every id whose box overlaps the given one.
+  pub fn query(&self, min: Vector3<f32>, max: Vector3<f32>, mut visit: impl FnMut(SaltyId)) {
+    for i in 0..self.len {
+      let proxy = &self.proxies[self.sorted[i] as usize];
+      if proxy.min.x > max.x {
+        break;
+      }
+      if proxy.max.x >= min.x && overlaps_yz(proxy, min, max) {
+        visit(proxy.id);
+      }
+    }
+  }
+
+  /// Calls `visit` with every id and its box, in no particular order.
+  pub fn boxes(&self, mut visit: impl FnMut(SaltyId, Vector3<f32>, Vector3<f32>)) {
+    for i in 0..self.len {
+      let proxy = &self.proxies[self.sorted[i] as usize];
+      visit(proxy.id, proxy.min, proxy.max);
+    }
+  }
+}
+
+fn overlaps_yz(proxy: &Proxy, min: Vector3<f32>, max: Vector3<f32>) -> bool {
+  proxy.min.y <= max.y && proxy.max.y >= min.y &&
+  proxy.min.z <= max.z && proxy.max.z >= min.z
+}
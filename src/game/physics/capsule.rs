@@ -0,0 +1,43 @@
+use cgmath::{prelude::*, Quaternion, Vector3};
+
+use super::convex::Convex;
+
+/// A segment along the local Z axis inflated by `radius`. The total height is
+/// `2 * (half_height + radius)`.
+#[derive(Copy, Clone, Debug)]
+pub struct Capsule {
+  pub radius: f32,
+  pub half_height: f32,
+  pub position: Vector3<f32>,
+  pub rotation: Quaternion<f32>,
+  pub velocity: Vector3<f32>,
+}
+
+impl Capsule {
+  pub fn axis(&self) -> Vector3<f32> {
+    self.rotation.rotate_vector(Vector3::unit_z())
+  }
+}
+
+impl Convex for Capsule {
+  fn core_support(&self, direction: Vector3<f32>) -> Vector3<f32> {
+    let axis = self.axis() * self.half_height;
+    if axis.dot(direction) >= 0.0 {
+      self.position + axis
+    } else {
+      self.position - axis
+    }
+  }
+
+  fn margin(&self) -> f32 {
+    self.radius
+  }
+
+  fn center(&self) -> Vector3<f32> {
+    self.position
+  }
+
+  fn velocity(&self) -> Vector3<f32> {
+    self.velocity
+  }
+}
@@ -1,10 +1,162 @@
+use std::f32::consts::PI;
+use cgmath::{prelude::*, Quaternion, Vector3};
+
+use super::convex::{self, Convex};
+use super::{Aabb, Capsule, FixedPlane, OrientedBox, Sphere};
+
+/// When and where two moving shapes first touch. The time of contact is in the same units of
+/// time as the shapes' velocities and is zero when they already overlap, in which case `depth`
+/// says by how much.
+#[derive(Copy, Clone, Debug)]
+pub struct Contact {
+  pub toc: f32,
+  // Unit length, pointing from the other shape towards this one
+  pub normal: Vector3<f32>,
+  pub point: Vector3<f32>,
+  pub depth: f32,
+}
+
+impl Contact {
+  /// The same contact from the other shape's point of view.
+  pub fn flipped(self) -> Contact {
+    Contact {
+      normal: -self.normal,
+      ..self
+    }
+  }
+}
+
 pub trait CollideWith<O> {
-  fn toc(&self, other: &O) -> Option<f32>;
-  // fn resolve(&self, other: &O);
+  fn contact(&self, other: &O) -> Option<Contact>;
+
+  fn toc(&self, other: &O) -> Option<f32> {
+    self.contact(other).map(|contact| contact.toc)
+  }
+
+  fn intersects(&self, other: &O) -> bool {
+    self.contact(other).is_some_and(|contact| contact.toc == 0.0)
+  }
+}
+
+//every pair of convex shapes collides the same way, so one impl covers them all in both orders
+impl<A: Convex, B: Convex> CollideWith<B> for A {
+  fn contact(&self, other: &B) -> Option<Contact> {
+    convex::sweep(self, other)
+  }
+}
+
+impl<A: Convex> CollideWith<FixedPlane> for A {
+  fn contact(&self, other: &FixedPlane) -> Option<Contact> {
+    convex::sweep_plane(self, other)
+  }
+}
+
+impl<B: Convex> CollideWith<B> for FixedPlane {
+  fn contact(&self, other: &B) -> Option<Contact> {
+    convex::sweep_plane(other, self).map(Contact::flipped)
+  }
+}
+
+/// Any collision shape, for when the kinds of shape involved are only known at runtime.
+#[derive(Copy, Clone, Debug)]
+pub enum Collider {
+  Sphere(Sphere),
+  Aabb(Aabb),
+  OrientedBox(OrientedBox),
+  Capsule(Capsule),
+  Plane(FixedPlane),
 }
 
-// impl <O, S> CollideWith<S> for O where S: CollideWith<O> {
-//   fn toc(&self, other: &S) -> Option<f32> {
-//     other.toc(self)
-//   }
-// }
\ No newline at end of file
+impl Collider {
+  pub fn as_convex(&self) -> Option<&dyn Convex> {
+    match self {
+      Collider::Sphere(sphere) => Some(sphere),
+      Collider::Aabb(aabb) => Some(aabb),
+      Collider::OrientedBox(obb) => Some(obb),
+      Collider::Capsule(capsule) => Some(capsule),
+      Collider::Plane(_) => None,
+    }
+  }
+
+  pub fn position(&self) -> Vector3<f32> {
+    match self {
+      Collider::Sphere(sphere) => sphere.position,
+      Collider::Aabb(aabb) => aabb.position,
+      Collider::OrientedBox(obb) => obb.position,
+      Collider::Capsule(capsule) => capsule.position,
+      Collider::Plane(plane) => plane.normal * -plane.d,
+    }
+  }
+
+  pub fn rotation(&self) -> Quaternion<f32> {
+    match self {
+      Collider::OrientedBox(obb) => obb.rotation,
+      Collider::Capsule(capsule) => capsule.rotation,
+      _ => Quaternion::one(),
+    }
+  }
+
+  /// Principal moments of inertia about the shape's local axes for a solid of the given mass.
+  /// Planes never move and have none.
+  pub fn inertia(&self, mass: f32) -> Vector3<f32> {
+    match self {
+      Collider::Sphere(sphere) => Vector3::from_value(0.4 * mass * sphere.radius * sphere.radius),
+      Collider::Aabb(aabb) => box_inertia(aabb.half_extents, mass),
+      Collider::OrientedBox(obb) => box_inertia(obb.half_extents, mass),
+      Collider::Capsule(capsule) => {
+        //a cylinder plus a sphere split between its ends, sharing the mass by volume
+        let (r, h) = (capsule.radius, capsule.half_height * 2.0);
+        let cylinder_volume = PI * r * r * h;
+        let sphere_volume = 4.0 / 3.0 * PI * r * r * r;
+        let cylinder = mass * cylinder_volume / (cylinder_volume + sphere_volume);
+        let sphere = mass - cylinder;
+        let axial = cylinder * r * r / 2.0 + sphere * 0.4 * r * r;
+        let across = cylinder * (h * h / 12.0 + r * r / 4.0) + sphere * (0.4 * r * r + h * h / 4.0 + 3.0 * h * r / 8.0);
+        Vector3::new(across, across, axial)
+      },
+      Collider::Plane(_) => Vector3::zero(),
+    }
+  }
+
+  /// Space taken up by the shape. Planes are infinite, but have none for lack of anything better.
+  pub fn volume(&self) -> f32 {
+    match self {
+      Collider::Sphere(sphere) => 4.0 / 3.0 * PI * sphere.radius.powi(3),
+      Collider::Aabb(aabb) => 8.0 * aabb.half_extents.x * aabb.half_extents.y * aabb.half_extents.z,
+      Collider::OrientedBox(obb) => 8.0 * obb.half_extents.x * obb.half_extents.y * obb.half_extents.z,
+      Collider::Capsule(capsule) => {
+        let r = capsule.radius;
+        PI * r * r * capsule.half_height * 2.0 + 4.0 / 3.0 * PI * r * r * r
+      },
+      Collider::Plane(_) => 0.0,
+    }
+  }
+
+  /// The same shape moved to a new pose. Planes are fixed and stay where they are.
+  pub fn placed(&self, position: Vector3<f32>, rotation: Quaternion<f32>, velocity: Vector3<f32>) -> Collider {
+    match *self {
+      Collider::Sphere(sphere) => Collider::Sphere(Sphere { position, velocity, ..sphere }),
+      Collider::Aabb(aabb) => Collider::Aabb(Aabb { position, velocity, ..aabb }),
+      Collider::OrientedBox(obb) => Collider::OrientedBox(OrientedBox { position, rotation, velocity, ..obb }),
+      Collider::Capsule(capsule) => Collider::Capsule(Capsule { position, rotation, velocity, ..capsule }),
+      Collider::Plane(plane) => Collider::Plane(plane),
+    }
+  }
+}
+
+fn box_inertia(half_extents: Vector3<f32>, mass: f32) -> Vector3<f32> {
+  let size = half_extents * 2.0;
+  let (x2, y2, z2) = (size.x * size.x, size.y * size.y, size.z * size.z);
+  Vector3::new(y2 + z2, x2 + z2, x2 + y2) * (mass / 12.0)
+}
+
+impl CollideWith<Collider> for Collider {
+  fn contact(&self, other: &Collider) -> Option<Contact> {
+    match (self, other) {
+      (Collider::Plane(_), Collider::Plane(_)) => None,
+      (Collider::Plane(plane), _) => convex::sweep_plane(other.as_convex()?, plane).map(Contact::flipped),
+      (_, Collider::Plane(plane)) => convex::sweep_plane(self.as_convex()?, plane),
+      _ => convex::sweep(self.as_convex()?, other.as_convex()?),
+    }
+  }
+}
@@ -0,0 +1,25 @@
+// Layer of bodies which don't say otherwise
+pub const DEFAULT_LAYER: u32 = 1;
+
+/// Which collision layers a body is on, as bit flags, and which it collides with. Two bodies only
+/// collide if each is on a layer the other's mask includes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CollisionFilter {
+  pub layer: u32,
+  pub mask: u32,
+}
+
+impl Default for CollisionFilter {
+  fn default() -> CollisionFilter {
+    CollisionFilter {
+      layer: DEFAULT_LAYER,
+      mask: u32::MAX,
+    }
+  }
+}
+
+impl CollisionFilter {
+  pub fn collides(&self, other: &CollisionFilter) -> bool {
+    self.layer & other.mask != 0 && other.layer & self.mask != 0
+  }
+}
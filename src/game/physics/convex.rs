@@ -0,0 +1,122 @@
+use cgmath::{prelude::*, Vector3};
+
+use super::collider::Contact;
+use super::fixed_plane::FixedPlane;
+use super::gjk;
+
+// Shapes closer than this are considered touching
+const CONTACT_TOLERANCE: f32 = 1e-4;
+const MAX_ADVANCEMENT_STEPS: usize = 64;
+
+/// A convex shape described by its support mapping, which is all GJK needs to collide any pair of
+/// them. Each shape is split into a core and a margin: the full shape is the core inflated by the
+/// margin in every direction. Spheres and capsules are a point and a segment with their radius as
+/// margin, which keeps their distances exact and their contacts stable.
+pub trait Convex {
+  /// The point of the core furthest in the given direction, which need not be normalized.
+  fn core_support(&self, direction: Vector3<f32>) -> Vector3<f32>;
+
+  fn margin(&self) -> f32 {
+    0.0
+  }
+
+  fn center(&self) -> Vector3<f32>;
+
+  fn velocity(&self) -> Vector3<f32>;
+
+  /// The point of the full shape furthest in the given direction.
+  fn support(&self, direction: Vector3<f32>) -> Vector3<f32> {
+    let core = self.core_support(direction);
+    let margin = self.margin();
+    if margin > 0.0 && direction.magnitude2() > 0.0 {
+      core + direction.normalize() * margin
+    } else {
+      core
+    }
+  }
+
+  /// Minimum and maximum corners of the axis-aligned box around the shape.
+  fn bounds(&self) -> (Vector3<f32>, Vector3<f32>) {
+    let min = Vector3::new(
+      self.support(-Vector3::unit_x()).x,
+      self.support(-Vector3::unit_y()).y,
+      self.support(-Vector3::unit_z()).z,
+    );
+    let max = Vector3::new(
+      self.support(Vector3::unit_x()).x,
+      self.support(Vector3::unit_y()).y,
+      self.support(Vector3::unit_z()).z,
+    );
+    (min, max)
+  }
+
+  /// Bounds of everywhere the shape passes through while moving for `duration`.
+  fn swept_bounds(&self, duration: f32) -> (Vector3<f32>, Vector3<f32>) {
+    let (min, max) = self.bounds();
+    let offset = self.velocity() * duration;
+    (
+      Vector3::new(min.x.min(min.x + offset.x), min.y.min(min.y + offset.y), min.z.min(min.z + offset.z)),
+      Vector3::new(max.x.max(max.x + offset.x), max.y.max(max.y + offset.y), max.z.max(max.z + offset.z)),
+    )
+  }
+}
+
+/// Sweeps two shapes along their velocities by conservative advancement: each step moves them by
+/// as much time as they could take to close their current gap, which can never overshoot.
+pub fn sweep<A: Convex + ?Sized, B: Convex + ?Sized>(a: &A, b: &B) -> Option<Contact> {
+  let relative_velocity = a.velocity() - b.velocity();
+  let mut toc = 0.0;
+  for _ in 0..MAX_ADVANCEMENT_STEPS {
+    let separation = gjk::separation(a, a.velocity() * toc, b, b.velocity() * toc);
+    if separation.distance <= CONTACT_TOLERANCE {
+      //touching flat faces, like a ray meeting a triangle, can come back facing either way, but
+      //anything reached by moving was approached from the front
+      let normal = if toc > 0.0 && relative_velocity.dot(separation.normal) > 0.0 {
+        -separation.normal
+      } else {
+        separation.normal
+      };
+      return Some(Contact {
+        toc,
+        normal,
+        point: separation.point,
+        depth: (-separation.distance).max(0.0),
+      });
+    }
+
+    //parallel or moving away
+    let approach_speed = -relative_velocity.dot(separation.normal);
+    if approach_speed <= 0.0 {
+      return None;
+    }
+    toc += separation.distance / approach_speed;
+  }
+  None
+}
+
+pub fn sweep_plane<A: Convex + ?Sized>(shape: &A, plane: &FixedPlane) -> Option<Contact> {
+  let deepest = shape.support(-plane.normal);
+  let distance = plane.distance_to(deepest);
+  //the plane is solid behind, so a shape past it is penetrating rather than missing
+  if distance <= 0.0 {
+    return Some(Contact {
+      toc: 0.0,
+      normal: plane.normal,
+      point: deepest - plane.normal * distance,
+      depth: -distance,
+    });
+  }
+
+  let approach_speed = -shape.velocity().dot(plane.normal);
+  if approach_speed <= 0.0 {
+    return None;
+  }
+
+  let toc = distance / approach_speed;
+  Some(Contact {
+    toc,
+    normal: plane.normal,
+    point: deepest + shape.velocity() * toc,
+    depth: 0.0,
+  })
+}
@@ -1,15 +1,15 @@
-use cgmath::Vector3;
-
-use super::collider::CollideWith;
-use super::sphere::Sphere;
+use cgmath::{prelude::*, Vector3};
 
+/// An immovable plane satisfying `normal . p + d = 0`. Everything behind it is considered solid.
+#[derive(Copy, Clone, Debug)]
 pub struct FixedPlane {
   pub normal: Vector3<f32>,
   pub d: f32,
 }
 
-impl CollideWith<Sphere> for FixedPlane {
-  fn toc(&self, other: &Sphere) -> Option<f32> {
-      other.toc(self)
+impl FixedPlane {
+  /// Signed distance from the plane, positive in front of it.
+  pub fn distance_to(&self, point: Vector3<f32>) -> f32 {
+    self.normal.dot(point) + self.d
   }
-}
\ No newline at end of file
+}
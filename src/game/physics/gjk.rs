@@ -0,0 +1,455 @@
+use cgmath::{prelude::*, Vector3};
+
+use super::convex::Convex;
+
+const MAX_ITERATIONS: usize = 64;
+const EPSILON: f32 = 1e-6;
+// GJK stops once an iteration improves the squared distance by less than this fraction
+const RELATIVE_TOLERANCE: f32 = 1e-5;
+const EPA_TOLERANCE: f32 = 1e-4;
+
+/// The closest features of two convex shapes, or the shallowest way out if they overlap.
+#[derive(Copy, Clone, Debug)]
+pub struct Separation {
+  // Negative when the shapes overlap
+  pub distance: f32,
+  // Unit length, pointing from b towards a
+  pub normal: Vector3<f32>,
+  // Midway between the two shapes' surfaces
+  pub point: Vector3<f32>,
+}
+
+/// A point on the Minkowski difference of the cores of a and b, remembering which points of each
+/// produced it so that closest points on the shapes themselves can be recovered.
+#[derive(Copy, Clone, Debug)]
+struct SupportPoint {
+  w: Vector3<f32>,
+  a: Vector3<f32>,
+  b: Vector3<f32>,
+}
+
+struct MinkowskiDifference<'s, A: Convex + ?Sized, B: Convex + ?Sized> {
+  a: &'s A,
+  a_offset: Vector3<f32>,
+  b: &'s B,
+  b_offset: Vector3<f32>,
+}
+
+impl<'s, A: Convex + ?Sized, B: Convex + ?Sized> MinkowskiDifference<'s, A, B> {
+  fn support(&self, direction: Vector3<f32>) -> SupportPoint {
+    let a = self.a.core_support(direction) + self.a_offset;
+    let b = self.b.core_support(-direction) + self.b_offset;
+    SupportPoint {
+      w: a - b,
+      a,
+      b,
+    }
+  }
+}
+
+#[derive(Copy, Clone)]
+struct Simplex {
+  points: [SupportPoint; 4],
+  // Barycentric coordinates of the point closest to the origin
+  lambdas: [f32; 4],
+  len: usize,
+}
+
+impl Simplex {
+  fn new(point: SupportPoint) -> Simplex {
+    Simplex {
+      points: [point; 4],
+      lambdas: [1.0, 0.0, 0.0, 0.0],
+      len: 1,
+    }
+  }
+
+  fn from_weighted(weighted: &[(SupportPoint, f32)]) -> Simplex {
+    let mut simplex = Simplex::new(weighted[0].0);
+    simplex.len = weighted.len();
+    for (i, (point, lambda)) in weighted.iter().enumerate() {
+      simplex.points[i] = *point;
+      simplex.lambdas[i] = *lambda;
+    }
+    simplex
+  }
+
+  fn push(&mut self, point: SupportPoint) {
+    self.points[self.len] = point;
+    self.len += 1;
+  }
+
+  fn closest(&self) -> Vector3<f32> {
+    self.weighted_sum(|p| p.w)
+  }
+
+  fn weighted_sum(&self, f: impl Fn(&SupportPoint) -> Vector3<f32>) -> Vector3<f32> {
+    (0..self.len).fold(Vector3::zero(), |sum, i| sum + f(&self.points[i]) * self.lambdas[i])
+  }
+
+  /// Reduces the simplex to the smallest subset containing the point closest to the origin.
+  /// Returns false if the origin is enclosed by a tetrahedron.
+  fn reduce(&mut self) -> bool {
+    match self.len {
+      1 => {
+        self.lambdas[0] = 1.0;
+        true
+      },
+      2 => {
+        *self = closest_on_segment(self.points[0], self.points[1]);
+        true
+      },
+      3 => {
+        *self = closest_on_triangle(self.points[0], self.points[1], self.points[2]);
+        true
+      },
+      _ => match closest_on_tetrahedron(&self.points) {
+        Some(reduced) => {
+          *self = reduced;
+          true
+        },
+        None => false,
+      },
+    }
+  }
+}
+
+fn closest_on_segment(a: SupportPoint, b: SupportPoint) -> Simplex {
+  let ab = b.w - a.w;
+  let length2 = ab.magnitude2();
+  if length2 < EPSILON {
+    return Simplex::new(a);
+  }
+  let t = -a.w.dot(ab) / length2;
+  if t <= 0.0 {
+    Simplex::new(a)
+  } else if t >= 1.0 {
+    Simplex::new(b)
+  } else {
+    Simplex::from_weighted(&[(a, 1.0 - t), (b, t)])
+  }
+}
+
+//voronoi region tests from Ericson's Real-Time Collision Detection, for the origin as the query point
+fn closest_on_triangle(a: SupportPoint, b: SupportPoint, c: SupportPoint) -> Simplex {
+  let ab = b.w - a.w;
+  let ac = c.w - a.w;
+  let d1 = ab.dot(-a.w);
+  let d2 = ac.dot(-a.w);
+  if d1 <= 0.0 && d2 <= 0.0 {
+    return Simplex::new(a);
+  }
+
+  let d3 = ab.dot(-b.w);
+  let d4 = ac.dot(-b.w);
+  if d3 >= 0.0 && d4 <= d3 {
+    return Simplex::new(b);
+  }
+
+  let vc = d1 * d4 - d3 * d2;
+  if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+    let v = d1 / (d1 - d3);
+    return Simplex::from_weighted(&[(a, 1.0 - v), (b, v)]);
+  }
+
+  let d5 = ab.dot(-c.w);
+  let d6 = ac.dot(-c.w);
+  if d6 >= 0.0 && d5 <= d6 {
+    return Simplex::new(c);
+  }
+
+  let vb = d5 * d2 - d1 * d6;
+  if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+    let w = d2 / (d2 - d6);
+    return Simplex::from_weighted(&[(a, 1.0 - w), (c, w)]);
+  }
+
+  let va = d3 * d6 - d5 * d4;
+  if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+    let w = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+    return Simplex::from_weighted(&[(b, 1.0 - w), (c, w)]);
+  }
+
+  let denom = va + vb + vc;
+  if denom.abs() < EPSILON {
+    //degenerate triangle, fall back to its longest edge
+    return closest_on_segment(a, if ac.magnitude2() > ab.magnitude2() { c } else { b });
+  }
+  let v = vb / denom;
+  let w = vc / denom;
+  Simplex::from_weighted(&[(a, 1.0 - v - w), (b, v), (c, w)])
+}
+
+fn closest_on_tetrahedron(points: &[SupportPoint; 4]) -> Option<Simplex> {
+  let [a, b, c, d] = *points;
+  //each face with the vertex opposite it
+  let faces = [(a, b, c, d), (a, c, d, b), (a, d, b, c), (b, d, c, a)];
+  let mut best: Option<(f32, Simplex)> = None;
+  for (p, q, r, opposite) in faces.iter() {
+    let normal = (q.w - p.w).cross(r.w - p.w);
+    let origin_side = normal.dot(-p.w);
+    let opposite_side = normal.dot(opposite.w - p.w);
+    //a flat tetrahedron can't enclose anything, so every face is a candidate
+    if opposite_side.abs() > EPSILON && origin_side * opposite_side >= 0.0 {
+      continue;
+    }
+    let candidate = closest_on_triangle(*p, *q, *r);
+    let distance2 = candidate.closest().magnitude2();
+    if best.is_none_or(|(best_distance2, _)| distance2 < best_distance2) {
+      best = Some((distance2, candidate));
+    }
+  }
+  best.map(|(_, simplex)| simplex)
+}
+
+enum GjkResult {
+  Separated(Simplex),
+  Overlapping(Simplex),
+}
+
+fn gjk<A: Convex + ?Sized, B: Convex + ?Sized>(shapes: &MinkowskiDifference<A, B>) -> GjkResult {
+  let mut direction = (shapes.a.center() + shapes.a_offset) - (shapes.b.center() + shapes.b_offset);
+  if direction.magnitude2() < EPSILON {
+    direction = Vector3::unit_x();
+  }
+  let mut simplex = Simplex::new(shapes.support(-direction));
+  let mut v = simplex.closest();
+
+  for _ in 0..MAX_ITERATIONS {
+    let v2 = v.magnitude2();
+    if v2 < EPSILON * EPSILON {
+      return GjkResult::Overlapping(simplex);
+    }
+    let point = shapes.support(-v);
+    //no support point gets meaningfully closer, so v is the closest point
+    if v2 - v.dot(point.w) <= RELATIVE_TOLERANCE * v2 {
+      break;
+    }
+    let prev = simplex;
+    simplex.push(point);
+    if !simplex.reduce() {
+      return GjkResult::Overlapping(simplex);
+    }
+    let next_v = simplex.closest();
+    if next_v.magnitude2() >= v2 {
+      //numerical trouble. A support point past the origin means the origin is within rounding of
+      //the simplex, which happens when a small shape sinks into a much larger one
+      if v.dot(point.w) < 0.0 {
+        let mut enclosing = prev;
+        enclosing.push(point);
+        return GjkResult::Overlapping(enclosing);
+      }
+      //otherwise keep the best simplex found so far
+      simplex = prev;
+      break;
+    }
+    v = next_v;
+  }
+  GjkResult::Separated(simplex)
+}
+
+/// How deeply the cores overlap, the direction to move a to separate them, and the deepest point
+/// of each.
+struct Penetration {
+  depth: f32,
+  normal: Vector3<f32>,
+  a: Vector3<f32>,
+  b: Vector3<f32>,
+}
+
+#[derive(Copy, Clone)]
+struct Face {
+  indices: [usize; 3],
+  normal: Vector3<f32>,
+  distance: f32,
+}
+
+/// Grows a simplex which contains the origin into a tetrahedron, so EPA has a volume to expand.
+/// If the Minkowski difference turns out to be flat, such as for two crossing segments, returns
+/// a direction perpendicular to it instead.
+fn blow_up<A: Convex + ?Sized, B: Convex + ?Sized>(shapes: &MinkowskiDifference<A, B>, simplex: &Simplex) -> Result<Vec<SupportPoint>, Vector3<f32>> {
+  let mut points: Vec<SupportPoint> = simplex.points[..simplex.len].to_vec();
+  let axes = [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()];
+
+  if points.len() == 1 {
+    for axis in axes.iter().flat_map(|axis| [*axis, -*axis]) {
+      let point = shapes.support(axis);
+      if (point.w - points[0].w).magnitude2() > EPSILON {
+        points.push(point);
+        break;
+      }
+    }
+    if points.len() == 1 {
+      return Err(Vector3::unit_z());
+    }
+  }
+  if points.len() == 2 {
+    let line = (points[1].w - points[0].w).normalize();
+    //start from whichever axis is least parallel to the line
+    let least_parallel = axes.iter().copied().min_by(|p, q| {
+      line.dot(*p).abs().total_cmp(&line.dot(*q).abs())
+    }).unwrap();
+    let mut perpendicular = line.cross(least_parallel).normalize();
+    for _ in 0..6 {
+      let point = shapes.support(perpendicular);
+      if (point.w - points[0].w).cross(line).magnitude2() > EPSILON {
+        points.push(point);
+        break;
+      }
+      perpendicular = cgmath::Quaternion::from_axis_angle(line, cgmath::Rad(std::f32::consts::FRAC_PI_3)).rotate_vector(perpendicular);
+    }
+    if points.len() == 2 {
+      return Err(perpendicular);
+    }
+  }
+  if points.len() == 3 {
+    let normal = (points[1].w - points[0].w).cross(points[2].w - points[0].w);
+    for direction in [normal, -normal] {
+      let point = shapes.support(direction);
+      if normal.dot(point.w - points[0].w).abs() > EPSILON {
+        points.push(point);
+        break;
+      }
+    }
+    if points.len() == 3 {
+      return Err(normal.normalize());
+    }
+  }
+  Ok(points)
+}
+
+fn make_face(points: &[SupportPoint], indices: [usize; 3], centroid: Vector3<f32>) -> Option<Face> {
+  let [i, j, k] = indices;
+  let normal = (points[j].w - points[i].w).cross(points[k].w - points[i].w);
+  if normal.magnitude2() < EPSILON * EPSILON {
+    return None;
+  }
+  let mut normal = normal.normalize();
+  let mut indices = indices;
+  //keep faces wound outwards, using the centroid since the origin may lie on the surface
+  if normal.dot(points[i].w - centroid) < 0.0 {
+    normal = -normal;
+    indices = [i, k, j];
+  }
+  Some(Face {
+    indices,
+    normal,
+    distance: normal.dot(points[i].w),
+  })
+}
+
+/// Expanding polytope algorithm: finds the face of the Minkowski difference closest to the origin
+/// and so the shallowest penetration.
+fn epa<A: Convex + ?Sized, B: Convex + ?Sized>(shapes: &MinkowskiDifference<A, B>, simplex: &Simplex) -> Result<Penetration, Vector3<f32>> {
+  let mut points = blow_up(shapes, simplex)?;
+  let centroid = points.iter().fold(Vector3::zero(), |sum, p| sum + p.w) / 4.0;
+  let mut faces: Vec<Face> = [[0, 1, 2], [0, 3, 1], [0, 2, 3], [1, 3, 2]].iter()
+    .filter_map(|indices| make_face(&points, *indices, centroid))
+    .collect();
+
+  let closest_face = |faces: &[Face]| *faces.iter().min_by(|f, g| f.distance.total_cmp(&g.distance)).unwrap();
+  let resolve = |points: &[SupportPoint], face: Face| {
+    let [i, j, k] = face.indices;
+    let lambdas = barycentric(face.normal * face.distance, points[i].w, points[j].w, points[k].w);
+    Penetration {
+      depth: face.distance,
+      normal: -face.normal,
+      a: points[i].a * lambdas[0] + points[j].a * lambdas[1] + points[k].a * lambdas[2],
+      b: points[i].b * lambdas[0] + points[j].b * lambdas[1] + points[k].b * lambdas[2],
+    }
+  };
+  if faces.is_empty() {
+    return Err(Vector3::unit_z());
+  }
+
+  for _ in 0..MAX_ITERATIONS {
+    let closest = closest_face(&faces);
+    let point = shapes.support(closest.normal);
+    let improvement = point.w.dot(closest.normal) - closest.distance;
+    if improvement < EPA_TOLERANCE {
+      return Ok(resolve(&points, closest));
+    }
+
+    let new_index = points.len();
+    points.push(point);
+
+    //remove every face the new point can see, remembering the boundary of the hole they leave
+    let mut horizon: Vec<(usize, usize)> = Vec::new();
+    faces.retain(|face| {
+      if face.normal.dot(point.w - points[face.indices[0]].w) <= 0.0 {
+        return true;
+      }
+      let [i, j, k] = face.indices;
+      for edge in [(i, j), (j, k), (k, i)] {
+        if let Some(shared) = horizon.iter().position(|&(p, q)| p == edge.1 && q == edge.0) {
+          horizon.remove(shared);
+        } else {
+          horizon.push(edge);
+        }
+      }
+      false
+    });
+    let centroid = points.iter().fold(Vector3::zero(), |sum, p| sum + p.w) / points.len() as f32;
+    faces.extend(horizon.iter().filter_map(|&(i, j)| make_face(&points, [i, j, new_index], centroid)));
+    if faces.is_empty() {
+      return Err(closest.normal);
+    }
+  }
+  //out of iterations, but the closest face so far is still a reasonable answer
+  Ok(resolve(&points, closest_face(&faces)))
+}
+
+fn barycentric(p: Vector3<f32>, a: Vector3<f32>, b: Vector3<f32>, c: Vector3<f32>) -> [f32; 3] {
+  let v0 = b - a;
+  let v1 = c - a;
+  let v2 = p - a;
+  let d00 = v0.dot(v0);
+  let d01 = v0.dot(v1);
+  let d11 = v1.dot(v1);
+  let d20 = v2.dot(v0);
+  let d21 = v2.dot(v1);
+  let denom = d00 * d11 - d01 * d01;
+  if denom.abs() < EPSILON {
+    return [1.0, 0.0, 0.0];
+  }
+  let v = (d11 * d20 - d01 * d21) / denom;
+  let w = (d00 * d21 - d01 * d20) / denom;
+  [1.0 - v - w, v, w]
+}
+
+/// Finds how far apart a and b are after offsetting them. Distances between the cores come from
+/// GJK and have the margins subtracted; only when the cores themselves overlap is EPA needed.
+pub fn separation<A: Convex + ?Sized, B: Convex + ?Sized>(a: &A, a_offset: Vector3<f32>, b: &B, b_offset: Vector3<f32>) -> Separation {
+  let shapes = MinkowskiDifference {
+    a,
+    a_offset,
+    b,
+    b_offset,
+  };
+  let margins = a.margin() + b.margin();
+
+  let (core_distance, normal, core_a, core_b) = match gjk(&shapes) {
+    GjkResult::Separated(simplex) => {
+      let v = simplex.closest();
+      let distance = v.magnitude();
+      (distance, v / distance, simplex.weighted_sum(|p| p.a), simplex.weighted_sum(|p| p.b))
+    },
+    GjkResult::Overlapping(simplex) => match epa(&shapes, &simplex) {
+      Ok(penetration) => (-penetration.depth, penetration.normal, penetration.a, penetration.b),
+      //the cores touch without any volume of overlap, so only the margins are penetrating
+      Err(flat_normal) => {
+        let towards_a = (a.center() + a_offset) - (b.center() + b_offset);
+        let normal = if flat_normal.dot(towards_a) < 0.0 { -flat_normal } else { flat_normal };
+        (0.0, normal, simplex.weighted_sum(|p| p.a), simplex.weighted_sum(|p| p.b))
+      },
+    },
+  };
+
+  let surface_a = core_a - normal * a.margin();
+  let surface_b = core_b + normal * b.margin();
+  Separation {
+    distance: core_distance - margins,
+    normal,
+    point: (surface_a + surface_b) / 2.0,
+  }
+}
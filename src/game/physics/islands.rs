@@ -0,0 +1,40 @@
+use super::solver::{Body, BodyContact};
+
+/// Groups of bodies which touch each other, directly or through other bodies, so that they can
+/// be put to sleep and woken up together. Static bodies hold up whatever rests on them without
+/// being part of its island, which keeps a whole level from becoming one island.
+pub struct Islands {
+  // Union-find forest over body indices
+  parent: Vec<usize>,
+}
+
+impl Islands {
+  pub fn new(bodies: &[Body], contacts: &[BodyContact]) -> Islands {
+    let mut islands = Islands {
+      parent: (0..bodies.len()).collect(),
+    };
+    for c in contacts {
+      if bodies[c.a].inverse_mass > 0.0 && bodies[c.b].inverse_mass > 0.0 {
+        islands.join(c.a, c.b);
+      }
+    }
+    islands
+  }
+
+  /// The index of a body standing in for its whole island.
+  pub fn root(&mut self, mut body: usize) -> usize {
+    while self.parent[body] != body {
+      //path halving keeps later lookups short
+      self.parent[body] = self.parent[self.parent[body]];
+      body = self.parent[body];
+    }
+    body
+  }
+
+  pub fn join(&mut self, a: usize, b: usize) {
+    let (a, b) = (self.root(a), self.root(b));
+    if a != b {
+      self.parent[b] = a;
+    }
+  }
+}
@@ -0,0 +1,224 @@
+use cgmath::{prelude::*, Matrix3, Quaternion, Vector3};
+
+use super::solver::Body;
+
+const JOINT_ITERATIONS: usize = 8;
+// Fraction of a joint's drift corrected per step; more than this and joints start to shake
+const JOINT_CORRECTION_FACTOR: f32 = 0.2;
+const EPSILON: f32 = 1e-6;
+
+/// How a joint limits its bodies' movement relative to each other.
+#[derive(Copy, Clone, Debug)]
+pub enum JointKind {
+  // The anchors stay together but the bodies turn freely
+  BallSocket,
+  // The anchors stay together and the bodies only turn about the axes, which stay aligned
+  Hinge {
+    axis_a: Vector3<f32>,
+    axis_b: Vector3<f32>,
+  },
+  // Like a rope, the anchors can come closer together but never further apart than `length`
+  Distance {
+    length: f32,
+  },
+  // The bodies move as one, keeping a's rotation relative to b's
+  Fixed {
+    rotation: Quaternion<f32>,
+  },
+}
+
+/// A joint between two bodies, or a body and the world. Anchors and axes are in each body's own
+/// space, or the world's when there's no body.
+#[derive(Copy, Clone, Debug)]
+pub struct Joint {
+  pub kind: JointKind,
+  pub anchor_a: Vector3<f32>,
+  pub anchor_b: Vector3<f32>,
+}
+
+/// A joint between bodies `a` and `b`, as indices into the solved slice, or `a` and the world
+/// when `b` is `None`.
+#[derive(Copy, Clone, Debug)]
+pub struct BodyJoint {
+  pub a: usize,
+  pub b: Option<usize>,
+  pub joint: Joint,
+  // Pulling impulse accumulated by distance joints, which can't push
+  impulse: f32,
+}
+
+impl BodyJoint {
+  pub fn new(a: usize, b: Option<usize>, joint: Joint) -> BodyJoint {
+    BodyJoint {
+      a,
+      b,
+      joint,
+      impulse: 0.0,
+    }
+  }
+}
+
+/// Where one end of a joint is and how that point is moving.
+#[derive(Copy, Clone)]
+struct End {
+  index: Option<usize>,
+  point: Vector3<f32>,
+  // From the body's center to the anchor
+  offset: Vector3<f32>,
+  rotation: Quaternion<f32>,
+}
+
+impl End {
+  fn new(bodies: &[Body], index: Option<usize>, anchor: Vector3<f32>) -> End {
+    match index {
+      Some(i) => {
+        let rotation = bodies[i].rotation;
+        let offset = rotation.rotate_vector(anchor);
+        End {
+          index,
+          point: bodies[i].shape.position() + offset,
+          offset,
+          rotation,
+        }
+      },
+      None => End {
+        index,
+        point: anchor,
+        offset: Vector3::zero(),
+        rotation: Quaternion::one(),
+      },
+    }
+  }
+
+  fn velocity(&self, bodies: &[Body]) -> Vector3<f32> {
+    self.index.map_or(Vector3::zero(), |i| bodies[i].velocity + bodies[i].angular_velocity.cross(self.offset))
+  }
+
+  fn angular_velocity(&self, bodies: &[Body]) -> Vector3<f32> {
+    self.index.map_or(Vector3::zero(), |i| bodies[i].angular_velocity)
+  }
+
+  /// Change in the anchor's velocity from a unit impulse there along each axis.
+  fn point_mass(&self, bodies: &[Body]) -> Matrix3<f32> {
+    let body = match self.index {
+      Some(i) => &bodies[i],
+      None => return Matrix3::zero(),
+    };
+    let column = |axis: Vector3<f32>| axis * body.inverse_mass + body.turn_by(self.offset.cross(axis)).cross(self.offset);
+    Matrix3::from_cols(column(Vector3::unit_x()), column(Vector3::unit_y()), column(Vector3::unit_z()))
+  }
+
+  /// Change in angular velocity from a unit angular impulse about each axis.
+  fn turn_mass(&self, bodies: &[Body]) -> Matrix3<f32> {
+    let body = match self.index {
+      Some(i) => &bodies[i],
+      None => return Matrix3::zero(),
+    };
+    Matrix3::from_cols(body.turn_by(Vector3::unit_x()), body.turn_by(Vector3::unit_y()), body.turn_by(Vector3::unit_z()))
+  }
+
+  fn apply(&self, bodies: &mut [Body], impulse: Vector3<f32>) {
+    if let Some(i) = self.index {
+      let body = &mut bodies[i];
+      body.velocity += impulse * body.inverse_mass;
+      body.angular_velocity += body.turn_by(self.offset.cross(impulse));
+    }
+  }
+
+  fn apply_angular(&self, bodies: &mut [Body], angular_impulse: Vector3<f32>) {
+    if let Some(i) = self.index {
+      bodies[i].angular_velocity += bodies[i].turn_by(angular_impulse);
+    }
+  }
+}
+
+/// Changes the bodies' velocities so that they keep to their joints, with sequential impulses
+/// like contacts. Drift from what the joints allow is steered back out over the next few steps.
+pub fn solve_joints(bodies: &mut [Body], joints: &mut [BodyJoint], dt: f32) {
+  for _ in 0..JOINT_ITERATIONS {
+    for j in joints.iter_mut() {
+      let a = End::new(bodies, Some(j.a), j.joint.anchor_a);
+      let b = End::new(bodies, j.b, j.joint.anchor_b);
+      match j.joint.kind {
+        JointKind::BallSocket => solve_point(bodies, &a, &b, dt),
+        JointKind::Hinge { axis_a, axis_b } => {
+          solve_point(bodies, &a, &b, dt);
+          let axis_a = a.rotation.rotate_vector(axis_a);
+          let axis_b = b.rotation.rotate_vector(axis_b);
+          //turning about the axis is free, but not about the two directions across it
+          let across = axis_a.cross(if axis_a.x.abs() < 0.9 { Vector3::unit_x() } else { Vector3::unit_y() }).normalize();
+          let misalignment = axis_a.cross(axis_b);
+          for direction in [across, axis_a.cross(across)] {
+            solve_turn(bodies, &a, &b, direction, misalignment.dot(direction), dt);
+          }
+        },
+        JointKind::Distance { length } => j.impulse = solve_distance(bodies, &a, &b, length, j.impulse, dt),
+        JointKind::Fixed { rotation } => {
+          solve_point(bodies, &a, &b, dt);
+          //the small turn which would take a to where it should be
+          let mut error = b.rotation * rotation * a.rotation.conjugate();
+          if error.s < 0.0 {
+            error = -error;
+          }
+          let misalignment = error.v * 2.0;
+          let relative = a.angular_velocity(bodies) - b.angular_velocity(bodies);
+          let mass = a.turn_mass(bodies) + b.turn_mass(bodies);
+          if let Some(inverse) = mass.invert() {
+            let impulse = inverse * (misalignment * JOINT_CORRECTION_FACTOR / dt - relative);
+            a.apply_angular(bodies, impulse);
+            b.apply_angular(bodies, -impulse);
+          }
+        },
+      }
+    }
+  }
+}
+
+fn solve_point(bodies: &mut [Body], a: &End, b: &End, dt: f32) {
+  let drift = a.point - b.point;
+  let relative = a.velocity(bodies) - b.velocity(bodies);
+  let mass = a.point_mass(bodies) + b.point_mass(bodies);
+  if let Some(inverse) = mass.invert() {
+    let impulse = inverse * (-drift * JOINT_CORRECTION_FACTOR / dt - relative);
+    a.apply(bodies, impulse);
+    b.apply(bodies, -impulse);
+  }
+}
+
+fn solve_turn(bodies: &mut [Body], a: &End, b: &End, direction: Vector3<f32>, misalignment: f32, dt: f32) {
+  let relative = (a.angular_velocity(bodies) - b.angular_velocity(bodies)).dot(direction);
+  let mass = direction.dot((a.turn_mass(bodies) + b.turn_mass(bodies)) * direction);
+  if mass < EPSILON {
+    return;
+  }
+  let impulse = direction * (misalignment * JOINT_CORRECTION_FACTOR / dt - relative) / mass;
+  a.apply_angular(bodies, impulse);
+  b.apply_angular(bodies, -impulse);
+}
+
+/// Returns the new accumulated impulse, which only ever pulls the anchors together.
+fn solve_distance(bodies: &mut [Body], a: &End, b: &End, length: f32, accumulated: f32, dt: f32) -> f32 {
+  let apart = a.point - b.point;
+  let distance = apart.magnitude();
+  if distance < EPSILON {
+    return accumulated;
+  }
+  let direction = apart / distance;
+  let stretch = distance - length;
+  //slack ropes can stretch until taut this step, while stretched ones are pulled back gradually
+  let target_speed = if stretch < 0.0 {
+    -stretch / dt
+  } else {
+    -stretch * JOINT_CORRECTION_FACTOR / dt
+  };
+  let speed = (a.velocity(bodies) - b.velocity(bodies)).dot(direction);
+  let mass = direction.dot((a.point_mass(bodies) + b.point_mass(bodies)) * direction);
+  if mass < EPSILON {
+    return accumulated;
+  }
+  let total = (accumulated + (target_speed - speed) / mass).min(0.0);
+  let impulse = direction * (total - accumulated);
+  a.apply(bodies, impulse);
+  b.apply(bodies, -impulse);
+  total
+}
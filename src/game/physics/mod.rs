@@ -1,15 +1,41 @@
 mod sphere;
+mod aabb;
+mod oriented_box;
+mod capsule;
 mod fixed_plane;
+mod triangle;
+mod triangle_mesh;
+mod convex;
+mod gjk;
 mod collider;
+pub mod solver;
+mod broadphase;
+mod collision_filter;
+mod islands;
+mod joint;
 
 pub use sphere::Sphere;
+pub use aabb::Aabb;
+pub use oriented_box::OrientedBox;
+pub use capsule::Capsule;
 pub use fixed_plane::FixedPlane;
+pub use triangle::Triangle;
+pub use triangle_mesh::{MeshInstance, TriangleMesh};
+pub use convex::Convex;
+pub use collider::{Collider, CollideWith, Contact};
+pub use broadphase::Broadphase;
+pub use collision_filter::{CollisionFilter, DEFAULT_LAYER};
+pub use islands::Islands;
+pub use joint::{solve_joints, BodyJoint, Joint, JointKind};
 
 mod test {
   use crate::math::realcmp;
 
-use super::{FixedPlane, Sphere, collider::CollideWith};
-  use cgmath::Vector3;
+  use super::{Aabb, Capsule, Collider, CollideWith, FixedPlane, OrientedBox, Sphere};
+  use super::{Broadphase, BodyJoint, CollisionFilter, Islands, Joint, JointKind, MeshInstance, Triangle, TriangleMesh};
+  use crate::util::saltybuffer::SaltyId;
+  use super::solver::{self, Body, BodyShape};
+  use cgmath::{prelude::*, Deg, Quaternion, Vector3};
 
   #[test]
   fn test_collision_time() {
@@ -25,4 +51,732 @@ use super::{FixedPlane, Sphere, collider::CollideWith};
     let toc = ball.toc(&floor).unwrap();
     assert!(realcmp(toc, 2.0, 0.001))
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn test_collision_contact() {
+    let floor = FixedPlane {
+      normal: Vector3::unit_z(),
+      d: 10.,
+    };
+    let ball = Sphere {
+      radius: 1.,
+      position: Vector3::new(4., 0., 0.),
+      velocity: Vector3::new(-1., 0., -1.),
+    };
+    let contact = ball.contact(&floor).unwrap();
+    assert!(realcmp(contact.toc, 9.0, 0.001));
+    assert!(realcmp(contact.point.x, -5.0, 0.001));
+    assert!(realcmp(contact.point.z, -10.0, 0.001));
+    assert_eq!(Vector3::unit_z(), contact.normal);
+    assert_eq!(-Vector3::unit_z(), floor.contact(&ball).unwrap().normal);
+  }
+
+  #[test]
+  fn test_collision_penetrating() {
+    let floor = FixedPlane {
+      normal: Vector3::unit_z(),
+      d: 0.,
+    };
+    let ball = Sphere {
+      radius: 1.,
+      position: Vector3::new(0., 0., 0.5),
+      velocity: Vector3::new(0., 0., 1.),
+    };
+    let contact = ball.contact(&floor).unwrap();
+    assert_eq!(0.0, contact.toc);
+    assert!(realcmp(contact.depth, 0.5, 0.001));
+
+    //even fully behind the plane is a contact, since it's solid
+    let buried = Sphere {
+      position: Vector3::new(0., 0., -3.),
+      ..ball
+    };
+    assert!(realcmp(buried.contact(&floor).unwrap().depth, 4.0, 0.001));
+  }
+
+  #[test]
+  fn test_collision_miss() {
+    let floor = FixedPlane {
+      normal: Vector3::unit_z(),
+      d: 0.,
+    };
+    let parallel = Sphere {
+      radius: 1.,
+      position: Vector3::new(0., 0., 2.),
+      velocity: Vector3::new(1., 0., 0.),
+    };
+    assert!(parallel.toc(&floor).is_none());
+    let receding = Sphere {
+      velocity: Vector3::new(0., 0., 1.),
+      ..parallel
+    };
+    assert!(receding.toc(&floor).is_none());
+  }
+
+  //shapes used by the pairwise tests, all resting at the origin unless moved
+  fn sphere() -> Sphere {
+    Sphere {
+      radius: 1.,
+      position: Vector3::zero(),
+      velocity: Vector3::zero(),
+    }
+  }
+
+  fn aabb() -> Aabb {
+    Aabb {
+      half_extents: Vector3::new(1., 1., 1.),
+      position: Vector3::zero(),
+      velocity: Vector3::zero(),
+    }
+  }
+
+  //turned so that a vertical edge faces each horizontal axis, sqrt(2) from the center
+  fn diamond() -> OrientedBox {
+    OrientedBox {
+      half_extents: Vector3::new(1., 1., 1.),
+      position: Vector3::zero(),
+      rotation: Quaternion::from_angle_z(Deg(45.)),
+      velocity: Vector3::zero(),
+    }
+  }
+
+  fn capsule() -> Capsule {
+    Capsule {
+      radius: 0.5,
+      half_height: 1.,
+      position: Vector3::zero(),
+      rotation: Quaternion::one(),
+      velocity: Vector3::zero(),
+    }
+  }
+
+  fn incoming() -> (Vector3<f32>, Vector3<f32>) {
+    (Vector3::new(-5., 0., 0.), Vector3::unit_x())
+  }
+
+  fn assert_toc<A: CollideWith<B>, B: CollideWith<A>>(a: &A, b: &B, expected: f32) {
+    let contact = a.contact(b).unwrap();
+    assert!(realcmp(contact.toc, expected, 0.01), "toc {} != {}", contact.toc, expected);
+    //the normal points from b towards a, though edge contacts may lean either side of the x axis
+    assert!(contact.normal.x < -0.7, "normal {:?}", contact.normal);
+    let reverse = b.contact(a).unwrap();
+    assert!(realcmp(reverse.toc, expected, 0.01));
+    assert!(reverse.normal.x > 0.7);
+  }
+
+  #[test]
+  fn test_sphere_sphere() {
+    let (position, velocity) = incoming();
+    let a = Sphere { position, velocity, ..sphere() };
+    assert_toc(&a, &sphere(), 3.0);
+
+    let overlapping = Sphere { position: Vector3::new(-1.5, 0., 0.), ..sphere() };
+    let contact = overlapping.contact(&sphere()).unwrap();
+    assert_eq!(0.0, contact.toc);
+    assert!(realcmp(contact.depth, 0.5, 0.001));
+    assert!(realcmp(contact.point.x, -0.75, 0.001));
+    assert!(overlapping.intersects(&sphere()));
+  }
+
+  #[test]
+  fn test_sphere_aabb() {
+    let (position, velocity) = incoming();
+    assert_toc(&Sphere { position, velocity, ..sphere() }, &aabb(), 3.0);
+  }
+
+  #[test]
+  fn test_sphere_oriented_box() {
+    let (position, velocity) = incoming();
+    assert_toc(&Sphere { position, velocity, ..sphere() }, &diamond(), 4.0 - 2f32.sqrt());
+  }
+
+  #[test]
+  fn test_sphere_capsule() {
+    let (position, velocity) = incoming();
+    assert_toc(&Sphere { position, velocity, ..sphere() }, &capsule(), 3.5);
+
+    //passing over the top of the capsule's upper cap
+    let above = Sphere {
+      position: Vector3::new(-5., 0., 1.2),
+      velocity,
+      ..sphere()
+    };
+    let contact = above.contact(&capsule()).unwrap();
+    assert!(realcmp(contact.toc, 5.0 - (1.5f32.powi(2) - 0.2f32.powi(2)).sqrt(), 0.01));
+    assert!(contact.normal.z > 0.0);
+  }
+
+  #[test]
+  fn test_aabb_aabb() {
+    let (position, velocity) = incoming();
+    assert_toc(&Aabb { position, velocity, ..aabb() }, &aabb(), 3.0);
+
+    let overlapping = Aabb { position: Vector3::new(0.2, 0., 1.8), ..aabb() };
+    let contact = overlapping.contact(&aabb()).unwrap();
+    assert_eq!(0.0, contact.toc);
+    assert!(realcmp(contact.depth, 0.2, 0.001));
+    assert!(realcmp(contact.normal.z, 1.0, 0.001));
+  }
+
+  #[test]
+  fn test_aabb_oriented_box() {
+    let (position, velocity) = incoming();
+    assert_toc(&Aabb { position, velocity, ..aabb() }, &diamond(), 4.0 - 2f32.sqrt());
+  }
+
+  #[test]
+  fn test_aabb_capsule() {
+    let (position, velocity) = incoming();
+    assert_toc(&Aabb { position, velocity, ..aabb() }, &capsule(), 3.5);
+  }
+
+  #[test]
+  fn test_oriented_box_oriented_box() {
+    let (position, velocity) = incoming();
+    assert_toc(&OrientedBox { position, velocity, ..diamond() }, &diamond(), 5.0 - 2.0 * 2f32.sqrt());
+
+    let overlapping = OrientedBox { position: Vector3::new(-2., 0., 0.), ..diamond() };
+    let contact = overlapping.contact(&diamond()).unwrap();
+    //pushing out through a pair of faces is shallower than back along x
+    assert!(realcmp(contact.depth, 2.0 - 2f32.sqrt(), 0.01));
+  }
+
+  #[test]
+  fn test_small_box_in_large_box() {
+    //slightly off-center so GJK's simplex ends up within rounding of the origin
+    let floor = OrientedBox {
+      half_extents: Vector3::new(20., 20., 1.),
+      position: Vector3::new(0., 0., -1.),
+      rotation: Quaternion::one(),
+      velocity: Vector3::zero(),
+    };
+    for offset in [-0.003, 0.0014] {
+      let sunk = OrientedBox {
+        half_extents: Vector3::new(1., 1., 1.),
+        position: Vector3::new(offset, offset, 0.93),
+        ..floor
+      };
+      let contact = sunk.contact(&floor).unwrap();
+      assert!(realcmp(contact.depth, 0.07, 0.001));
+      assert!(realcmp(contact.normal.z, 1.0, 0.001));
+    }
+  }
+
+  #[test]
+  fn test_oriented_box_capsule() {
+    let (position, velocity) = incoming();
+    assert_toc(&OrientedBox { position, velocity, ..diamond() }, &capsule(), 4.5 - 2f32.sqrt());
+  }
+
+  #[test]
+  fn test_capsule_capsule() {
+    let (position, velocity) = incoming();
+    let lying = Capsule {
+      position,
+      velocity,
+      rotation: Quaternion::from_angle_y(Deg(90.)),
+      ..capsule()
+    };
+    assert_toc(&lying, &capsule(), 3.0);
+
+    //the segments cross, so the way out is sideways by both radii
+    let crossing = Capsule { position: Vector3::new(-0.8, 0., 0.), ..lying };
+    let contact = crossing.contact(&capsule()).unwrap();
+    assert!(realcmp(contact.depth, 1.0, 0.001));
+    assert!(realcmp(contact.normal.y.abs(), 1.0, 0.001));
+  }
+
+  #[test]
+  fn test_plane_shapes() {
+    let floor = FixedPlane {
+      normal: Vector3::unit_z(),
+      d: 0.,
+    };
+    let falling = -Vector3::unit_z();
+    let crate_box = Aabb { position: Vector3::new(0., 0., 3.), velocity: falling, ..aabb() };
+    assert!(realcmp(crate_box.toc(&floor).unwrap(), 2.0, 0.001));
+    assert!(realcmp(floor.toc(&crate_box).unwrap(), 2.0, 0.001));
+
+    let tipped = OrientedBox {
+      position: Vector3::new(0., 0., 3.),
+      rotation: Quaternion::from_angle_x(Deg(45.)),
+      velocity: falling,
+      ..diamond()
+    };
+    assert!(realcmp(tipped.toc(&floor).unwrap(), 3.0 - 2f32.sqrt(), 0.001));
+
+    let pill = Capsule { position: Vector3::new(0., 0., 3.), velocity: falling, ..capsule() };
+    let contact = pill.contact(&floor).unwrap();
+    assert!(realcmp(contact.toc, 1.5, 0.001));
+    assert!(realcmp(contact.point.z, 0.0, 0.001));
+    assert_eq!(-Vector3::unit_z(), floor.contact(&pill).unwrap().normal);
+  }
+
+  #[test]
+  fn test_separated_shapes_miss() {
+    let (position, velocity) = incoming();
+    let passing = Sphere {
+      position: position + Vector3::new(0., 3., 0.),
+      velocity,
+      ..sphere()
+    };
+    assert!(passing.contact(&aabb()).is_none());
+    assert!(!passing.intersects(&capsule()));
+    let receding = OrientedBox { position, velocity: -velocity, ..diamond() };
+    assert!(receding.contact(&diamond()).is_none());
+  }
+
+  #[test]
+  fn test_collider_dispatch() {
+    let (position, velocity) = incoming();
+    let moving = Collider::Sphere(Sphere { position, velocity, ..sphere() });
+    let still = [
+      Collider::Sphere(sphere()),
+      Collider::Aabb(aabb()),
+      Collider::OrientedBox(diamond()),
+      Collider::Capsule(capsule()),
+    ];
+    let expected = [3.0, 3.0, 4.0 - 2f32.sqrt(), 3.5];
+    for (other, toc) in still.iter().zip(expected.iter()) {
+      assert_toc(&moving, other, *toc);
+    }
+
+    let wall = Collider::Plane(FixedPlane {
+      normal: -Vector3::unit_x(),
+      d: -1.,
+    });
+    assert!(realcmp(moving.toc(&wall).unwrap(), 3.0, 0.001));
+    assert!(realcmp(wall.toc(&moving).unwrap(), 3.0, 0.001));
+    assert!(wall.contact(&wall).is_none());
+  }
+
+  fn floor_body() -> Body<'static> {
+    Body {
+      shape: BodyShape::Convex(Collider::Aabb(Aabb {
+        half_extents: Vector3::new(10., 10., 1.),
+        position: Vector3::new(0., 0., -1.),
+        velocity: Vector3::zero(),
+      })),
+      rotation: Quaternion::one(),
+      velocity: Vector3::zero(),
+      angular_velocity: Vector3::zero(),
+      inverse_mass: 0.,
+      inverse_inertia: Vector3::zero(),
+      restitution: 0.5,
+      friction: 1.,
+      filter: CollisionFilter::default(),
+      fast: false,
+    }
+  }
+
+  fn dropped_crate(height: f32, velocity: Vector3<f32>) -> Body<'static> {
+    Body {
+      shape: BodyShape::Convex(Collider::Aabb(Aabb {
+        position: Vector3::new(0., 0., height),
+        velocity,
+        ..aabb()
+      })),
+      rotation: Quaternion::one(),
+      velocity,
+      angular_velocity: Vector3::zero(),
+      inverse_mass: 1.,
+      inverse_inertia: Vector3::zero(),
+      restitution: 0.,
+      friction: 0.25,
+      filter: CollisionFilter::default(),
+      fast: false,
+    }
+  }
+
+  #[test]
+  fn test_solver_bounce() {
+    let mut bodies = [dropped_crate(1.05, Vector3::new(0., 0., -5.)), floor_body()];
+    let mut contacts = solver::find_contacts(&bodies, &[(0, 1)], 1. / 60.);
+    assert_eq!(1, contacts.len());
+    solver::solve_velocities(&mut bodies, &mut contacts);
+    //the bounciest of the two wins, and static bodies never move
+    assert!(realcmp(bodies[0].velocity.z, 2.5, 0.001));
+    assert_eq!(Vector3::zero(), bodies[1].velocity);
+
+    //too slow to bounce, so it only closes the gap
+    let mut bodies = [dropped_crate(1.05, Vector3::new(0., 0., -0.5)), floor_body()];
+    let mut contacts = solver::find_contacts(&bodies, &[(0, 1)], 1.);
+    solver::solve_velocities(&mut bodies, &mut contacts);
+    assert!(realcmp(bodies[0].velocity.z, -0.05, 0.001));
+  }
+
+  #[test]
+  fn test_solver_friction() {
+    let mut bodies = [dropped_crate(1., Vector3::new(3., 0., -2.)), floor_body()];
+    let mut contacts = solver::find_contacts(&bodies, &[(0, 1)], 1. / 60.);
+    solver::solve_velocities(&mut bodies, &mut contacts);
+    //bouncing at half speed takes a normal impulse of 3, allowing friction up to sqrt(0.25 * 1) * 3
+    assert!(realcmp(bodies[0].velocity.x, 1.5, 0.001));
+
+    //friction stops sliding but never reverses it
+    let mut bodies = [dropped_crate(1., Vector3::new(0.5, 0., -2.)), floor_body()];
+    let mut contacts = solver::find_contacts(&bodies, &[(0, 1)], 1. / 60.);
+    solver::solve_velocities(&mut bodies, &mut contacts);
+    assert!(realcmp(bodies[0].velocity.x, 0.0, 0.001));
+  }
+
+  #[test]
+  fn test_solver_correction() {
+    let bodies = [dropped_crate(0.5, Vector3::zero()), floor_body()];
+    let contacts = solver::find_contacts(&bodies, &[(0, 1)], 1. / 60.);
+    let corrections = solver::correct_positions(&bodies, &contacts);
+    assert!(corrections[0].z > 0.0 && corrections[0].z < 0.5);
+    assert_eq!(Vector3::zero(), corrections[1]);
+
+    //two static bodies never interact
+    let bodies = [floor_body(), floor_body()];
+    assert!(solver::find_contacts(&bodies, &[(0, 1)], 1. / 60.).is_empty());
+  }
+
+  #[test]
+  fn test_solver_filter() {
+    //on a layer the floor doesn't collide with
+    let ghost = Body {
+      filter: CollisionFilter { layer: 2, mask: u32::MAX },
+      ..dropped_crate(1.05, Vector3::new(0., 0., -5.))
+    };
+    let bodies = [ghost, Body { filter: CollisionFilter { layer: 1, mask: 1 }, ..floor_body() }];
+    assert!(solver::find_contacts(&bodies, &[(0, 1)], 1. / 60.).is_empty());
+  }
+
+  #[test]
+  fn test_solver_speculative() {
+    //falling together, a crate resting on another gets no swept contact until the bottom one stops
+    let fall = Vector3::new(0., 0., -3.);
+    let top = dropped_crate(1.03, fall);
+    let bottom = Body {
+      shape: BodyShape::Convex(Collider::Aabb(Aabb {
+        position: Vector3::new(0., 0., -1.),
+        velocity: fall,
+        ..aabb()
+      })),
+      ..top
+    };
+    let mut bodies = [top, bottom];
+    let mut contacts = solver::find_contacts(&bodies, &[(0, 1)], 1. / 60.);
+    //one at each corner where they meet
+    assert_eq!(4, contacts.len());
+
+    //stopping the bottom one lets the top one close the gap, but no more
+    bodies[1].inverse_mass = 0.0;
+    bodies[1].velocity = Vector3::zero();
+    solver::solve_velocities(&mut bodies, &mut contacts);
+    assert!(realcmp(bodies[0].velocity.z, -0.03 * 60., 0.01));
+
+    //too far apart to be worth a contact
+    let mut bodies = [dropped_crate(1.5, fall), bottom];
+    bodies[1].velocity = fall;
+    assert!(solver::find_contacts(&bodies, &[(0, 1)], 1. / 60.).is_empty());
+  }
+
+  //a flat 20x20 grid of triangles on the XY plane, centered on the origin
+  fn grid_mesh() -> TriangleMesh {
+    let mut triangles = Vec::new();
+    for x in -10..10 {
+      for y in -10..10 {
+        let corner = |dx: i32, dy: i32| Vector3::new((x + dx) as f32, (y + dy) as f32, 0.);
+        triangles.push(Triangle { vertices: [corner(0, 0), corner(1, 0), corner(1, 1)] });
+        triangles.push(Triangle { vertices: [corner(0, 0), corner(1, 1), corner(0, 1)] });
+      }
+    }
+    TriangleMesh::new(triangles)
+  }
+
+  #[test]
+  fn test_mesh_sweep() {
+    let mesh = grid_mesh();
+    let ground = MeshInstance {
+      mesh: &mesh,
+      position: Vector3::new(0., 0., -1.),
+      rotation: Quaternion::one(),
+    };
+    let falling = Sphere {
+      position: Vector3::new(2.3, -4.6, 3.),
+      velocity: Vector3::new(0., 0., -2.),
+      ..sphere()
+    };
+    let contact = ground.sweep(&falling, 10.).unwrap();
+    assert!(realcmp(contact.toc, 1.5, 0.001));
+    assert!(realcmp(contact.normal.z, 1.0, 0.001));
+    assert!(ground.sweep(&falling, 1.).is_none());
+
+    //a capsule lying on the ground and sliding along it stays in contact
+    let sliding = Capsule {
+      position: Vector3::new(0.5, 0.5, -0.6),
+      rotation: Quaternion::from_angle_x(Deg(90.)),
+      velocity: Vector3::new(1., 0., 0.),
+      ..capsule()
+    };
+    let contact = ground.sweep(&sliding, 1.).unwrap();
+    assert_eq!(0.0, contact.toc);
+    assert!(realcmp(contact.depth, 0.1, 0.001));
+
+    //tilting the mesh tilts what's hit
+    let ramp = MeshInstance {
+      rotation: Quaternion::from_angle_y(Deg(30.)),
+      position: Vector3::zero(),
+      ..ground
+    };
+    let contact = ramp.sweep(&Sphere { position: Vector3::new(0., 0., 3.), ..falling }, 10.).unwrap();
+    assert!(realcmp(contact.normal.z, 30f32.to_radians().cos(), 0.001));
+  }
+
+  #[test]
+  fn test_mesh_from_gltf() {
+    let mesh = TriangleMesh::from_gltf("maps/cube.gltf").unwrap();
+    assert!(!mesh.triangles().is_empty());
+    let ground = MeshInstance {
+      mesh: &mesh,
+      position: Vector3::zero(),
+      rotation: Quaternion::one(),
+    };
+    let dropped = Sphere {
+      position: Vector3::new(0., 0., 5.),
+      velocity: -Vector3::unit_z(),
+      ..sphere()
+    };
+    assert!(realcmp(ground.sweep(&dropped, 10.).unwrap().toc, 3.0, 0.001));
+    assert!(TriangleMesh::from_gltf("maps/missing.gltf").is_err());
+  }
+
+  #[test]
+  fn test_solver_mesh() {
+    let mesh = grid_mesh();
+    let ground = Body {
+      shape: BodyShape::Mesh(MeshInstance {
+        mesh: &mesh,
+        position: Vector3::zero(),
+        rotation: Quaternion::one(),
+      }),
+      ..floor_body()
+    };
+    let mut bodies = [dropped_crate(1.05, Vector3::new(0., 0., -5.)), ground];
+    let mut contacts = solver::find_contacts(&bodies, &[(0, 1)], 1. / 60.);
+    assert_eq!(1, contacts.len());
+    solver::solve_velocities(&mut bodies, &mut contacts);
+    assert!(realcmp(bodies[0].velocity.z, 2.5, 0.001));
+  }
+
+  fn unit_box(center: Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
+    (center - Vector3::new(1., 1., 1.), center + Vector3::new(1., 1., 1.))
+  }
+
+  fn sorted_pairs(broadphase: &Broadphase<64>) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    broadphase.pairs(|a, b| pairs.push((a.index().min(b.index()), a.index().max(b.index()))));
+    pairs.sort();
+    pairs
+  }
+
+  #[test]
+  fn test_broadphase_pairs() {
+    let mut broadphase = Broadphase::<64>::new();
+    let ids: Vec<SaltyId> = (0..4).map(|i| SaltyId::new(1, i)).collect();
+    let (min, max) = unit_box(Vector3::new(0., 0., 0.));
+    broadphase.update(ids[0], min, max, CollisionFilter::default());
+    let (min, max) = unit_box(Vector3::new(1.5, 0., 0.));
+    broadphase.update(ids[1], min, max, CollisionFilter::default());
+    let (min, max) = unit_box(Vector3::new(1.5, 5., 0.));
+    broadphase.update(ids[2], min, max, CollisionFilter::default());
+    let (min, max) = unit_box(Vector3::new(-10., 0., 0.));
+    broadphase.update(ids[3], min, max, CollisionFilter::default());
+    assert_eq!(4, broadphase.len());
+    assert_eq!(vec![(0, 1)], sorted_pairs(&broadphase));
+
+    //moving across the others reorders it
+    let (min, max) = unit_box(Vector3::new(2., 4., 0.));
+    broadphase.update(ids[3], min, max, CollisionFilter::default());
+    assert_eq!(vec![(0, 1), (2, 3)], sorted_pairs(&broadphase));
+
+    broadphase.remove(ids[1]);
+    assert!(!broadphase.contains(ids[1]));
+    assert_eq!(vec![(2, 3)], sorted_pairs(&broadphase));
+
+    let mut found = Vec::new();
+    broadphase.query(Vector3::new(-0.5, -0.5, -0.5), Vector3::new(0.5, 0.5, 0.5), |id| found.push(id));
+    assert_eq!(vec![ids[0]], found);
+  }
+
+  #[test]
+  fn test_broadphase_filter() {
+    //debris collides with everything but more debris
+    let debris = CollisionFilter { layer: 2, mask: !2 };
+    let mut broadphase = Broadphase::<64>::new();
+    let ids: Vec<SaltyId> = (0..3).map(|i| SaltyId::new(1, i)).collect();
+    let (min, max) = unit_box(Vector3::new(0., 0., 0.));
+    broadphase.update(ids[0], min, max, CollisionFilter::default());
+    broadphase.update(ids[1], min, max, debris);
+    broadphase.update(ids[2], min, max, debris);
+    assert_eq!(vec![(0, 1), (0, 2)], sorted_pairs(&broadphase));
+  }
+
+  #[test]
+  fn test_broadphase_matches_brute_force() {
+    let mut broadphase = Broadphase::<64>::new();
+    let mut seed = 12345u32;
+    let mut random = move || {
+      seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+      (seed >> 16) as f32 / 65536. * 20. - 10.
+    };
+    let mut centers: Vec<Vector3<f32>> = (0..64).map(|_| Vector3::new(random(), random(), random())).collect();
+    for step in 0..3 {
+      for (i, center) in centers.iter_mut().enumerate() {
+        if step > 0 {
+          *center += Vector3::new(random(), random(), random()) * 0.2;
+        }
+        let (min, max) = unit_box(*center);
+        broadphase.update(SaltyId::new(1, i as u16), min, max, CollisionFilter::default());
+      }
+      let mut expected = Vec::new();
+      for i in 0..centers.len() {
+        for j in i + 1..centers.len() {
+          let d = centers[i] - centers[j];
+          if d.x.abs() <= 2. && d.y.abs() <= 2. && d.z.abs() <= 2. {
+            expected.push((i, j));
+          }
+        }
+      }
+      assert_eq!(expected, sorted_pairs(&broadphase));
+    }
+  }
+
+  fn rigid_box() -> Collider {
+    Collider::OrientedBox(OrientedBox {
+      half_extents: Vector3::new(2., 1., 0.5),
+      position: Vector3::zero(),
+      rotation: Quaternion::one(),
+      velocity: Vector3::zero(),
+    })
+  }
+
+  fn inverse(inertia: Vector3<f32>) -> Vector3<f32> {
+    Vector3::new(1. / inertia.x, 1. / inertia.y, 1. / inertia.z)
+  }
+
+  #[test]
+  fn test_inertia() {
+    let ball = Collider::Sphere(Sphere { radius: 2., ..sphere() });
+    assert!(realcmp(ball.inertia(5.).x, 8., 0.0001));
+
+    //a 4x2x1 box
+    let inertia = rigid_box().inertia(12.);
+    assert!(realcmp(inertia.x, 5., 0.0001));
+    assert!(realcmp(inertia.y, 17., 0.0001));
+    assert!(realcmp(inertia.z, 20., 0.0001));
+
+    //a capsule without a cylinder is a sphere, and a longer one is harder to tip than to spin
+    let round = Collider::Capsule(Capsule { half_height: 0., radius: 2., ..capsule() });
+    assert!(realcmp(round.inertia(5.).x, 8., 0.0001));
+    assert!(realcmp(round.inertia(5.).z, 8., 0.0001));
+    let long = Collider::Capsule(capsule()).inertia(1.);
+    assert!(long.x > long.z);
+  }
+
+  #[test]
+  fn test_volume() {
+    assert!(realcmp(rigid_box().volume(), 8., 0.0001));
+    let ball = Collider::Sphere(Sphere { radius: 2., ..sphere() });
+    let round = Collider::Capsule(Capsule { half_height: 0., radius: 2., ..capsule() });
+    assert!(realcmp(round.volume(), ball.volume(), 0.0001));
+    assert!(Collider::Capsule(Capsule { radius: 2., ..capsule() }).volume() > round.volume());
+  }
+
+  #[test]
+  fn test_integrate_rotation() {
+    //spinning about a principal axis turns steadily
+    let inverse_inertia = inverse(rigid_box().inertia(1.));
+    let mut rotation = Quaternion::one();
+    let mut angular_velocity = Vector3::new(0., 0., 2.);
+    for _ in 0..60 {
+      let (r, w) = solver::integrate_rotation(rotation, angular_velocity, inverse_inertia, 1. / 60.);
+      rotation = r;
+      angular_velocity = w;
+    }
+    let expected = Quaternion::from_angle_z(cgmath::Rad(2.));
+    assert!(realcmp(rotation.dot(expected).abs(), 1., 0.0001));
+    assert!(realcmp(angular_velocity.z, 2., 0.0001));
+    assert!(realcmp(rotation.magnitude(), 1., 0.0001));
+  }
+
+  #[test]
+  fn test_rotation_drift() {
+    //a tumbling box wobbles, but keeps its angular momentum and energy
+    let inertia = rigid_box().inertia(1.);
+    let inverse_inertia = inverse(inertia);
+    let mut rotation = Quaternion::one();
+    let mut angular_velocity = Vector3::new(0.2, 3., 0.2);
+    let momentum = |rotation: Quaternion<f32>, angular_velocity: Vector3<f32>| {
+      let local = rotation.conjugate().rotate_vector(angular_velocity);
+      let local_momentum = local.mul_element_wise(inertia);
+      (rotation.rotate_vector(local_momentum), 0.5 * local.dot(local_momentum))
+    };
+    let (start_momentum, start_energy) = momentum(rotation, angular_velocity);
+    for _ in 0..600 {
+      let (r, w) = solver::integrate_rotation(rotation, angular_velocity, inverse_inertia, 1. / 60.);
+      rotation = r;
+      angular_velocity = w;
+    }
+    let (end_momentum, end_energy) = momentum(rotation, angular_velocity);
+    assert!((end_momentum - start_momentum).magnitude() < start_momentum.magnitude() * 0.001);
+    assert!(realcmp(end_energy, start_energy, start_energy * 0.005), "energy went from {} to {}", start_energy, end_energy);
+    assert!(realcmp(rotation.magnitude(), 1., 0.0001));
+  }
+
+  #[test]
+  fn test_islands() {
+    let bodies = [
+      dropped_crate(1., Vector3::zero()),
+      dropped_crate(3., Vector3::zero()),
+      dropped_crate(10., Vector3::zero()),
+      dropped_crate(12., Vector3::zero()),
+      floor_body(),
+    ];
+    //a stack of two on the floor, and another two touching in the air
+    let pairs = [(0, 1), (0, 4), (2, 3), (1, 2)];
+    let contacts = solver::find_contacts(&bodies, &pairs, 1. / 60.);
+    let mut islands = Islands::new(&bodies, &contacts);
+    assert_eq!(islands.root(0), islands.root(1));
+    assert_eq!(islands.root(2), islands.root(3));
+    assert_ne!(islands.root(0), islands.root(2));
+    //the floor holds up the stack without joining it
+    assert_ne!(islands.root(0), islands.root(4));
+  }
+
+  #[test]
+  fn test_solve_joints() {
+    //a crate hanging from the world by its top edge, knocked sideways
+    let hanging = Body {
+      inverse_inertia: Vector3::new(6., 6., 6.),
+      ..dropped_crate(0., Vector3::new(1., 0., -1.))
+    };
+    let ball_socket = Joint {
+      kind: JointKind::BallSocket,
+      anchor_a: Vector3::new(0., 0., 1.),
+      anchor_b: Vector3::new(0., 0., 1.),
+    };
+    let mut bodies = [hanging];
+    super::solve_joints(&mut bodies, &mut [BodyJoint::new(0, None, ball_socket)], 1. / 60.);
+    //the anchor stops, so the crate swings about it instead
+    let anchor_velocity = bodies[0].velocity + bodies[0].angular_velocity.cross(Vector3::new(0., 0., 1.));
+    assert!(anchor_velocity.magnitude() < 0.001);
+    assert!(bodies[0].angular_velocity.y < 0.0);
+
+    //a slack rope lets the crate fall, but a taut one doesn't let it fall further
+    let rope = |length| Joint {
+      kind: JointKind::Distance { length },
+      anchor_b: Vector3::new(0., 0., 1.5),
+      ..ball_socket
+    };
+    let mut bodies = [Body { inverse_inertia: Vector3::zero(), ..hanging }];
+    super::solve_joints(&mut bodies, &mut [BodyJoint::new(0, None, rope(1.))], 1. / 60.);
+    assert!(realcmp(bodies[0].velocity.z, -1., 0.001));
+    let mut bodies = [Body { inverse_inertia: Vector3::zero(), ..dropped_crate(-0.5, Vector3::new(0., 0., -1.)) }];
+    super::solve_joints(&mut bodies, &mut [BodyJoint::new(0, None, rope(1.))], 1. / 60.);
+    assert!(realcmp(bodies[0].velocity.z, 0., 0.001));
+  }
+}
@@ -0,0 +1,27 @@
+use cgmath::{prelude::*, Quaternion, Vector3};
+
+use super::aabb::box_corner;
+use super::convex::Convex;
+
+#[derive(Copy, Clone, Debug)]
+pub struct OrientedBox {
+  pub half_extents: Vector3<f32>,
+  pub position: Vector3<f32>,
+  pub rotation: Quaternion<f32>,
+  pub velocity: Vector3<f32>,
+}
+
+impl Convex for OrientedBox {
+  fn core_support(&self, direction: Vector3<f32>) -> Vector3<f32> {
+    let local_direction = self.rotation.conjugate().rotate_vector(direction);
+    self.position + self.rotation.rotate_vector(box_corner(self.half_extents, local_direction))
+  }
+
+  fn center(&self) -> Vector3<f32> {
+    self.position
+  }
+
+  fn velocity(&self) -> Vector3<f32> {
+    self.velocity
+  }
+}
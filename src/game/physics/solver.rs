@@ -0,0 +1,379 @@
+use cgmath::{prelude::*, Quaternion, Rad, Vector3};
+
+use super::collider::{CollideWith, Collider, Contact};
+use super::collision_filter::CollisionFilter;
+use super::convex::Convex;
+use super::gjk;
+use super::sphere::Sphere;
+use super::triangle_mesh::MeshInstance;
+
+// Slower approaches than this don't bounce, so resting contacts settle instead of jittering
+const RESTITUTION_THRESHOLD: f32 = 1.0;
+const VELOCITY_ITERATIONS: usize = 8;
+// Penetration tolerated before positional correction kicks in, keeping resting contacts stable
+const PENETRATION_SLOP: f32 = 0.01;
+// Fraction of the remaining penetration corrected per step
+const CORRECTION_FACTOR: f32 = 0.8;
+const MIDPOINT_ITERATIONS: usize = 3;
+// Bodies this close get a contact even when they aren't approaching yet, so that a stack falling
+// as one still holds together once its bottom lands
+const SPECULATIVE_DISTANCE: f32 = 0.05;
+// How far the directions used to find the corners a body rests on lean away from the contact
+// normal, enough to pick out every corner of a face lying flat
+const CORNER_SPREAD: f32 = 0.1;
+// Corners closer together than this along the contact count as one point of contact
+const CORNER_MERGE_DISTANCE: f32 = 0.01;
+
+#[derive(Copy, Clone, Debug)]
+pub enum BodyShape<'m> {
+  Convex(Collider),
+  // Always static
+  Mesh(MeshInstance<'m>),
+}
+
+impl<'m> BodyShape<'m> {
+  pub fn position(&self) -> Vector3<f32> {
+    match self {
+      BodyShape::Convex(collider) => collider.position(),
+      BodyShape::Mesh(mesh) => mesh.position,
+    }
+  }
+}
+
+/// A shape along with what the solver needs to know to push it around.
+#[derive(Copy, Clone, Debug)]
+pub struct Body<'m> {
+  pub shape: BodyShape<'m>,
+  // Kept separately from the shape, since some shapes look the same however they're turned
+  pub rotation: Quaternion<f32>,
+  pub velocity: Vector3<f32>,
+  pub angular_velocity: Vector3<f32>,
+  // Zero for static bodies, which nothing can move
+  pub inverse_mass: f32,
+  // About the shape's own axes, and zero for bodies which nothing can turn
+  pub inverse_inertia: Vector3<f32>,
+  pub restitution: f32,
+  pub friction: f32,
+  pub filter: CollisionFilter,
+  // Swept through the whole step so it can't pass through anything, for projectiles
+  pub fast: bool,
+}
+
+impl<'m> Body<'m> {
+  /// Bounds of everywhere the body could reach within `dt`.
+  pub fn swept_bounds(&self, dt: f32) -> (Vector3<f32>, Vector3<f32>) {
+    match &self.shape {
+      BodyShape::Convex(collider) => match collider.as_convex() {
+        Some(convex) => convex.swept_bounds(dt),
+        None => (Vector3::new(f32::MIN, f32::MIN, f32::MIN), Vector3::new(f32::MAX, f32::MAX, f32::MAX)),
+      },
+      BodyShape::Mesh(mesh) => mesh.bounds(),
+    }
+  }
+
+  /// How far ahead to look for contacts. Bodies which aren't fast only look about as far as
+  /// they are thin, any further and they'd already be overlapping by the next step.
+  fn reach(&self) -> f32 {
+    if self.fast {
+      return f32::INFINITY;
+    }
+    match &self.shape {
+      BodyShape::Convex(collider) => collider.as_convex().map_or(f32::INFINITY, |convex| {
+        let (min, max) = convex.bounds();
+        let half_extents = (max - min) / 2.0;
+        half_extents.x.min(half_extents.y).min(half_extents.z)
+      }),
+      BodyShape::Mesh(_) => 0.0,
+    }
+  }
+
+  /// Change in angular velocity from an angular impulse in world space.
+  pub fn turn_by(&self, angular_impulse: Vector3<f32>) -> Vector3<f32> {
+    let local = self.rotation.conjugate().rotate_vector(angular_impulse).mul_element_wise(self.inverse_inertia);
+    self.rotation.rotate_vector(local)
+  }
+
+  /// Velocity of the point `offset` from the body's centre, spin included.
+  fn point_velocity(&self, offset: Vector3<f32>) -> Vector3<f32> {
+    self.velocity + self.angular_velocity.cross(offset)
+  }
+
+  /// How much a unit impulse along `direction` at `offset` from the centre speeds that point up
+  /// along it, from both pushing the body and turning it.
+  fn point_inverse_mass(&self, offset: Vector3<f32>, direction: Vector3<f32>) -> f32 {
+    self.inverse_mass + self.turn_by(offset.cross(direction)).cross(offset).dot(direction)
+  }
+
+  fn apply_impulse(&mut self, offset: Vector3<f32>, impulse: Vector3<f32>) {
+    self.velocity += impulse * self.inverse_mass;
+    self.angular_velocity += self.turn_by(offset.cross(impulse));
+  }
+}
+
+/// A contact between bodies `a` and `b`, as indices into the solved slice, and the impulses
+/// accumulated for it so far.
+#[derive(Copy, Clone, Debug)]
+pub struct BodyContact {
+  pub a: usize,
+  pub b: usize,
+  // Normal points from b towards a
+  pub contact: Contact,
+  // From each body's centre to the contact point, as of when they touch
+  offset_a: Vector3<f32>,
+  offset_b: Vector3<f32>,
+  // Relative normal velocity the solver aims for
+  target_speed: f32,
+  normal_impulse: f32,
+  tangent_impulse: Vector3<f32>,
+}
+
+/// Finds which of the candidate pairs of bodies will touch within `dt`. Contacts which haven't
+/// happened yet are included too, letting the solver act before the bodies tunnel into each other.
+pub fn find_contacts(bodies: &[Body], pairs: &[(usize, usize)], dt: f32) -> Vec<BodyContact> {
+  let mut contacts = Vec::new();
+  for &(i, j) in pairs {
+    let (a, b) = (&bodies[i], &bodies[j]);
+    if (a.inverse_mass == 0.0 && b.inverse_mass == 0.0) || !a.filter.collides(&b.filter) {
+      continue;
+    }
+    for (contact, gap) in body_contact(a, b, dt).map_or(Vec::new(), |(contact, gap)| resting_contacts(a, b, contact, gap)) {
+      let offset_a = contact.point - a.shape.position() - a.velocity * contact.toc;
+      let offset_b = contact.point - b.shape.position() - b.velocity * contact.toc;
+      let approach_speed = -(a.point_velocity(offset_a) - b.point_velocity(offset_b)).dot(contact.normal);
+      contacts.push(BodyContact {
+        a: i,
+        b: j,
+        contact,
+        offset_a,
+        offset_b,
+        target_speed: target_speed(a, b, approach_speed, gap, dt),
+        normal_impulse: 0.0,
+        tangent_impulse: Vector3::zero(),
+      });
+    }
+  }
+  contacts
+}
+
+/// The contact between two bodies along with the gap still separating them. Contacts further
+/// off than either body reaches are left out, so only fast bodies are stopped short of anything
+/// they'd otherwise pass right through in one step.
+fn body_contact(a: &Body, b: &Body, dt: f32) -> Option<(Contact, f32)> {
+  let reach = a.reach().max(b.reach()).max(SPECULATIVE_DISTANCE);
+  let swept = |contact: Contact| {
+    let approach_speed = -(a.velocity - b.velocity).dot(contact.normal);
+    let gap = approach_speed.max(0.0) * contact.toc;
+    (gap <= reach).then_some((contact, gap))
+  };
+  match (&a.shape, &b.shape) {
+    (BodyShape::Convex(a_collider), BodyShape::Convex(b_collider)) => {
+      match a_collider.contact(b_collider).filter(|contact| contact.toc <= dt).and_then(swept) {
+        Some(contact) => Some(contact),
+        None => speculative_contact(a_collider.as_convex()?, b_collider.as_convex()?),
+      }
+    },
+    (BodyShape::Convex(a), BodyShape::Mesh(b)) => b.sweep(a.as_convex()?, dt).and_then(swept),
+    (BodyShape::Mesh(a), BodyShape::Convex(b)) => a.sweep(b.as_convex()?, dt).map(Contact::flipped).and_then(swept),
+    (BodyShape::Mesh(_), BodyShape::Mesh(_)) => None,
+  }
+}
+
+/// Splits a contact between bodies resting against each other into one at each corner they
+/// touch by, since a single point isn't enough to hold up a box lying flat without it rocking.
+/// Only corners actually over the other body count, so things hanging off an edge still tip.
+/// Contacts which are still to come stay as they are.
+fn resting_contacts(a: &Body, b: &Body, contact: Contact, gap: f32) -> Vec<(Contact, f32)> {
+  let normal = contact.normal;
+  let corners = match (&a.shape, &b.shape) {
+    _ if contact.toc > 0.0 => Vec::new(),
+    (BodyShape::Convex(a_collider), BodyShape::Convex(b_collider)) => match (a_collider.as_convex(), b_collider.as_convex()) {
+      (Some(a_convex), Some(b_convex)) => {
+        let mut corners = touching_corners(a_convex, -normal, b_convex);
+        corners.extend(touching_corners(b_convex, normal, a_convex));
+        corners
+      },
+      _ => Vec::new(),
+    },
+    //meshes are assumed to be under the whole of whatever rests on them
+    (BodyShape::Convex(collider), BodyShape::Mesh(_)) => collider.as_convex().map_or(Vec::new(), |convex| {
+      deepest_corners(convex, -normal, gap)
+    }),
+    (BodyShape::Mesh(_), BodyShape::Convex(collider)) => collider.as_convex().map_or(Vec::new(), |convex| {
+      deepest_corners(convex, normal, gap)
+    }),
+    (BodyShape::Mesh(_), BodyShape::Mesh(_)) => Vec::new(),
+  };
+
+  //corners from either side facing each other across the gap are the same point of contact
+  let mut merged: Vec<(Vector3<f32>, f32)> = Vec::new();
+  for (point, corner_gap) in corners {
+    let apart = |other: Vector3<f32>| {
+      let offset = point - other;
+      (offset - normal * offset.dot(normal)).magnitude()
+    };
+    if merged.iter().all(|(other, _)| apart(*other) > CORNER_MERGE_DISTANCE) {
+      merged.push((point, corner_gap));
+    }
+  }
+  if merged.len() < 2 {
+    return vec![(contact, gap)];
+  }
+  //the bodies are only pushed apart once, however many corners they touch by
+  merged.iter().enumerate().map(|(i, (point, corner_gap))| {
+    let depth = if i == 0 { contact.depth } else { 0.0 };
+    (Contact { point: *point, depth, ..contact }, *corner_gap)
+  }).collect()
+}
+
+/// The corners of a shape at the far end of it in `direction`, its core's vertices found by
+/// leaning the direction every way, pushed out to the full shape.
+fn corners(shape: &dyn Convex, direction: Vector3<f32>) -> Vec<Vector3<f32>> {
+  let across = direction.cross(if direction.x.abs() < 0.9 { Vector3::unit_x() } else { Vector3::unit_y() }).normalize();
+  let other_across = direction.cross(across);
+  (0..8).map(|i| {
+    let angle = i as f32 * std::f32::consts::FRAC_PI_4;
+    let lean = (across * angle.cos() + other_across * angle.sin()) * CORNER_SPREAD;
+    shape.core_support(direction + lean) + direction * shape.margin()
+  }).collect()
+}
+
+/// Corners of `shape` facing `direction` which are within the speculative distance of `other`,
+/// with their gaps.
+fn touching_corners(shape: &dyn Convex, direction: Vector3<f32>, other: &dyn Convex) -> Vec<(Vector3<f32>, f32)> {
+  corners(shape, direction).into_iter().filter_map(|corner| {
+    let point = Sphere { radius: 0.0, position: corner, velocity: Vector3::zero() };
+    let separation = gjk::separation(&point, Vector3::zero(), other, Vector3::zero());
+    (separation.distance <= SPECULATIVE_DISTANCE).then_some((corner, separation.distance.max(0.0)))
+  }).collect()
+}
+
+/// Corners of `shape` facing `direction` which are within the speculative distance of the deepest
+/// one, with their gaps given the deepest has a gap of `gap`.
+fn deepest_corners(shape: &dyn Convex, direction: Vector3<f32>, gap: f32) -> Vec<(Vector3<f32>, f32)> {
+  let corners = corners(shape, direction);
+  let deepest = corners.iter().map(|corner| corner.dot(direction)).fold(f32::MIN, f32::max);
+  corners.into_iter()
+    .map(|corner| (corner, gap + deepest - corner.dot(direction)))
+    .filter(|(_, corner_gap)| *corner_gap - gap <= SPECULATIVE_DISTANCE)
+    .collect()
+}
+
+fn speculative_contact(a: &dyn Convex, b: &dyn Convex) -> Option<(Contact, f32)> {
+  let separation = gjk::separation(a, Vector3::zero(), b, Vector3::zero());
+  if separation.distance > SPECULATIVE_DISTANCE {
+    return None;
+  }
+  let contact = Contact {
+    toc: 0.0,
+    normal: separation.normal,
+    point: separation.point,
+    depth: (-separation.distance).max(0.0),
+  };
+  Some((contact, separation.distance.max(0.0)))
+}
+
+fn target_speed(a: &Body, b: &Body, approach_speed: f32, gap: f32, dt: f32) -> f32 {
+  //only bounce off contacts which are actually reached this step, and are close enough that
+  //bouncing straight away doesn't leave a fast body stopped well short of what it hit
+  if approach_speed > RESTITUTION_THRESHOLD && approach_speed * dt >= gap && gap <= SPECULATIVE_DISTANCE {
+    approach_speed * a.restitution.max(b.restitution)
+  } else {
+    //close whatever gap is left this step, but no more
+    -gap / dt
+  }
+}
+
+/// Applies normal and friction impulses to the bodies' velocities with sequential impulses,
+/// iterating so that stacks of contacts converge on a consistent answer.
+pub fn solve_velocities(bodies: &mut [Body], contacts: &mut [BodyContact]) {
+  for _ in 0..VELOCITY_ITERATIONS {
+    for c in contacts.iter_mut() {
+      let (i, j) = (c.a, c.b);
+      let (a, b) = (bodies[i], bodies[j]);
+      let (offset_a, offset_b) = (c.offset_a, c.offset_b);
+      let normal = c.contact.normal;
+      //pushing off centre turns the bodies as well, so they can tip over and roll
+      let inverse_mass = |direction: Vector3<f32>| {
+        a.point_inverse_mass(offset_a, direction) + b.point_inverse_mass(offset_b, direction)
+      };
+      let relative_velocity = |bodies: &[Body]| {
+        bodies[i].point_velocity(offset_a) - bodies[j].point_velocity(offset_b)
+      };
+
+      //accumulate rather than apply each impulse, so later iterations can take some back
+      let normal_speed = relative_velocity(bodies).dot(normal);
+      let impulse = (c.normal_impulse + (c.target_speed - normal_speed) / inverse_mass(normal)).max(0.0);
+      let applied = normal * (impulse - c.normal_impulse);
+      c.normal_impulse = impulse;
+      bodies[i].apply_impulse(offset_a, applied);
+      bodies[j].apply_impulse(offset_b, -applied);
+
+      //coulomb friction, limited by how hard the bodies are pressed together
+      let relative = relative_velocity(bodies);
+      let tangent_velocity = relative - normal * relative.dot(normal);
+      if tangent_velocity.magnitude2() == 0.0 {
+        continue;
+      }
+      let max_friction = (a.friction * b.friction).sqrt() * c.normal_impulse;
+      let mut tangent_impulse = c.tangent_impulse - tangent_velocity / inverse_mass(tangent_velocity.normalize());
+      if tangent_impulse.magnitude2() > max_friction * max_friction {
+        tangent_impulse = tangent_impulse.normalize_to(max_friction);
+      }
+      let applied = tangent_impulse - c.tangent_impulse;
+      c.tangent_impulse = tangent_impulse;
+      bodies[i].apply_impulse(offset_a, applied);
+      bodies[j].apply_impulse(offset_b, -applied);
+    }
+  }
+}
+
+/// How far to move each body to push apart those which are already overlapping, since velocity
+/// alone lets resting bodies slowly sink into each other.
+pub fn correct_positions(bodies: &[Body], contacts: &[BodyContact]) -> Vec<Vector3<f32>> {
+  let mut corrections = vec![Vector3::zero(); bodies.len()];
+  for c in contacts.iter().filter(|c| c.contact.depth > PENETRATION_SLOP) {
+    let (a, b) = (&bodies[c.a], &bodies[c.b]);
+    let push = c.contact.normal * (c.contact.depth - PENETRATION_SLOP) * CORRECTION_FACTOR / (a.inverse_mass + b.inverse_mass);
+    corrections[c.a] += push * a.inverse_mass;
+    corrections[c.b] -= push * b.inverse_mass;
+  }
+  corrections
+}
+
+/// Turns a body by its angular velocity for `dt`, returning the new rotation and angular
+/// velocity. Without torques a body's angular momentum is what stays constant, so the angular
+/// velocity is recovered from it: bodies which aren't spinning about a principal axis wobble and
+/// tumble as they should. Bodies without inertia just keep spinning the same way.
+pub fn integrate_rotation(rotation: Quaternion<f32>, angular_velocity: Vector3<f32>, inverse_inertia: Vector3<f32>, dt: f32) -> (Quaternion<f32>, Vector3<f32>) {
+  if angular_velocity.magnitude2() == 0.0 {
+    return (rotation, angular_velocity);
+  }
+  if inverse_inertia.x == 0.0 || inverse_inertia.y == 0.0 || inverse_inertia.z == 0.0 {
+    return (turn(rotation, angular_velocity, dt), angular_velocity);
+  }
+
+  let spin = |rotation: Quaternion<f32>, momentum: Vector3<f32>| {
+    let local = rotation.conjugate().rotate_vector(momentum).mul_element_wise(inverse_inertia);
+    rotation.rotate_vector(local)
+  };
+  let local = rotation.conjugate().rotate_vector(angular_velocity);
+  let momentum = rotation.rotate_vector(local.div_element_wise(inverse_inertia));
+
+  //turn at the angular velocity halfway through the step, which keeps tumbling bodies from
+  //gaining energy the way using the starting velocity would
+  let mut turned = turn(rotation, angular_velocity, dt);
+  for _ in 0..MIDPOINT_ITERATIONS {
+    let midpoint = rotation.nlerp(turned, 0.5);
+    turned = turn(rotation, spin(midpoint, momentum), dt);
+  }
+  (turned, spin(turned, momentum))
+}
+
+fn turn(rotation: Quaternion<f32>, angular_velocity: Vector3<f32>, dt: f32) -> Quaternion<f32> {
+  let speed = angular_velocity.magnitude();
+  if speed == 0.0 {
+    return rotation;
+  }
+  let turn = Quaternion::from_axis_angle(angular_velocity / speed, Rad(speed * dt));
+  (turn * rotation).normalize()
+}
@@ -1,8 +1,8 @@
 use cgmath::Vector3;
 
-use super::collider::CollideWith;
-use super::fixed_plane::FixedPlane;
+use super::convex::Convex;
 
+#[derive(Copy, Clone, Debug)]
 pub struct Sphere {
   pub radius: f32,
   pub position: Vector3<f32>,
@@ -10,9 +10,20 @@ pub struct Sphere {
 }
 
 //todo: some things dont need continuous phys
-impl CollideWith<FixedPlane> for Sphere {
-  fn toc(&self, other: &FixedPlane) -> Option<f32> {
-      
-      None
+impl Convex for Sphere {
+  fn core_support(&self, _direction: Vector3<f32>) -> Vector3<f32> {
+    self.position
   }
-}
\ No newline at end of file
+
+  fn margin(&self) -> f32 {
+    self.radius
+  }
+
+  fn center(&self) -> Vector3<f32> {
+    self.position
+  }
+
+  fn velocity(&self) -> Vector3<f32> {
+    self.velocity
+  }
+}
@@ -0,0 +1,39 @@
+use cgmath::{prelude::*, Vector3};
+
+use super::convex::Convex;
+
+/// A single static triangle, usually one of a `TriangleMesh`. It's two-sided.
+#[derive(Copy, Clone, Debug)]
+pub struct Triangle {
+  pub vertices: [Vector3<f32>; 3],
+}
+
+impl Triangle {
+  pub fn normal(&self) -> Vector3<f32> {
+    let [a, b, c] = self.vertices;
+    (b - a).cross(c - a).normalize()
+  }
+}
+
+impl Convex for Triangle {
+  fn core_support(&self, direction: Vector3<f32>) -> Vector3<f32> {
+    let [a, b, c] = self.vertices;
+    let (da, db, dc) = (a.dot(direction), b.dot(direction), c.dot(direction));
+    if da >= db && da >= dc {
+      a
+    } else if db >= dc {
+      b
+    } else {
+      c
+    }
+  }
+
+  fn center(&self) -> Vector3<f32> {
+    let [a, b, c] = self.vertices;
+    (a + b + c) / 3.0
+  }
+
+  fn velocity(&self) -> Vector3<f32> {
+    Vector3::zero()
+  }
+}
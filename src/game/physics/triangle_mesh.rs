@@ -0,0 +1,223 @@
+use cgmath::{prelude::*, Matrix4, Quaternion, Vector3};
+
+use super::collider::Contact;
+use super::convex::{self, Convex};
+use super::triangle::Triangle;
+
+// Most triangles a BVH leaf holds before it's split
+const LEAF_SIZE: usize = 4;
+
+#[derive(Copy, Clone, Debug)]
+struct BvhNode {
+  min: Vector3<f32>,
+  max: Vector3<f32>,
+  // Leaves hold `count` triangles from `first`; other nodes have children at `first` and `first + 1`
+  first: usize,
+  count: usize,
+}
+
+/// Static level geometry as a soup of triangles, kept in a bounding volume hierarchy so that
+/// shapes only need testing against the few triangles near them.
+#[derive(Clone, Debug)]
+pub struct TriangleMesh {
+  triangles: Vec<Triangle>,
+  nodes: Vec<BvhNode>,
+}
+
+impl TriangleMesh {
+  pub fn new(mut triangles: Vec<Triangle>) -> TriangleMesh {
+    let mut nodes = vec![BvhNode {
+      min: Vector3::zero(),
+      max: Vector3::zero(),
+      first: 0,
+      count: 0,
+    }];
+    let len = triangles.len();
+    build_node(&mut triangles, &mut nodes, 0, 0, len);
+    TriangleMesh {
+      triangles,
+      nodes,
+    }
+  }
+
+  /// Loads the triangles of a glTF model. A node named "collision" is used if the model has one,
+  /// letting levels collide with simpler geometry than they render, otherwise the "root" mesh is.
+  pub fn from_gltf(path: &str) -> Result<TriangleMesh, String> {
+    let (file, buffers, _images) = gltf::import(path)
+      .map_err(|e| format!("Failed to read GLTF file for collision mesh {}: {}", path, e))?;
+    //the renderer ignores the root node's transform, so only a separate collision node's applies
+    let (node, transform) = match file.nodes().find(|n| n.name() == Some("collision")) {
+      Some(node) => {
+        let transform = Matrix4::from(node.transform().matrix());
+        (node, transform)
+      },
+      None => match file.nodes().find(|n| n.name() == Some("root")) {
+        Some(node) => (node, Matrix4::identity()),
+        None => return Err(format!("Collision mesh error in {}: no 'collision' or 'root' node found", path)),
+      },
+    };
+    let mesh = node.mesh().ok_or_else(|| format!("Collision mesh error in {}: node has no mesh", path))?;
+
+    let mut triangles = Vec::new();
+    for primitive in mesh.primitives() {
+      let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+      let positions: Vec<Vector3<f32>> = match reader.read_positions() {
+        Some(positions) => positions.map(|p| (transform * Vector3::from(p).extend(1.0)).truncate()).collect(),
+        None => return Err(format!("Collision mesh error in {}: mesh has no positions", path)),
+      };
+      let indices: Vec<usize> = match reader.read_indices() {
+        Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+        None => (0..positions.len()).collect(),
+      };
+      triangles.extend(indices.chunks_exact(3).map(|tri| Triangle {
+        vertices: [positions[tri[0]], positions[tri[1]], positions[tri[2]]],
+      }));
+    }
+    Ok(TriangleMesh::new(triangles))
+  }
+
+  pub fn triangles(&self) -> &[Triangle] {
+    &self.triangles
+  }
+
+  /// Minimum and maximum corners of the box around every triangle, in the mesh's own space.
+  pub fn bounds(&self) -> (Vector3<f32>, Vector3<f32>) {
+    if self.triangles.is_empty() {
+      return (Vector3::zero(), Vector3::zero());
+    }
+    (self.nodes[0].min, self.nodes[0].max)
+  }
+
+  /// Calls `visit` for every triangle whose bounds overlap the given box.
+  pub fn query(&self, min: Vector3<f32>, max: Vector3<f32>, mut visit: impl FnMut(&Triangle)) {
+    if self.triangles.is_empty() {
+      return;
+    }
+    let mut stack = vec![0];
+    while let Some(index) = stack.pop() {
+      let node = &self.nodes[index];
+      if !overlaps(node.min, node.max, min, max) {
+        continue;
+      }
+      if node.count > 0 {
+        for triangle in &self.triangles[node.first..node.first + node.count] {
+          let (tri_min, tri_max) = triangle.bounds();
+          if overlaps(tri_min, tri_max, min, max) {
+            visit(triangle);
+          }
+        }
+      } else {
+        stack.push(node.first);
+        stack.push(node.first + 1);
+      }
+    }
+  }
+}
+
+fn overlaps(a_min: Vector3<f32>, a_max: Vector3<f32>, b_min: Vector3<f32>, b_max: Vector3<f32>) -> bool {
+  a_min.x <= b_max.x && a_max.x >= b_min.x &&
+  a_min.y <= b_max.y && a_max.y >= b_min.y &&
+  a_min.z <= b_max.z && a_max.z >= b_min.z
+}
+
+/// The box around all eight corners of a box after transforming them.
+fn transform_bounds(min: Vector3<f32>, max: Vector3<f32>, transform: impl Fn(Vector3<f32>) -> Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
+  let mut out_min = Vector3::new(f32::MAX, f32::MAX, f32::MAX);
+  let mut out_max = Vector3::new(f32::MIN, f32::MIN, f32::MIN);
+  for i in 0..8 {
+    let corner = transform(Vector3::new(
+      if i & 1 == 0 { min.x } else { max.x },
+      if i & 2 == 0 { min.y } else { max.y },
+      if i & 4 == 0 { min.z } else { max.z },
+    ));
+    out_min = Vector3::new(out_min.x.min(corner.x), out_min.y.min(corner.y), out_min.z.min(corner.z));
+    out_max = Vector3::new(out_max.x.max(corner.x), out_max.y.max(corner.y), out_max.z.max(corner.z));
+  }
+  (out_min, out_max)
+}
+
+fn build_node(triangles: &mut [Triangle], nodes: &mut Vec<BvhNode>, index: usize, first: usize, end: usize) {
+  let mut min = Vector3::new(f32::MAX, f32::MAX, f32::MAX);
+  let mut max = Vector3::new(f32::MIN, f32::MIN, f32::MIN);
+  for triangle in &triangles[first..end] {
+    let (tri_min, tri_max) = triangle.bounds();
+    min = Vector3::new(min.x.min(tri_min.x), min.y.min(tri_min.y), min.z.min(tri_min.z));
+    max = Vector3::new(max.x.max(tri_max.x), max.y.max(tri_max.y), max.z.max(tri_max.z));
+  }
+
+  if end - first <= LEAF_SIZE {
+    nodes[index] = BvhNode {
+      min,
+      max,
+      first,
+      count: end - first,
+    };
+    return;
+  }
+
+  //split at the median centroid along the longest axis
+  let extent = max - min;
+  let axis = if extent.x >= extent.y && extent.x >= extent.z {
+    0
+  } else if extent.y >= extent.z {
+    1
+  } else {
+    2
+  };
+  triangles[first..end].sort_by(|a, b| a.center()[axis].total_cmp(&b.center()[axis]));
+  let mid = (first + end) / 2;
+
+  let children = nodes.len();
+  nodes.push(nodes[index]);
+  nodes.push(nodes[index]);
+  nodes[index] = BvhNode {
+    min,
+    max,
+    first: children,
+    count: 0,
+  };
+  build_node(triangles, nodes, children, first, mid);
+  build_node(triangles, nodes, children + 1, mid, end);
+}
+
+/// A triangle mesh placed in the world. Meshes never move, so the pose is fixed.
+#[derive(Copy, Clone, Debug)]
+pub struct MeshInstance<'m> {
+  pub mesh: &'m TriangleMesh,
+  pub position: Vector3<f32>,
+  pub rotation: Quaternion<f32>,
+}
+
+impl<'m> MeshInstance<'m> {
+  /// Minimum and maximum corners of the world space box around the placed mesh.
+  pub fn bounds(&self) -> (Vector3<f32>, Vector3<f32>) {
+    let (min, max) = self.mesh.bounds();
+    transform_bounds(min, max, |corner| self.rotation.rotate_vector(corner) + self.position)
+  }
+
+  /// The earliest contact within `max_toc` between a moving shape and any of the mesh's
+  /// triangles. The normal points from the mesh towards the shape.
+  pub fn sweep<A: Convex + ?Sized>(&self, shape: &A, max_toc: f32) -> Option<Contact> {
+    //bring the shape's swept bounds into the mesh's space to search the hierarchy
+    let (world_min, world_max) = shape.swept_bounds(max_toc);
+    let inverse = self.rotation.conjugate();
+    let (min, max) = transform_bounds(world_min, world_max, |corner| inverse.rotate_vector(corner - self.position));
+
+    let mut earliest: Option<Contact> = None;
+    self.mesh.query(min, max, |triangle| {
+      let world = Triangle {
+        vertices: triangle.vertices.map(|v| self.rotation.rotate_vector(v) + self.position),
+      };
+      if let Some(contact) = convex::sweep(shape, &world).filter(|contact| contact.toc <= max_toc) {
+        //prefer the earliest, then the deepest when already overlapping several
+        let better = earliest.is_none_or(|best| {
+          contact.toc < best.toc || (contact.toc == best.toc && contact.depth > best.depth)
+        });
+        if better {
+          earliest = Some(contact);
+        }
+      }
+    });
+    earliest
+  }
+}
@@ -27,12 +27,13 @@ impl Default for CameraState {
 
 impl CameraState {
     pub fn init(map: &Map) -> CameraState {
-        let mut camera = CameraState::default();
-        camera.v_fov = map.globals.v_fov_as_radians();
-        camera
+        CameraState {
+            v_fov: map.globals.v_fov_as_radians(),
+            ..CameraState::default()
+        }
     }
 
-    pub fn to_camera_matrix(&self, width: u32, height: u32, transform: &Transform) -> Matrix4<f32> {
+    pub fn to_camera_matrix(self, width: u32, height: u32, transform: &Transform) -> Matrix4<f32> {
         let proj: Matrix4<f32> = PerspectiveFov::<f32> {
             fovy: Rad::<f32>(self.v_fov),
             aspect: width as f32 / height as f32,
@@ -0,0 +1,545 @@
+use cgmath::{prelude::*, Quaternion, Rad, Vector3};
+use super::prelude::*;
+use super::query::{QueryFilter, RayHit};
+use super::physics_state::EARTH_GRAVITY;
+use crate::game::physics::Collider;
+use crate::game::tags::Character;
+
+// Gap kept between characters and what they walk on or into, so casts don't start touching
+const SKIN: f32 = 0.01;
+// Most times a move is redirected along what it bumps into in one tick
+const MAX_SLIDES: usize = 4;
+const BOOST_SCALE: f32 = 2.0;
+// Fraction of walking speed when swimming up
+const SWIM_SCALE: f32 = 0.5;
+// How fast swimmers sink when not swimming up
+const SINK_SPEED: f32 = 1.0;
+
+state_nodef! {
+    pub struct CharacterState {
+        pub grounded: bool,
+        // Up when airborne
+        pub ground_normal: Vector3<f32>,
+        pub ground_object: SaltyId,
+        pub crouched: bool,
+        // The physics collider is swapped for a shorter one while crouched
+        pub standing: Collider,
+    }
+}
+
+impl CharacterState {
+    pub fn init(standing: Collider) -> CharacterState {
+        CharacterState {
+            grounded: false,
+            ground_normal: Vector3::unit_z(),
+            ground_object: NONE,
+            crouched: false,
+            standing,
+        }
+    }
+}
+
+/// Where a move ended up, and the velocity left after removing what pushed into surfaces.
+struct Slide {
+    position: Vector3<f32>,
+    velocity: Vector3<f32>,
+    blocked: bool,
+}
+
+impl GameState {
+    /// Moves every object with a character controller for one tick. Characters are kinematic:
+    /// rather than being pushed around by the solver they sweep their shape through the world,
+    /// sliding along whatever they touch.
+    pub(crate) fn update_characters(&mut self, map: &Map, dt: f32) {
+        let ids: Vec<SaltyId> = self.objects.iter()
+            .filter(|(_, object_state)| {
+                object_state.riding == NONE &&
+                    self.physics.get(object_state.physics_id).is_some_and(|physics_state| physics_state.character.is_some())
+            })
+            .map(|(id, _)| id)
+            .collect();
+        for id in ids {
+            self.update_character(map, id, dt);
+        }
+    }
+
+    fn update_character(&mut self, map: &Map, id: SaltyId, dt: f32) {
+        let object_state = *self.objects.get(id).unwrap();
+        let mut physics_state = *self.physics.get(object_state.physics_id).unwrap();
+        let physics_tag = match map.get_physics(&physics_state.tag) {
+            Some(physics_tag) => physics_tag,
+            None => return,
+        };
+        let tag = match &physics_tag.character {
+            Some(tag) => tag,
+            None => return,
+        };
+        let mut character = physics_state.character.unwrap();
+        let filter = QueryFilter::default().excluding(id).with_mask(physics_state.mask);
+        let mut position = object_state.transform.position;
+        let mut velocity = physics_state.velocity;
+        let was_grounded = character.grounded;
+
+        //riders go wherever what they're standing on went, lifted straight up out of the way
+        //of rising platforms and swept across by them
+        let carried = if was_grounded {
+            self.mover_movement(character.ground_object)
+        } else {
+            Vector3::zero()
+        };
+        position.z += carried.z;
+
+        //only the possessed object listens to the controls
+        let controls = if id == self.player_control.target_object {
+            Some(self.player_control)
+        } else {
+            None
+        };
+
+        //crouching keeps the feet in place, and standing back up needs headroom
+        let (crouched_collider, drop) = crouched(character.standing, tag.crouch_scale());
+        let wants_crouch = controls.is_some_and(|controls| controls.down);
+        if wants_crouch && !character.crouched {
+            character.crouched = true;
+            position.z -= drop;
+        } else if !wants_crouch && character.crouched {
+            let placed = crouched_collider.placed(position, Quaternion::one(), Vector3::zero());
+            if self.shape_cast(map, &placed, Vector3::unit_z(), drop * 2.0, &filter).is_none() {
+                character.crouched = false;
+                position.z += drop;
+            }
+        }
+        let collider = if character.crouched {
+            crouched_collider
+        } else {
+            character.standing
+        };
+
+        //steer towards where the controls point, along the ground
+        let mut wish = Vector3::zero();
+        if let Some(controls) = controls {
+            let mut movement = controls.get_movement_vector();
+            movement.z = 0.0;
+            if movement.magnitude2() > 0.0 {
+                let yaw: Quaternion<f32> = Quaternion::from_angle_z(Rad(-controls.yaw));
+                wish = yaw.rotate_vector(movement.normalize());
+            }
+            let mut speed = tag.walk_speed();
+            if character.crouched {
+                speed *= tag.crouch_scale();
+            }
+            if controls.boost {
+                speed *= BOOST_SCALE;
+            }
+            wish *= speed;
+        }
+        //in water there's something to push against even off the ground
+        let swimming = physics_state.submerged && !character.grounded;
+        let mut accel = map.globals.player_accel * dt;
+        if !character.grounded && !swimming {
+            accel *= tag.air_control();
+        }
+        let horizontal = Vector3::new(velocity.x, velocity.y, 0.0);
+        let change = wish - horizontal;
+        let change = if change.magnitude() > accel {
+            change.normalize_to(accel)
+        } else {
+            change
+        };
+        velocity += change;
+
+        let jumped = character.grounded && controls.is_some_and(|controls| controls.up);
+        if jumped {
+            velocity.z = tag.jump_speed();
+            character.grounded = false;
+        } else if character.grounded {
+            //follow the slope underfoot at the same horizontal speed
+            let normal = character.ground_normal;
+            if normal.z > 0.0 {
+                velocity.z = -(normal.x * velocity.x + normal.y * velocity.y) / normal.z;
+            }
+        } else if swimming {
+            let target = if controls.is_some_and(|controls| controls.up) {
+                tag.walk_speed() * SWIM_SCALE
+            } else {
+                -SINK_SPEED
+            };
+            velocity.z += (target - velocity.z).clamp(-accel, accel);
+        } else {
+            velocity.z -= EARTH_GRAVITY * self.gravity * physics_tag.gravity_scale() * dt;
+        }
+
+        let min_normal_z = tag.min_ground_normal_z();
+        let motion = velocity * dt + Vector3::new(carried.x, carried.y, 0.0);
+        let mut slide = self.slide(map, &collider, position, motion, velocity, character.grounded, min_normal_z, &filter);
+        if slide.blocked && character.grounded {
+            if let Some(stepped) = self.step_up(map, &collider, position, motion, velocity, tag, &filter) {
+                let plain = slide.position - position;
+                let climbed = stepped.position - position;
+                if climbed.x * climbed.x + climbed.y * climbed.y > plain.x * plain.x + plain.y * plain.y {
+                    slide = stepped;
+                }
+            }
+        }
+        position = slide.position;
+        velocity = slide.velocity;
+
+        //find the ground, sticking to it when walking down slopes and steps
+        character.grounded = false;
+        character.ground_normal = Vector3::unit_z();
+        character.ground_object = NONE;
+        //walking uphill moves upwards too, so only leaving the ground by jumping or falling counts
+        if !jumped && (was_grounded || velocity.z <= 0.0) {
+            let reach = if was_grounded {
+                tag.step_height() + SKIN * 2.0
+            } else {
+                SKIN * 2.0
+            };
+            if let Some(hit) = self.find_ground(map, &collider, position, reach, min_normal_z, &filter) {
+                position.z -= (hit.distance - SKIN).max(0.0);
+                velocity.z = 0.0;
+                character.grounded = true;
+                character.ground_normal = hit.normal;
+                character.ground_object = hit.object;
+            }
+        }
+
+        physics_state.prev_transform = object_state.transform;
+        physics_state.velocity = velocity;
+        physics_state.collider = Some(collider);
+        physics_state.character = Some(character);
+        *self.physics.get_mut(object_state.physics_id).unwrap() = physics_state;
+        self.objects.get_mut(id).unwrap().transform.position = position;
+    }
+
+    /// Moves the collider by `motion`, redirecting whatever's left along each surface it hits.
+    /// Walking characters treat slopes too steep to climb as walls, rather than riding up them.
+    #[allow(clippy::too_many_arguments)]
+    fn slide(&self, map: &Map, collider: &Collider, mut position: Vector3<f32>, motion: Vector3<f32>, mut velocity: Vector3<f32>, grounded: bool, min_normal_z: f32, filter: &QueryFilter) -> Slide {
+        let mut remaining = motion;
+        let mut blocked = false;
+        for _ in 0..MAX_SLIDES {
+            let distance = remaining.magnitude();
+            if distance < 1e-6 {
+                break;
+            }
+            let direction = remaining / distance;
+            let placed = collider.placed(position, Quaternion::one(), Vector3::zero());
+            let hit: RayHit = match self.shape_cast(map, &placed, direction, distance + SKIN, filter) {
+                Some(hit) => hit,
+                None => {
+                    position += remaining;
+                    break;
+                },
+            };
+            let travel = (hit.distance - SKIN).max(0.0).min(distance);
+            position += direction * travel;
+            remaining -= direction * travel;
+
+            let mut normal = hit.normal;
+            if normal.z < min_normal_z {
+                blocked = true;
+                if grounded && normal.z > 0.0 {
+                    normal.z = 0.0;
+                    if normal.magnitude2() < 1e-6 {
+                        continue;
+                    }
+                    normal = normal.normalize();
+                }
+            }
+            let into = remaining.dot(normal);
+            if into < 0.0 {
+                remaining -= normal * into;
+            }
+            let into = velocity.dot(normal);
+            if into < 0.0 {
+                velocity -= normal * into;
+            }
+        }
+        Slide {
+            position,
+            velocity,
+            blocked,
+        }
+    }
+
+    /// Tries the same move lifted by the step height and then set back down, for walking up
+    /// ledges. Only succeeds if the character lands on walkable ground.
+    #[allow(clippy::too_many_arguments)]
+    fn step_up(&self, map: &Map, collider: &Collider, position: Vector3<f32>, motion: Vector3<f32>, velocity: Vector3<f32>, tag: &Character, filter: &QueryFilter) -> Option<Slide> {
+        let min_normal_z = tag.min_ground_normal_z();
+        let placed = collider.placed(position, Quaternion::one(), Vector3::zero());
+        let lift = match self.shape_cast(map, &placed, Vector3::unit_z(), tag.step_height() + SKIN, filter) {
+            Some(hit) => (hit.distance - SKIN).max(0.0),
+            None => tag.step_height(),
+        };
+        let raised = position + Vector3::unit_z() * lift;
+        let across = Vector3::new(motion.x, motion.y, 0.0);
+        let mut slide = self.slide(map, collider, raised, across, velocity, true, min_normal_z, filter);
+
+        let hit = self.find_ground(map, collider, slide.position, lift + SKIN, min_normal_z, filter)?;
+        slide.position.z -= (hit.distance - SKIN).max(0.0);
+        Some(slide)
+    }
+
+    /// Walkable ground within `reach` below the collider. The normal is that of the surface
+    /// underfoot: a rounded shape resting on the edge of a step touches it at an angle, but is
+    /// still standing on the step.
+    fn find_ground(&self, map: &Map, collider: &Collider, position: Vector3<f32>, reach: f32, min_normal_z: f32, filter: &QueryFilter) -> Option<RayHit> {
+        let placed = collider.placed(position, Quaternion::one(), Vector3::zero());
+        let mut hit = self.shape_cast(map, &placed, -Vector3::unit_z(), reach, filter)?;
+        if hit.normal.z >= min_normal_z {
+            return Some(hit);
+        }
+
+        //look just past the contact, away from the collider, at what it's resting on
+        let mut outwards = hit.point - position;
+        outwards.z = 0.0;
+        if outwards.magnitude2() < 1e-6 || hit.normal.z <= 0.0 {
+            return None;
+        }
+        let origin = hit.point + outwards.normalize_to(SKIN * 2.0) + Vector3::unit_z() * SKIN;
+        let surface = self.raycast(map, origin, -Vector3::unit_z(), SKIN * 3.0, filter)?;
+        if surface.object != hit.object || surface.normal.z < min_normal_z {
+            return None;
+        }
+        hit.normal = surface.normal;
+        Some(hit)
+    }
+}
+
+/// The collider shrunk for crouching, and how far its center drops to keep the feet in place.
+fn crouched(standing: Collider, scale: f32) -> (Collider, f32) {
+    match standing {
+        Collider::Capsule(mut capsule) => {
+            let height = (capsule.half_height + capsule.radius) * scale;
+            let half_height = (height - capsule.radius).max(0.0);
+            let drop = capsule.half_height - half_height;
+            capsule.half_height = half_height;
+            (Collider::Capsule(capsule), drop)
+        },
+        Collider::OrientedBox(mut oriented_box) => {
+            let drop = oriented_box.half_extents.z * (1.0 - scale);
+            oriented_box.half_extents.z -= drop;
+            (Collider::OrientedBox(oriented_box), drop)
+        },
+        Collider::Aabb(mut aabb) => {
+            let drop = aabb.half_extents.z * (1.0 - scale);
+            aabb.half_extents.z -= drop;
+            (Collider::Aabb(aabb), drop)
+        },
+        //spheres can't get any shorter
+        other => (other, 0.0),
+    }
+}
+
+mod tests {
+    use super::*;
+    use super::super::test_map::test_map;
+    use crate::math::realcmp;
+
+    const TEST_MAP: &str = r#"
+        [physics.biped]
+        mass = 1.0
+        shape = {type = "capsule", radius = 0.4, half_height = 0.5}
+        character = {walk_speed = 4.0, step_height = 0.4}
+
+        [physics.floor]
+        mass = 0.0
+        shape = {type = "box", half_extents = [20.0, 20.0, 1.0]}
+
+        [physics.ledge]
+        mass = 0.0
+        shape = {type = "box", half_extents = [5.0, 5.0, 0.15]}
+
+        [physics.wall]
+        mass = 0.0
+        shape = {type = "box", half_extents = [1.0, 5.0, 2.0]}
+
+        [physics.ramp]
+        mass = 0.0
+        shape = {type = "box", half_extents = [2.0, 4.0, 0.2]}
+
+        [object.player]
+        physics = "biped"
+
+        [object.floor]
+        physics = "floor"
+        model = "maps/cube.gltf"
+        colour = [0.5, 0.5, 0.5]
+
+        [object.ledge]
+        physics = "ledge"
+        model = "maps/cube.gltf"
+        colour = [0.5, 0.5, 0.5]
+
+        [object.wall]
+        physics = "wall"
+        model = "maps/cube.gltf"
+        colour = [0.5, 0.5, 0.5]
+
+        [object.ramp]
+        physics = "ramp"
+        model = "maps/cube.gltf"
+        colour = [0.5, 0.5, 0.5]
+
+        [scenario]
+        player_location = {pos = [0.0, 0.0, 3.0]}
+
+        [[scenario.scenery]]
+        object_type = "floor"
+        position = {pos = [0.0, 0.0, -1.0]}
+
+        [[scenario.scenery]]
+        object_type = "ledge"
+        position = {pos = [8.0, 0.0, 0.15]}
+
+        [[scenario.scenery]]
+        object_type = "wall"
+        position = {pos = [-5.0, 0.0, 2.0]}
+
+        [[scenario.scenery]]
+        object_type = "ramp"
+        position = {pos = [0.0, 6.0, 0.0], rot = [0.0, 0.0, 30.0]}
+
+        [[scenario.scenery]]
+        object_type = "ramp"
+        position = {pos = [0.0, -6.0, 0.0], rot = [0.0, 0.0, -60.0]}
+    "#;
+
+    // Height of the capsule's center above its feet when standing
+    const STANDING: f32 = 0.9;
+
+    // Long enough to have fallen to the floor from the starting height
+    const LANDED: u32 = 120;
+
+    fn load() -> Map {
+        test_map(TEST_MAP)
+    }
+
+    fn player(state: &GameState) -> (Vector3<f32>, CharacterState) {
+        let object_state = state.objects.get(state.player_control.target_object).unwrap();
+        let physics_state = state.physics.get(object_state.physics_id).unwrap();
+        (object_state.transform.position, physics_state.character.unwrap())
+    }
+
+    #[test]
+    fn test_character_lands() {
+        let map = load();
+        let mut state = GameState::init(&map);
+        state.advance_to_tick(&map, LANDED);
+        let (position, character) = player(&state);
+        assert!(character.grounded);
+        assert!(realcmp(position.z, STANDING, 0.02), "resting at {:?}", position);
+        assert!(realcmp(character.ground_normal.z, 1.0, 0.001));
+    }
+
+    #[test]
+    fn test_character_jumps() {
+        let map = load();
+        let mut state = GameState::init(&map);
+        state.advance_to_tick(&map, LANDED);
+        state.player_control.up = true;
+        state.advance_to_tick(&map, LANDED + 1);
+        state.player_control.up = false;
+        let (_, character) = player(&state);
+        assert!(!character.grounded);
+
+        //a 5m/s jump peaks about 1.27m up and comes back down within a second
+        let mut peak: f32 = 0.0;
+        for tick in LANDED + 2..LANDED + 70 {
+            state.advance_to_tick(&map, tick);
+            peak = peak.max(player(&state).0.z);
+        }
+        assert!(realcmp(peak, STANDING + 1.27, 0.1), "peaked at {}", peak);
+        let (position, character) = player(&state);
+        assert!(character.grounded);
+        assert!(realcmp(position.z, STANDING, 0.02));
+    }
+
+    #[test]
+    fn test_character_steps_up() {
+        let map = load();
+        let mut state = GameState::init(&map);
+        state.advance_to_tick(&map, LANDED);
+        state.player_control.forward = true;
+        state.advance_to_tick(&map, LANDED + 120);
+        let (position, character) = player(&state);
+        assert!(position.x > 4.0, "stopped at {:?}", position);
+        assert!(character.grounded);
+        assert!(realcmp(position.z, STANDING + 0.3, 0.02), "standing at {:?}", position);
+    }
+
+    #[test]
+    fn test_character_blocked_by_wall() {
+        let map = load();
+        let mut state = GameState::init(&map);
+        state.advance_to_tick(&map, LANDED);
+        state.player_control.back = true;
+        state.advance_to_tick(&map, LANDED + 120);
+        let (position, character) = player(&state);
+        assert!(character.grounded);
+        assert!(position.x >= -3.6 - 0.02, "walked into the wall to {:?}", position);
+        assert!(position.x <= -3.5, "stopped short at {:?}", position);
+        assert!(realcmp(position.z, STANDING, 0.02));
+    }
+
+    #[test]
+    fn test_character_slope_limit() {
+        let map = load();
+        let mut state = GameState::init(&map);
+        state.advance_to_tick(&map, LANDED);
+
+        //30 degrees is walkable
+        state.player_control.left = true;
+        state.advance_to_tick(&map, LANDED + 120);
+        let (position, character) = player(&state);
+        assert!(character.grounded);
+        assert!(position.y > 7.0 && position.z > STANDING + 1.0, "stopped at {:?}", position);
+        assert!(realcmp(character.ground_normal.z, 30f32.to_radians().cos(), 0.01));
+
+        //60 degrees isn't
+        state.player_control.left = false;
+        state.player_control.right = true;
+        state.advance_to_tick(&map, LANDED + 480);
+        let (position, character) = player(&state);
+        assert!(character.grounded);
+        assert!(position.y > -6.0 && position.z < STANDING + 0.5, "climbed to {:?}", position);
+    }
+
+    #[test]
+    fn test_character_slope_limit_short_of_vertical() {
+        let map = test_map(&TEST_MAP.replace("step_height = 0.4}", "step_height = 0.4, max_slope = 90.0}"));
+        let tag = map.physics.values().find_map(|physics_tag| physics_tag.character.as_ref()).unwrap();
+        assert!(tag.min_ground_normal_z() > 0.0);
+
+        //walls still block rather than being walked up
+        let mut state = GameState::init(&map);
+        state.advance_to_tick(&map, LANDED);
+        state.player_control.back = true;
+        state.advance_to_tick(&map, LANDED + 120);
+        let (position, character) = player(&state);
+        assert!(character.grounded);
+        assert!(position.x >= -3.6 - 0.02, "walked into the wall to {:?}", position);
+        assert!(realcmp(position.z, STANDING, 0.02), "standing at {:?}", position);
+    }
+
+    #[test]
+    fn test_character_crouches() {
+        let map = load();
+        let mut state = GameState::init(&map);
+        state.advance_to_tick(&map, LANDED);
+        state.player_control.down = true;
+        state.advance_to_tick(&map, LANDED + 10);
+        let (position, character) = player(&state);
+        assert!(character.crouched);
+        assert!(character.grounded);
+        assert!(realcmp(position.z, STANDING * 0.5, 0.02), "crouching at {:?}", position);
+
+        state.player_control.down = false;
+        state.advance_to_tick(&map, LANDED + 20);
+        let (position, character) = player(&state);
+        assert!(!character.crouched);
+        assert!(realcmp(position.z, STANDING, 0.02));
+    }
+}
@@ -8,12 +8,25 @@ use super::player_control::PlayerControl;
 use super::object_state::ObjectState;
 use super::transform::Transform;
 use super::PhysicsState;
+use super::trigger_state::{TriggerEvent, TriggerState};
+use super::joint_state::JointState;
+use super::water_state::WaterState;
+use super::query::QueryFilter;
+use super::physics_state::EARTH_GRAVITY;
 use crate::game::PlayerAction;
+use crate::game::physics::{self, BodyJoint, Broadphase, Contact, Islands, MeshInstance};
+use crate::game::physics::solver::{self, Body, BodyShape};
+use crate::game::tags::{Shape, TagString};
 
-const GRAV: f32 = 0.1;
 const TICK_RATE: u32 = 60;
 const TICK_DURATION_NANOS: u32 = 1000000000 / TICK_RATE;
-const TICK_DURATION_SEC: f32 = 1.0 / TICK_RATE as f32;
+pub(crate) const TICK_DURATION_SEC: f32 = 1.0 / TICK_RATE as f32;
+// Bodies slower than these for long enough fall asleep
+const SLEEP_SPEED: f32 = 0.05;
+const SLEEP_ANGULAR_SPEED: f32 = 0.05;
+const SLEEP_TICKS: u32 = TICK_RATE / 2;
+// Fraction of damping left on a flying player while boosting
+const BOOST_DAMPING_SCALE: f32 = 0.1;
 // const MAX_TICKS_PER_FRAME: u32 = 10; //todo: prevent spiral of death
 
 #[derive(Copy, Clone)]
@@ -28,11 +41,19 @@ pub struct GameState {
     pub camera: CameraState,
     pub objects: SaltyBuffer<ObjectState, 1024>,
     pub physics: SaltyBuffer<PhysicsState, 1024>,
+    // Keyed by object id
+    pub broadphase: Broadphase<1024>,
+    pub triggers: SaltyBuffer<TriggerState, 64>,
+    // Waiting to be taken by game logic, see `take_trigger_events`
+    pub trigger_events: FixedRingBuffer<TriggerEvent, 256>,
+    pub joints: SaltyBuffer<JointState, 256>,
+    pub waters: SaltyBuffer<WaterState, 16>,
 }
 
 impl GameState {
-    pub fn init(map: &Map) -> GameState {
-        let mut state = GameState {
+    /// Sets up the map's scenario. The state is boxed since it's too big to keep on the stack.
+    pub fn init(map: &Map) -> Box<GameState> {
+        let mut state = Box::new(GameState {
             prev_time: SystemTime::now(),
             accum_nanos: 0,
             tick: 0,
@@ -41,7 +62,12 @@ impl GameState {
             camera: CameraState::init(map),
             objects: SaltyBuffer::<ObjectState, 1024>::new(),
             physics: SaltyBuffer::<PhysicsState, 1024>::new(),
-        };
+            broadphase: Broadphase::<1024>::new(),
+            triggers: SaltyBuffer::<TriggerState, 64>::new(),
+            trigger_events: FixedRingBuffer::<TriggerEvent, 256>::new(),
+            joints: SaltyBuffer::<JointState, 256>::new(),
+            waters: SaltyBuffer::<WaterState, 16>::new(),
+        });
 
         state.player_control.target_object = ObjectState::init(
             &mut state,
@@ -49,19 +75,46 @@ impl GameState {
             &map.globals.player_object,
             map.scenario.player_location.to_transform()
         );
+        state.player_control.own_object = state.player_control.target_object;
         state.camera.object_attachment = state.player_control.target_object;
 
+        let mut named: Vec<(TagString, SaltyId)> = Vec::new();
         if let Some(ref scenery_vec) = map.scenario.scenery {
             for scenery in scenery_vec {
-                ObjectState::init(
+                let object_id = ObjectState::init(
                     &mut state,
                     map,
                     &scenery.object_type,
                     scenery.position.to_transform()
                 );
+                if let Some(name) = scenery.name {
+                    named.push((name, object_id));
+                }
+            }
+        }
+
+        if let Some(ref water_vec) = map.scenario.water {
+            for water in water_vec {
+                WaterState::init(&mut state, water);
+            }
+        }
+
+        if let Some(ref joint_vec) = map.scenario.joints {
+            for joint in joint_vec {
+                JointState::init(&mut state, joint, &named);
+            }
+        }
+
+        if let Some(ref trigger_vec) = map.scenario.triggers {
+            for trigger in trigger_vec {
+                TriggerState::init(&mut state, map, trigger);
             }
         }
 
+        //so queries work before the first tick
+        let (body_ids, bodies) = state.bodies(map);
+        state.update_broadphase(&body_ids, &bodies, &vec![false; bodies.len()]);
+
         state
     }
 
@@ -105,6 +158,9 @@ impl GameState {
                     PlayerAction::Quit => {
                         return false;
                     },
+                    PlayerAction::Use => {
+                        self.use_target(map);
+                    },
                     action => {
                         self.apply_action(action);
                     },
@@ -125,52 +181,784 @@ impl GameState {
 
     pub fn update_variable(&mut self, map: &Map) {
         //player control physics
+        //vehicles steer themselves in fixed updates, and face the way they're driven
+        let drives = self.vehicle_tag(map, self.player_control.target_object).is_some();
         if let Some(player_state) = self.objects.get_mut(self.player_control.target_object) {
-            if let Some(physics_state) = self.physics.get_mut(player_state.physics_id) {
-                player_state.transform.rotation = self.player_control.get_aim_rot();
+            let physics_state = self.physics.get_mut(player_state.physics_id);
+            if drives {
+                return;
+            }
+            //objects without physics, like turrets, only turn to aim
+            player_state.transform.rotation = self.player_control.get_aim_rot();
+            if let Some(physics_state) = physics_state {
+                //walking characters steer themselves in fixed updates
+                if physics_state.character.is_some() {
+                    return;
+                }
 
                 let mut movement_vec = self.player_control.get_movement_vector();
                 movement_vec = player_state.transform.rotation.rotate_vector(movement_vec);
 
+                //slowed by the physics tag's damping in fixed updates
                 physics_state.velocity += movement_vec * map.globals.player_accel * TICK_DURATION_SEC;
+            }
+        }
+    }
 
-                let mut drag = map.globals.player_drag_scale * (
-                    physics_state.velocity.magnitude() +
-                    physics_state.velocity.magnitude2()
-                );
-                if self.player_control.boost {
-                    drag *= 0.1;
+    pub fn update_fixed(&mut self, map: &Map) {
+        self.update_movers(map, TICK_DURATION_SEC);
+        self.update_water(map, TICK_DURATION_SEC);
+        self.update_characters(map, TICK_DURATION_SEC);
+        self.update_vehicles(map, TICK_DURATION_SEC);
+
+        //gravity goes into velocity before anything moves, so contacts can hold bodies up, and
+        //damping and terminal velocity slow it down again
+        let gravity = Vector3::unit_z() * -EARTH_GRAVITY * self.gravity * TICK_DURATION_SEC;
+        //flying players boost by pushing through the air more easily
+        let boosted = self.objects.get(self.player_control.target_object)
+            .filter(|_| self.player_control.boost)
+            .map_or(NONE, |player_state| player_state.physics_id);
+        for (id, physics_state) in self.physics.iter_mut() {
+            if physics_state.is_kinematic() || physics_state.asleep {
+                continue;
+            }
+            let physics_tag = match map.get_physics(&physics_state.tag) {
+                Some(physics_tag) => physics_tag,
+                None => continue,
+            };
+            if physics_tag.inverse_mass() > 0.0 {
+                physics_state.velocity += gravity * physics_tag.gravity_scale();
+            }
+            let mut linear_damping = physics_tag.linear_damping();
+            if id == boosted {
+                linear_damping *= BOOST_DAMPING_SCALE;
+            }
+            physics_state.velocity *= (1.0 - linear_damping * TICK_DURATION_SEC).max(0.0);
+            physics_state.angular_velocity *= (1.0 - physics_tag.angular_damping() * TICK_DURATION_SEC).max(0.0);
+            let max_speed = physics_tag.max_speed();
+            if physics_state.velocity.magnitude() > max_speed {
+                physics_state.velocity = physics_state.velocity.normalize_to(max_speed);
+            }
+        }
+
+        //where every collidable object starts this tick
+        let (body_ids, mut bodies) = self.bodies(map);
+        let mut asleep: Vec<bool> = body_ids.iter()
+            .map(|id| self.object_physics(*id).is_some_and(|physics_state| physics_state.asleep))
+            .collect();
+        self.update_broadphase(&body_ids, &bodies, &asleep);
+
+        //only bodies whose swept bounds overlap can touch this tick, and only if one is moving
+        let active: Vec<bool> = bodies.iter().zip(asleep.iter())
+            .map(|(body, asleep)| !asleep && (body.inverse_mass > 0.0 || body.velocity.magnitude2() > 0.0))
+            .collect();
+        let mut body_indices: Vec<Option<usize>> = vec![None; self.objects.capacity() as usize];
+        for (i, id) in body_ids.iter().enumerate() {
+            body_indices[id.index()] = Some(i);
+        }
+        let mut joints = self.body_joints(&body_indices);
+        //jointed bodies are kept together by their joint, so don't collide with each other
+        let jointed = |i: usize, j: usize| joints.iter().any(|joint| {
+            (joint.a, joint.b) == (i, Some(j)) || (joint.a, joint.b) == (j, Some(i))
+        });
+        let mut pairs: Vec<(usize, usize)> = Vec::new();
+        self.broadphase.pairs(|a, b| {
+            if let (Some(i), Some(j)) = (body_indices[a.index()], body_indices[b.index()]) {
+                if (active[i] || active[j]) && !jointed(i, j) {
+                    pairs.push((i, j));
                 }
-                if physics_state.velocity.magnitude2() != 0.0 {
-                    physics_state.velocity -= physics_state.velocity.normalize_to(drag) * TICK_DURATION_SEC;
+            }
+        });
+        let mut contacts = solver::find_contacts(&bodies, &pairs, TICK_DURATION_SEC);
+
+        //wake whole islands when anything in them is awake, or when something kinematic pushes them
+        let mut islands = Islands::new(&bodies, &contacts);
+        for joint in &joints {
+            if let Some(b) = joint.b.filter(|b| bodies[*b].inverse_mass > 0.0 && bodies[joint.a].inverse_mass > 0.0) {
+                islands.join(joint.a, b);
+            }
+        }
+        let mut island_awake = vec![false; bodies.len()];
+        for (i, body) in bodies.iter().enumerate() {
+            if !asleep[i] && body.inverse_mass > 0.0 {
+                island_awake[islands.root(i)] = true;
+            }
+        }
+        for c in &contacts {
+            for (pusher, pushed) in [(c.a, c.b), (c.b, c.a)] {
+                let pusher = &bodies[pusher];
+                if pusher.inverse_mass == 0.0 && pusher.velocity.magnitude() > SLEEP_SPEED {
+                    island_awake[islands.root(pushed)] = true;
+                }
+            }
+        }
+        for i in 0..bodies.len() {
+            if asleep[i] && island_awake[islands.root(i)] {
+                asleep[i] = false;
+                if let Some(physics_state) = self.object_physics_mut(body_ids[i]) {
+                    physics_state.wake();
                 }
             }
         }
-    }
 
-    pub fn update_fixed(&mut self, map: &Map) {
+        //collision response, then joints
+        solver::solve_velocities(&mut bodies, &mut contacts);
+        let corrections = solver::correct_positions(&bodies, &contacts);
+        joints.retain(|joint| !asleep[joint.a] || joint.b.is_some_and(|b| !asleep[b]));
+        physics::solve_joints(&mut bodies, &mut joints, TICK_DURATION_SEC);
+        for (id, body) in body_ids.iter().zip(bodies.iter()) {
+            if let Some(physics_state) = self.object_physics_mut(*id) {
+                physics_state.velocity = body.velocity;
+                physics_state.angular_velocity = body.angular_velocity;
+            }
+        }
+
         //physics to position
         for (_id, object_state) in self.objects.iter_mut() {
             if let Some(object_tag) = map.get_object(&object_state.tag) {
                 if let Some(physics_tag_id) = object_tag.physics {
-                    if let Some(physics_tag) = map.get_physics(&physics_tag_id) {
+                    if let Some(_physics_tag) = map.get_physics(&physics_tag_id) {
                         if let Some(physics_state) = self.physics.get_mut(object_state.physics_id) {
-                            // let grav = GRAV * physics_tag.mass * 1.0 / object_state.transform.position.magnitude2();
-                            // physics_state.velocity += (-object_state.transform.position) * grav;
-                            
+                            //characters and movers have already moved
+                            if physics_state.is_kinematic() || physics_state.asleep {
+                                continue;
+                            }
                             physics_state.prev_transform = object_state.transform;
                             object_state.transform.position += physics_state.velocity * TICK_DURATION_SEC;
-                            object_state.transform.rotation += physics_state.angular_velocity * TICK_DURATION_SEC;
+                            let (rotation, angular_velocity) = solver::integrate_rotation(
+                                object_state.transform.rotation,
+                                physics_state.angular_velocity,
+                                physics_state.inverse_inertia,
+                                TICK_DURATION_SEC
+                            );
+                            object_state.transform.rotation = rotation;
+                            physics_state.angular_velocity = angular_velocity;
                         }
                     }
                 }
             }
         }
+
+        //push apart anything left overlapping
+        for (id, correction) in body_ids.iter().zip(corrections.iter()) {
+            if let Some(object_state) = self.objects.get_mut(*id) {
+                object_state.transform.position += *correction;
+            }
+        }
+        self.sweep_fast_bodies(map);
+
+        self.update_sleep(&body_ids, &bodies, &asleep, &mut islands);
+        self.update_triggers();
     
         self.tick = self.tick.wrapping_add(1);
     }
 
+    /// Stops fast bodies at the first thing they hit on the way from where they were last tick,
+    /// in case they moved far enough to pass through it.
+    fn sweep_fast_bodies(&mut self, map: &Map) {
+        let fast_ids: Vec<SaltyId> = self.objects.iter()
+            .filter(|(id, _)| self.object_physics(*id).is_some_and(|physics_state| {
+                !physics_state.is_kinematic() && !physics_state.asleep &&
+                    map.get_physics(&physics_state.tag).is_some_and(|physics_tag| physics_tag.fast())
+            }))
+            .map(|(id, _)| id)
+            .collect();
+
+        for id in fast_ids {
+            let transform = self.objects.get(id).unwrap().transform;
+            let physics_state = self.object_physics(id).unwrap();
+            let prev_transform = physics_state.prev_transform;
+            let movement = transform.position - prev_transform.position;
+            //moving the whole way in unit time makes the time of impact the fraction travelled
+            let cast = match physics_state.world_collider(&prev_transform) {
+                Some(collider) => collider.placed(prev_transform.position, prev_transform.rotation, movement),
+                None => continue,
+            };
+            let (min, max) = match cast.as_convex() {
+                Some(convex) if movement.magnitude2() > 0.0 => convex.swept_bounds(1.0),
+                _ => continue,
+            };
+            let filter = QueryFilter::default().excluding(id).with_mask(physics_state.mask);
+
+            let mut first: Option<Contact> = None;
+            self.broadphase.query(min, max, |other| {
+                if other == filter.exclude {
+                    return;
+                }
+                //anything already touching at the start was in this tick's contacts
+                if let Some(contact) = self.cast_against(map, other, &cast, 1.0, &filter).filter(|contact| contact.toc > 0.0) {
+                    if first.is_none_or(|first| contact.toc < first.toc) {
+                        first = Some(contact);
+                    }
+                }
+            });
+            if let Some(contact) = first.filter(|contact| contact.toc < 1.0) {
+                let object_state = self.objects.get_mut(id).unwrap();
+                object_state.transform = Transform::interpolate(&prev_transform, &transform, contact.toc);
+                //and stops moving into whatever it hit, so it doesn't carry on through next tick
+                let physics_state = self.object_physics_mut(id).unwrap();
+                physics_state.velocity -= contact.normal * physics_state.velocity.dot(contact.normal).min(0.0);
+            }
+        }
+    }
+
+    /// Puts islands to sleep once every body in them has been resting for long enough.
+    fn update_sleep(&mut self, body_ids: &[SaltyId], bodies: &[Body], asleep: &[bool], islands: &mut Islands) {
+        let mut island_rest = vec![u32::MAX; bodies.len()];
+        for (i, body) in bodies.iter().enumerate() {
+            if asleep[i] || body.inverse_mass == 0.0 {
+                continue;
+            }
+            if let Some(physics_state) = self.object_physics_mut(body_ids[i]) {
+                let resting = physics_state.velocity.magnitude() < SLEEP_SPEED &&
+                    physics_state.angular_velocity.magnitude() < SLEEP_ANGULAR_SPEED;
+                physics_state.rest_ticks = if resting {
+                    physics_state.rest_ticks.saturating_add(1)
+                } else {
+                    0
+                };
+                let root = islands.root(i);
+                island_rest[root] = island_rest[root].min(physics_state.rest_ticks);
+            }
+        }
+        for (i, body) in bodies.iter().enumerate() {
+            if asleep[i] || body.inverse_mass == 0.0 || island_rest[islands.root(i)] < SLEEP_TICKS {
+                continue;
+            }
+            if let Some(physics_state) = self.object_physics_mut(body_ids[i]) {
+                physics_state.asleep = true;
+                physics_state.velocity = Vector3::zero();
+                physics_state.angular_velocity = Vector3::zero();
+            }
+        }
+    }
+
+    /// Changes an object's velocity by `impulse` divided by its mass, waking it if it's asleep.
+    pub fn apply_impulse(&mut self, map: &Map, object: SaltyId, impulse: Vector3<f32>) {
+        if let Some(physics_state) = self.object_physics_mut(object) {
+            if let Some(physics_tag) = map.get_physics(&physics_state.tag) {
+                physics_state.velocity += impulse * physics_tag.inverse_mass();
+                physics_state.wake();
+            }
+        }
+    }
+
+    pub(crate) fn object_physics(&self, object: SaltyId) -> Option<&PhysicsState> {
+        self.physics.get(self.objects.get(object)?.physics_id)
+    }
+
+    pub(crate) fn object_physics_mut(&mut self, object: SaltyId) -> Option<&mut PhysicsState> {
+        let physics_id = self.objects.get(object)?.physics_id;
+        self.physics.get_mut(physics_id)
+    }
+
+    /// The object's collision body as it is now, if it has one.
+    pub fn body<'m>(&self, map: &'m Map, object_state: &ObjectState) -> Option<Body<'m>> {
+        if object_state.riding.is_some() {
+            return None;
+        }
+        let physics_state = self.physics.get(object_state.physics_id)?;
+        let physics_tag = map.get_physics(&physics_state.tag)?;
+        let mut inverse_mass = physics_tag.inverse_mass();
+        //characters and movers move themselves, and push other bodies aside rather than being pushed
+        if physics_state.is_kinematic() {
+            inverse_mass = 0.0;
+        }
+        let shape = if let Some(collider) = physics_state.world_collider(&object_state.transform) {
+            BodyShape::Convex(collider)
+        } else if let Some(Shape::Mesh { model }) = physics_tag.shape {
+            inverse_mass = 0.0;
+            BodyShape::Mesh(MeshInstance {
+                mesh: map.get_collision_mesh(&model)?,
+                position: object_state.transform.position,
+                rotation: object_state.transform.rotation,
+            })
+        } else {
+            return None;
+        };
+        let inverse_inertia = if inverse_mass > 0.0 {
+            physics_state.inverse_inertia
+        } else {
+            Vector3::zero()
+        };
+        Some(Body {
+            shape,
+            rotation: object_state.transform.rotation,
+            velocity: physics_state.velocity,
+            angular_velocity: physics_state.angular_velocity,
+            inverse_mass,
+            inverse_inertia,
+            restitution: physics_tag.restitution(),
+            friction: physics_tag.friction(),
+            filter: physics_state.collision_filter(),
+            fast: physics_tag.fast(),
+        })
+    }
+
+    pub(crate) fn bodies<'m>(&self, map: &'m Map) -> (Vec<SaltyId>, Vec<Body<'m>>) {
+        self.objects.iter()
+            .filter_map(|(id, object_state)| self.body(map, object_state).map(|body| (id, body)))
+            .unzip()
+    }
+
+    /// Joints between objects with bodies, as indices into the bodies. Joints to objects
+    /// without one are left out.
+    fn body_joints(&self, body_indices: &[Option<usize>]) -> Vec<BodyJoint> {
+        self.joints.iter()
+            .filter_map(|(_, joint_state)| {
+                let a = body_indices[joint_state.object_a.index()]?;
+                let b = if joint_state.object_b.is_some() {
+                    Some(body_indices[joint_state.object_b.index()]?)
+                } else {
+                    None
+                };
+                Some(BodyJoint::new(a, b, joint_state.joint))
+            })
+            .collect()
+    }
+
+    fn update_broadphase(&mut self, body_ids: &[SaltyId], bodies: &[Body], asleep: &[bool]) {
+        for ((id, body), asleep) in body_ids.iter().zip(bodies.iter()).zip(asleep.iter()) {
+            //sleeping bodies stay where they are
+            if *asleep && self.broadphase.contains(*id) {
+                continue;
+            }
+            let (min, max) = body.swept_bounds(TICK_DURATION_SEC);
+            self.broadphase.update(*id, min, max, body.filter);
+        }
+    }
+
+    /// Runs fixed updates without input until the given tick, leaving no partial tick to
+    /// interpolate. Used for offline rendering where wall clock time doesn't matter.
+    pub fn advance_to_tick(&mut self, map: &Map, tick: u32) {
+        while self.tick < tick {
+            self.update_fixed(map);
+        }
+        self.accum_nanos = 0;
+    }
+
     pub fn get_tick_interpolation_fraction(&self) -> f32 {
         self.accum_nanos as f32 / TICK_DURATION_NANOS as f32
     }
 }
+
+mod tests {
+    use super::*;
+    use super::super::test_map::test_map;
+    use crate::math::realcmp;
+
+    const TEST_MAP: &str = r#"
+        [physics.crate]
+        mass = 1.0
+        shape = {type = "box", half_extents = [1.0, 1.0, 1.0]}
+
+        [object.crate]
+        physics = "crate"
+        model = "maps/cube.gltf"
+        colour = [0.0, 0.0, 1.0]
+
+        [scenario]
+        player_location = {pos = [-10.0, 0.0, 0.0]}
+
+        [[scenario.scenery]]
+        object_type = "crate"
+        position = {pos = [0.0, 0.0, 0.0]}
+
+        [[scenario.scenery]]
+        object_type = "crate"
+        position = {pos = [5.0, 0.0, 0.0]}
+    "#;
+
+    #[test]
+    fn test_objects_collide() {
+        let map = test_map(TEST_MAP);
+        let mut state = GameState::init(&map);
+        let crates: Vec<SaltyId> = state.objects.iter()
+            .filter(|(_, object)| object.physics_id.is_some())
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(2, crates.len());
+
+        let physics_id = state.objects.get(crates[0]).unwrap().physics_id;
+        state.physics.get_mut(physics_id).unwrap().velocity = Vector3::new(10.0, 0.0, 0.0);
+        state.advance_to_tick(&map, 60);
+
+        //boxes with half extents of 1 touch when 2 apart
+        let moving = state.objects.get(crates[0]).unwrap();
+        let hit = state.objects.get(crates[1]).unwrap();
+        assert!(hit.transform.position.x - moving.transform.position.x >= 1.98, "passed through at {:?}", moving.transform.position);
+
+        //equal masses share the momentum, and the struck crate is sent on its way
+        let moving_velocity = state.physics.get(physics_id).unwrap().velocity;
+        let hit_velocity = state.physics.get(hit.physics_id).unwrap().velocity;
+        assert!(realcmp(moving_velocity.x + hit_velocity.x, 10.0, 0.01));
+        assert!(hit_velocity.x > moving_velocity.x);
+    }
+
+    #[test]
+    fn test_gravity() {
+        let map = test_map(TEST_MAP);
+        let mut state = GameState::init(&map);
+        let (crate_id, start) = state.objects.iter()
+            .find(|(_, object)| object.physics_id.is_some())
+            .map(|(id, object)| (id, object.transform.position))
+            .unwrap();
+        state.advance_to_tick(&map, TICK_RATE);
+
+        //falling freely for a second at 1G
+        let object = state.objects.get(crate_id).unwrap();
+        let velocity = state.physics.get(object.physics_id).unwrap().velocity;
+        assert!(realcmp(velocity.z, -EARTH_GRAVITY, 0.001));
+        assert!(realcmp(start.z - object.transform.position.z, 0.5 * EARTH_GRAVITY, 0.1));
+    }
+
+    #[test]
+    fn test_collision_layers() {
+        //crates as debris which only collides with the default layer
+        let layered = TEST_MAP
+            .replace("[physics.crate]", "[physics.crate]\nlayers = [\"debris\"]\nmask = [\"default\"]")
+            + "\n[globals]\ncollision_layers = [\"debris\"]";
+        let map = test_map(&layered);
+        let mut state = GameState::init(&map);
+        let crates: Vec<SaltyId> = state.objects.iter()
+            .filter(|(_, object)| object.physics_id.is_some())
+            .map(|(id, _)| id)
+            .collect();
+        let physics_id = state.objects.get(crates[0]).unwrap().physics_id;
+        assert_eq!(2, state.physics.get(physics_id).unwrap().layer);
+        assert_eq!(1, state.physics.get(physics_id).unwrap().mask);
+
+        state.physics.get_mut(physics_id).unwrap().velocity = Vector3::new(10.0, 0.0, 0.0);
+        state.advance_to_tick(&map, 60);
+        let moving = state.objects.get(crates[0]).unwrap();
+        let other = state.objects.get(crates[1]).unwrap();
+        assert!(moving.transform.position.x > other.transform.position.x, "blocked at {:?}", moving.transform.position);
+    }
+
+    #[test]
+    fn test_layer_names() {
+        let with_layers = |layers: &str| {
+            let source = TEST_MAP
+                .replace("[physics.crate]", &format!("[physics.crate]\nlayers = {}", layers))
+                + "\n[globals]\ncollision_layers = [\"debris\", \"trigger\"]";
+            let map = test_map(&source);
+            let physics_tag = map.physics.values().next().unwrap().clone();
+            physics_tag.collision_filter(&map.globals)
+        };
+        assert_eq!(5, with_layers(r#"["trigger", "default"]"#).unwrap().layer);
+        assert!(with_layers(r#"["water"]"#).is_err());
+    }
+
+    const DAMPING_MAP: &str = r#"
+        [physics.ball]
+        mass = 1.0
+        shape = {type = "sphere", radius = 0.5}
+        linear_damping = 0.5
+        angular_damping = 2.0
+
+        [physics.rock]
+        mass = 1.0
+        shape = {type = "sphere", radius = 0.5}
+        max_speed = 20.0
+
+        [physics.balloon]
+        mass = 0.01
+        shape = {type = "sphere", radius = 0.5}
+        gravity_scale = -0.1
+        max_speed = 1.0
+
+        [object.ball]
+        physics = "ball"
+        model = "maps/ball.gltf"
+        colour = [0.0, 0.0, 1.0]
+
+        [object.rock]
+        physics = "rock"
+        model = "maps/ball.gltf"
+        colour = [0.5, 0.5, 0.5]
+
+        [object.balloon]
+        physics = "balloon"
+        model = "maps/ball.gltf"
+        colour = [1.0, 0.0, 0.0]
+
+        [[scenario.scenery]]
+        object_type = "ball"
+        position = {pos = [0.0, 0.0, 0.0]}
+
+        [[scenario.scenery]]
+        object_type = "rock"
+        position = {pos = [10.0, 0.0, 0.0]}
+
+        [[scenario.scenery]]
+        object_type = "balloon"
+        position = {pos = [20.0, 0.0, 0.0]}
+    "#;
+
+    #[test]
+    fn test_damping() {
+        let map = test_map(DAMPING_MAP);
+        let mut state = GameState::init(&map);
+        let find = |state: &GameState, tag: &str| state.objects.iter()
+            .find(|(_, object)| String::from(object.tag) == tag)
+            .map(|(id, _)| id)
+            .unwrap();
+        let (ball, rock, balloon) = (find(&state, "ball"), find(&state, "rock"), find(&state, "balloon"));
+        let ball_physics = state.object_physics_mut(ball).unwrap();
+        ball_physics.velocity = Vector3::new(10.0, 0.0, 0.0);
+        ball_physics.angular_velocity = Vector3::new(0.0, 10.0, 0.0);
+        state.advance_to_tick(&map, TICK_RATE * 2);
+
+        //dying away exponentially, while gravity pulls it down
+        let ball_physics = state.object_physics(ball).unwrap();
+        assert!(realcmp(ball_physics.velocity.x, 10.0 * (-1.0f32).exp(), 0.05), "ball at {:?}", ball_physics.velocity);
+        assert!(ball_physics.angular_velocity.y < 10.0 * 0.05);
+        assert!(ball_physics.velocity.z < 0.0);
+
+        //falling no faster than terminal velocity
+        state.advance_to_tick(&map, TICK_RATE * 4);
+        let rock_velocity = state.object_physics(rock).unwrap().velocity;
+        assert!(realcmp(rock_velocity.z, -20.0, 0.001), "rock at {:?}", rock_velocity);
+
+        //and balloons drift upwards, slowly
+        let balloon_physics = state.object_physics(balloon).unwrap();
+        assert!(realcmp(balloon_physics.velocity.z, 1.0, 0.001));
+        assert!(state.objects.get(balloon).unwrap().transform.position.z > 2.0);
+    }
+
+    const STACK_MAP: &str = r#"
+        [physics.floor]
+        mass = 0.0
+        shape = {type = "box", half_extents = [20.0, 20.0, 1.0]}
+
+        [physics.crate]
+        mass = 1.0
+        shape = {type = "box", half_extents = [1.0, 1.0, 1.0]}
+
+        [object.floor]
+        physics = "floor"
+        model = "maps/cube.gltf"
+        colour = [0.5, 0.5, 0.5]
+
+        [object.crate]
+        physics = "crate"
+        model = "maps/cube.gltf"
+        colour = [0.0, 0.0, 1.0]
+
+        [[scenario.scenery]]
+        object_type = "floor"
+        position = {pos = [0.0, 0.0, -1.0]}
+
+        [[scenario.scenery]]
+        object_type = "crate"
+        position = {pos = [0.0, 0.0, 1.0]}
+
+        [[scenario.scenery]]
+        object_type = "crate"
+        position = {pos = [0.0, 0.0, 3.0]}
+
+        [[scenario.scenery]]
+        object_type = "crate"
+        position = {pos = [10.0, 0.0, 1.0]}
+    "#;
+
+    #[test]
+    fn test_sleeping() {
+        let map = test_map(STACK_MAP);
+        let mut state = GameState::init(&map);
+        let crates: Vec<SaltyId> = state.objects.iter()
+            .filter(|(_, object)| String::from(object.tag) == "crate")
+            .map(|(id, _)| id)
+            .collect();
+        let asleep = |state: &GameState, id: SaltyId| state.object_physics(id).unwrap().asleep;
+
+        state.advance_to_tick(&map, TICK_RATE * 2);
+        assert!(crates.iter().all(|id| asleep(&state, *id)));
+        let resting: Vec<Vector3<f32>> = crates.iter().map(|id| state.objects.get(*id).unwrap().transform.position).collect();
+        state.advance_to_tick(&map, TICK_RATE * 3);
+        for (id, position) in crates.iter().zip(resting.iter()) {
+            assert_eq!(*position, state.objects.get(*id).unwrap().transform.position);
+        }
+
+        //knocking the top crate wakes the one it rests on, but not the one across the room
+        state.apply_impulse(&map, crates[1], Vector3::new(2.0, 0.0, 0.0));
+        state.advance_to_tick(&map, TICK_RATE * 3 + 1);
+        assert!(!asleep(&state, crates[1]));
+        assert!(!asleep(&state, crates[0]));
+        assert!(asleep(&state, crates[2]));
+        assert!(state.objects.get(crates[1]).unwrap().transform.position.x > resting[1].x);
+
+        //and they settle down again
+        state.advance_to_tick(&map, TICK_RATE * 6);
+        assert!(crates.iter().all(|id| asleep(&state, *id)));
+    }
+
+    const TIPPING_MAP: &str = r#"
+        [physics.ledge]
+        mass = 0.0
+        shape = {type = "box", half_extents = [2.0, 2.0, 1.0]}
+
+        [physics.ramp]
+        mass = 0.0
+        shape = {type = "box", half_extents = [10.0, 2.0, 0.5]}
+
+        [physics.crate]
+        mass = 1.0
+        shape = {type = "box", half_extents = [0.5, 0.5, 0.5]}
+        restitution = 0.0
+
+        [physics.ball]
+        mass = 1.0
+        shape = {type = "sphere", radius = 0.5}
+        restitution = 0.0
+
+        [object.ledge]
+        physics = "ledge"
+        model = "maps/cube.gltf"
+        colour = [0.5, 0.5, 0.5]
+
+        [object.ramp]
+        physics = "ramp"
+        model = "maps/cube.gltf"
+        colour = [0.5, 0.5, 0.5]
+
+        [object.crate]
+        physics = "crate"
+        model = "maps/cube.gltf"
+        colour = [0.6, 0.4, 0.2]
+
+        [object.ball]
+        physics = "ball"
+        model = "maps/ball.gltf"
+        colour = [0.0, 0.0, 1.0]
+
+        [[scenario.scenery]]
+        object_type = "ledge"
+        position = {pos = [0.0, 0.0, -1.0]}
+
+        [[scenario.scenery]]
+        object_type = "crate"
+        position = {pos = [0.0, 0.0, 0.5]}
+
+        [[scenario.scenery]]
+        object_type = "crate"
+        position = {pos = [2.2, 0.0, 0.5]}
+
+        [[scenario.scenery]]
+        object_type = "ramp"
+        position = {pos = [0.0, 10.0, 0.0], rot = [0.0, 20.0, 0.0]}
+
+        [[scenario.scenery]]
+        object_type = "ball"
+        position = {pos = [0.0, 10.0, 0.0]}
+    "#;
+
+    #[test]
+    fn test_tipping_and_rolling() {
+        let map = test_map(TIPPING_MAP);
+        let mut state = GameState::init(&map);
+        let ids = |tag: &str| state.objects.iter()
+            .filter(|(_, object)| String::from(object.tag) == tag)
+            .map(|(id, _)| id)
+            .collect::<Vec<SaltyId>>();
+        let (crates, ball) = (ids("crate"), ids("ball")[0]);
+
+        //resting on the ramp, just touching
+        let ramp = ids("ramp")[0];
+        let ramp_transform = state.objects.get(ramp).unwrap().transform;
+        let up = ramp_transform.rotation.rotate_vector(Vector3::unit_z());
+        state.objects.get_mut(ball).unwrap().transform.position = ramp_transform.position + up;
+        state.advance_to_tick(&map, TICK_RATE * 2);
+
+        //a crate flat on the ledge stays put, while one hanging over the edge tips off it
+        let upright = state.objects.get(crates[0]).unwrap().transform;
+        assert!(realcmp(upright.position.z, 0.5, 0.02), "settled at {:?}", upright.position);
+        assert!(upright.rotation.rotate_vector(Vector3::unit_z()).z > 0.999);
+        let tipped = state.objects.get(crates[1]).unwrap().transform;
+        assert!(tipped.position.z < 0.0, "still on the ledge at {:?}", tipped.position);
+        let tumbling = state.object_physics(crates[1]).unwrap().angular_velocity;
+        assert!(tumbling.y > 1.0, "tumbling at {:?}", tumbling);
+
+        //and a ball rolls down the ramp rather than sliding, picking up speed more slowly
+        let ball_physics = state.object_physics(ball).unwrap();
+        let speed = 5.0 / 7.0 * EARTH_GRAVITY * 20f32.to_radians().sin() * 2.0;
+        assert!(realcmp(ball_physics.velocity.magnitude(), speed, 0.2), "rolling at {:?}", ball_physics.velocity);
+        let slip = ball_physics.velocity - ball_physics.angular_velocity.cross(up) * 0.5;
+        assert!(slip.magnitude() < 0.1, "slipping at {:?}", slip);
+    }
+
+    const FAST_MAP: &str = r#"
+        [physics.wall]
+        mass = 0.0
+        shape = {type = "box", half_extents = [0.05, 5.0, 5.0]}
+        restitution = 0.0
+
+        [physics.bullet]
+        mass = 0.01
+        shape = {type = "sphere", radius = 0.05}
+        restitution = 0.0
+        fast = true
+
+        [physics.slow_bullet]
+        mass = 0.01
+        shape = {type = "sphere", radius = 0.05}
+        restitution = 0.0
+
+        [object.wall]
+        physics = "wall"
+        model = "maps/cube.gltf"
+        colour = [0.5, 0.5, 0.5]
+
+        [object.bullet]
+        physics = "bullet"
+        model = "maps/ball.gltf"
+        colour = [1.0, 1.0, 0.0]
+
+        [object.slow_bullet]
+        physics = "slow_bullet"
+        model = "maps/ball.gltf"
+        colour = [1.0, 1.0, 0.0]
+
+        [globals]
+        gravity_scale = 0.0
+
+        [[scenario.scenery]]
+        object_type = "wall"
+        position = {pos = [5.0, 0.0, 0.0]}
+
+        [[scenario.scenery]]
+        object_type = "bullet"
+        position = {pos = [0.0, 0.0, 0.0]}
+
+        [[scenario.scenery]]
+        object_type = "slow_bullet"
+        position = {pos = [0.0, 2.0, 0.0]}
+    "#;
+
+    #[test]
+    fn test_fast_bodies() {
+        let map = test_map(FAST_MAP);
+        let mut state = GameState::init(&map);
+        let find = |state: &GameState, tag: &str| state.objects.iter()
+            .find(|(_, object)| String::from(object.tag) == tag)
+            .map(|(id, _)| id)
+            .unwrap();
+        let (bullet, slow_bullet) = (find(&state, "bullet"), find(&state, "slow_bullet"));
+        for id in [bullet, slow_bullet] {
+            state.object_physics_mut(id).unwrap().velocity = Vector3::new(600.0, 0.0, 0.0);
+        }
+
+        //ten metres a tick goes right through the wall in one step, unless swept
+        state.advance_to_tick(&map, 1);
+        let stopped = state.objects.get(bullet).unwrap().transform.position;
+        assert!(realcmp(stopped.x, 5.0 - 0.05 - 0.05, 0.001), "stopped at {:?}", stopped);
+        assert!(state.objects.get(slow_bullet).unwrap().transform.position.x > 5.0 + 0.05);
+
+        //and it stays stopped rather than carrying on through next tick
+        state.advance_to_tick(&map, 10);
+        let stopped = state.objects.get(bullet).unwrap().transform.position;
+        assert!(stopped.x < 5.0 - 0.05 - 0.05 + 0.001, "carried on to {:?}", stopped);
+        assert!(state.object_physics(bullet).unwrap().velocity.x <= 0.0);
+        assert!(state.objects.get(slow_bullet).unwrap().transform.position.x > 50.0);
+    }
+}
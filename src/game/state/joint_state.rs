@@ -0,0 +1,235 @@
+use cgmath::{prelude::*, Vector3};
+use super::prelude::*;
+use crate::game::physics::{Joint, JointKind};
+use crate::game::tags::{JointPlacement, JointType, TagString};
+
+state_nodef! {
+    pub struct JointState {
+        pub object_a: SaltyId,
+        // NONE when joined to the world
+        pub object_b: SaltyId,
+        // Anchors and axes in each object's own space
+        pub joint: Joint,
+    }
+}
+
+impl Default for JointState {
+    fn default() -> Self {
+        JointState {
+            object_a: NONE,
+            object_b: NONE,
+            joint: Joint {
+                kind: JointKind::BallSocket,
+                anchor_a: Vector3::zero(),
+                anchor_b: Vector3::zero(),
+            },
+        }
+    }
+}
+
+impl JointState {
+    /// Joins objects as they're currently placed, looking them up by the names of their scenery.
+    pub fn init(game_state: &mut GameState, placement: &JointPlacement, named: &[(TagString, SaltyId)]) -> SaltyId {
+        let find = |name: TagString| named.iter().find(|(n, _)| *n == name).map(|(_, id)| *id);
+        let object_a = match find(placement.object) {
+            Some(id) => id,
+            None => return NONE,
+        };
+        let object_b = match placement.other {
+            Some(other) => match find(other) {
+                Some(id) => id,
+                None => return NONE,
+            },
+            None => NONE,
+        };
+
+        let transform_a = match game_state.objects.get(object_a) {
+            Some(object_state) => object_state.transform,
+            None => return NONE,
+        };
+        //the world stays put, so its space is the same as world space
+        let transform_b = game_state.objects.get(object_b).map(|object_state| object_state.transform).unwrap_or_default();
+        let anchor: Vector3<f32> = placement.anchor.into();
+        let other_anchor: Vector3<f32> = placement.other_anchor.map(Vector3::from).unwrap_or(anchor);
+
+        let kind = match placement.joint {
+            JointType::BallSocket => JointKind::BallSocket,
+            JointType::Hinge { axis } => {
+                let axis = Vector3::from(axis).normalize();
+                JointKind::Hinge {
+                    axis_a: transform_a.rotation.conjugate().rotate_vector(axis),
+                    axis_b: transform_b.rotation.conjugate().rotate_vector(axis),
+                }
+            },
+            JointType::Distance => JointKind::Distance {
+                length: (anchor - other_anchor).magnitude(),
+            },
+            JointType::Fixed => JointKind::Fixed {
+                rotation: transform_b.rotation.conjugate() * transform_a.rotation,
+            },
+        };
+        let joint = Joint {
+            kind,
+            anchor_a: transform_a.rotation.conjugate().rotate_vector(anchor - transform_a.position),
+            anchor_b: transform_b.rotation.conjugate().rotate_vector(other_anchor - transform_b.position),
+        };
+
+        game_state.joints.add(JointState {
+            object_a,
+            object_b,
+            joint,
+        }).unwrap_or(NONE)
+    }
+}
+
+mod tests {
+    use super::*;
+    use super::super::test_map::test_map;
+    use crate::game::state::physics_state::PhysicsState;
+    use crate::game::state::transform::Transform;
+
+    const JOINT_MAP: &str = r#"
+        [physics.lamp]
+        mass = 1.0
+        shape = {type = "sphere", radius = 0.25}
+
+        [physics.door]
+        mass = 10.0
+        shape = {type = "box", half_extents = [0.05, 0.5, 1.0]}
+
+        [physics.crate]
+        mass = 1.0
+        shape = {type = "box", half_extents = [0.5, 0.5, 0.5]}
+
+        [object.lamp]
+        physics = "lamp"
+        model = "maps/cube.gltf"
+        colour = [1.0, 1.0, 0.0]
+
+        [object.door]
+        physics = "door"
+        model = "maps/cube.gltf"
+        colour = [0.5, 0.3, 0.1]
+
+        [object.crate]
+        physics = "crate"
+        model = "maps/cube.gltf"
+        colour = [0.0, 0.0, 1.0]
+
+        [scenario]
+        player_location = {pos = [0.0, -20.0, 0.0]}
+
+        [[scenario.scenery]]
+        name = "lamp"
+        object_type = "lamp"
+        position = {pos = [0.0, 0.0, 3.0]}
+
+        [[scenario.scenery]]
+        name = "pendulum"
+        object_type = "lamp"
+        position = {pos = [5.0, 0.0, 3.0]}
+
+        [[scenario.scenery]]
+        name = "door"
+        object_type = "door"
+        position = {pos = [10.0, 0.5, 1.0]}
+
+        [[scenario.scenery]]
+        name = "left"
+        object_type = "crate"
+        position = {pos = [15.0, 0.0, 5.0]}
+
+        [[scenario.scenery]]
+        name = "right"
+        object_type = "crate"
+        position = {pos = [16.0, 0.0, 5.0]}
+
+        [[scenario.joints]]
+        joint = {type = "distance"}
+        object = "lamp"
+        anchor = [0.0, 0.0, 3.25]
+        other_anchor = [0.0, 0.0, 5.0]
+
+        [[scenario.joints]]
+        joint = {type = "ball_socket"}
+        object = "pendulum"
+        anchor = [5.0, 0.0, 5.0]
+
+        [[scenario.joints]]
+        joint = {type = "hinge", axis = [0.0, 0.0, 1.0]}
+        object = "door"
+        anchor = [10.0, 0.0, 1.0]
+
+        [[scenario.joints]]
+        joint = {type = "fixed"}
+        object = "left"
+        other = "right"
+        anchor = [15.5, 0.0, 5.0]
+    "#;
+
+    //objects don't keep their scenery's name, so find the one still where that scenery placed it
+    fn named(map: &Map, state: &GameState, name: &str) -> SaltyId {
+        let placed = map.scenario.scenery.iter().flatten()
+            .find(|scenery| scenery.name.map(String::from).as_deref() == Some(name))
+            .unwrap()
+            .position
+            .to_pos();
+        state.objects.iter()
+            .find(|(_, object)| object.transform.position == placed)
+            .map(|(id, _)| id)
+            .unwrap()
+    }
+
+    fn transform(state: &GameState, id: SaltyId) -> Transform {
+        state.objects.get(id).unwrap().transform
+    }
+
+    fn physics(state: &GameState, id: SaltyId) -> &PhysicsState {
+        state.physics.get(state.objects.get(id).unwrap().physics_id).unwrap()
+    }
+
+    #[test]
+    fn test_joints() {
+        let map = test_map(JOINT_MAP);
+        map.scenario.check_joints().unwrap();
+        let mut state = GameState::init(&map);
+        assert_eq!(4, state.joints.iter().count());
+        let lamp = named(&map, &state, "lamp");
+        let pendulum = named(&map, &state, "pendulum");
+        let door = named(&map, &state, "door");
+        let left = named(&map, &state, "left");
+        let right = named(&map, &state, "right");
+
+        //everything hangs where it was placed, except the crates which only hold on to each other
+        state.advance_to_tick(&map, 60);
+        assert!((transform(&state, lamp).position.z - 3.0).abs() < 0.05);
+        assert!((transform(&state, pendulum).position.z - 3.0).abs() < 0.05);
+        assert!((transform(&state, door).position - Vector3::new(10.0, 0.5, 1.0)).magnitude() < 0.05);
+        let offset = transform(&state, right).position - transform(&state, left).position;
+        assert!(transform(&state, left).position.z < 1.0);
+        assert!((offset - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 0.05, "crates drifted to {:?}", offset);
+
+        //knocked sideways, the lamp and pendulum swing while the door turns on its hinge
+        state.apply_impulse(&map, lamp, Vector3::new(3.0, 0.0, 0.0));
+        state.apply_impulse(&map, pendulum, Vector3::new(3.0, 0.0, 0.0));
+        state.apply_impulse(&map, door, Vector3::new(10.0, 0.0, 0.0));
+        state.advance_to_tick(&map, 75);
+
+        let lamp_top = transform(&state, lamp).position + Vector3::new(0.0, 0.0, 0.25);
+        assert!(transform(&state, lamp).position.x > 0.2);
+        assert!((lamp_top - Vector3::new(0.0, 0.0, 5.0)).magnitude() < 1.8);
+
+        let pendulum_transform = transform(&state, pendulum);
+        let pivot = pendulum_transform.position + pendulum_transform.rotation.rotate_vector(Vector3::new(0.0, 0.0, 2.0));
+        assert!(pendulum_transform.position.x > 5.2);
+        assert!((pivot - Vector3::new(5.0, 0.0, 5.0)).magnitude() < 0.05, "pendulum pivot moved to {:?}", pivot);
+
+        let door_transform = transform(&state, door);
+        let hinge = door_transform.position + door_transform.rotation.rotate_vector(Vector3::new(0.0, -0.5, 0.0));
+        let door_axis = door_transform.rotation.rotate_vector(Vector3::unit_z());
+        assert!(door_transform.position.x > 10.1);
+        assert!((hinge - Vector3::new(10.0, 0.0, 1.0)).magnitude() < 0.05, "hinge moved to {:?}", hinge);
+        assert!(door_axis.z > 0.99);
+        assert!(physics(&state, door).angular_velocity.z.abs() > 0.0);
+    }
+}
@@ -4,6 +4,16 @@ pub mod physics_state;
 pub mod object_state;
 pub mod camera_state;
 pub mod transform;
+pub mod query;
+pub mod character;
+pub mod mover;
+pub mod vehicle;
+pub mod possession;
+pub mod physics_debug;
+pub mod trigger_state;
+pub mod joint_state;
+pub mod water_state;
+mod test_map;
 
 use crate::game::Placement;
 use physics_state::PhysicsState;
@@ -30,6 +40,7 @@ mod prelude {
     pub use state;
     pub use state_nodef;
     pub use crate::util::saltybuffer::{SaltyBuffer, SaltyId, NONE};
+    pub use crate::util::ringbuffer::FixedRingBuffer;
     pub use crate::game::tags::TagId;
     pub use crate::game::tags::Map;
     pub use super::game_state::GameState;
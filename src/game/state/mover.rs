@@ -0,0 +1,398 @@
+use cgmath::{prelude::*, Vector3};
+use super::prelude::*;
+use crate::game::tags::{Easing, Mover, MoverMode};
+
+state_nodef! {
+    pub struct MoverState {
+        pub tag: TagId,
+        // Where the object was placed, which the waypoints are relative to
+        pub origin: Vector3<f32>,
+        // Points of the leg being travelled, where the start of the path is 0
+        pub from: u32,
+        pub to: u32,
+        // Distance along the leg so far
+        pub travelled: f32,
+        // Seconds left paused at the last waypoint
+        pub waiting: f32,
+        // Heading back towards the start, for ping-pong movers
+        pub returning: bool,
+        // Reached the end of a path only travelled once
+        pub stopped: bool,
+    }
+}
+
+impl MoverState {
+    pub fn init(tag: TagId, origin: Vector3<f32>) -> MoverState {
+        MoverState {
+            tag,
+            origin,
+            from: 0,
+            to: 1,
+            travelled: 0.0,
+            waiting: 0.0,
+            returning: false,
+            stopped: false,
+        }
+    }
+
+    fn point(&self, tag: &Mover, index: u32) -> Vector3<f32> {
+        match index {
+            0 => self.origin,
+            i => self.origin + Vector3::from(tag.waypoints[i as usize - 1]),
+        }
+    }
+
+    /// Moves along the path for `dt` seconds, returning where the object should now be.
+    pub fn advance(&mut self, tag: &Mover, dt: f32) -> Vector3<f32> {
+        let count = tag.waypoints.len() as u32 + 1;
+        if count < 2 {
+            return self.origin;
+        }
+        let speed = tag.speed();
+        let mut time = dt;
+        //zero length legs are arrived at straight away, so give up rather than going round forever
+        for _ in 0..=count {
+            let paused = self.waiting.min(time);
+            self.waiting -= paused;
+            time -= paused;
+            if self.stopped || time <= 0.0 || speed <= 0.0 {
+                break;
+            }
+            let left = (self.point(tag, self.to) - self.point(tag, self.from)).magnitude() - self.travelled;
+            if time * speed < left {
+                self.travelled += time * speed;
+                break;
+            }
+            time -= left / speed;
+            self.arrive(tag, count);
+        }
+
+        let from = self.point(tag, self.from);
+        let to = self.point(tag, self.to);
+        let length = (to - from).magnitude();
+        if self.stopped || length <= 0.0 {
+            return to;
+        }
+        let fraction = self.travelled / length;
+        let eased = match tag.easing() {
+            Easing::Linear => fraction,
+            Easing::Smooth => fraction * fraction * (3.0 - 2.0 * fraction),
+        };
+        from + (to - from) * eased
+    }
+
+    fn arrive(&mut self, tag: &Mover, count: u32) {
+        let last = count - 1;
+        self.travelled = 0.0;
+        self.waiting = tag.wait();
+        let next = match tag.mode() {
+            MoverMode::Once if self.to == last => {
+                self.stopped = true;
+                return;
+            },
+            MoverMode::Once => self.to + 1,
+            MoverMode::Loop => (self.to + 1) % count,
+            MoverMode::PingPong => {
+                if self.to == last {
+                    self.returning = true;
+                } else if self.to == 0 {
+                    self.returning = false;
+                }
+                if self.returning {
+                    self.to - 1
+                } else {
+                    self.to + 1
+                }
+            },
+        };
+        self.from = self.to;
+        self.to = next;
+    }
+}
+
+impl GameState {
+    /// Moves every object with a mover along its path for one tick. Like characters, they've
+    /// already moved by the time bodies are solved, which only see them pushing at the velocity
+    /// they moved with.
+    pub(crate) fn update_movers(&mut self, map: &Map, dt: f32) {
+        let mut moved = Vec::new();
+        for (id, object_state) in self.objects.iter_mut() {
+            let physics_state = match self.physics.get_mut(object_state.physics_id) {
+                Some(physics_state) => physics_state,
+                None => continue,
+            };
+            let mut mover = match physics_state.mover {
+                Some(mover) => mover,
+                None => continue,
+            };
+            let tag = match map.get_mover(&mover.tag) {
+                Some(tag) => tag,
+                None => continue,
+            };
+            let position = mover.advance(tag, dt);
+            physics_state.prev_transform = object_state.transform;
+            physics_state.velocity = (position - object_state.transform.position) / dt;
+            physics_state.mover = Some(mover);
+            object_state.transform.position = position;
+            moved.push(id);
+        }
+
+        //characters and vehicles query the world before the broadphase is next updated, so they
+        //need to find movers where they are now, not where last tick's bounds expected them
+        for id in moved {
+            let body = match self.objects.get(id).and_then(|object_state| self.body(map, object_state)) {
+                Some(body) => body,
+                None => continue,
+            };
+            let (min, max) = body.swept_bounds(dt);
+            self.broadphase.update(id, min, max, body.filter);
+        }
+    }
+
+    /// How far the object moved this tick if it's a mover, for carrying what stands on it.
+    pub(crate) fn mover_movement(&self, object: SaltyId) -> Vector3<f32> {
+        let object_state = match self.objects.get(object) {
+            Some(object_state) => object_state,
+            None => return Vector3::zero(),
+        };
+        match self.physics.get(object_state.physics_id) {
+            Some(physics_state) if physics_state.mover.is_some() => {
+                object_state.transform.position - physics_state.prev_transform.position
+            },
+            _ => Vector3::zero(),
+        }
+    }
+}
+
+mod tests {
+    use super::*;
+    use super::super::test_map::{find, test_map};
+    use super::super::query::QueryFilter;
+    use crate::math::realcmp;
+
+    const MOVER_MAP: &str = r#"
+        [physics.floor]
+        mass = 0.0
+        shape = {type = "box", half_extents = [50.0, 50.0, 1.0]}
+
+        [physics.lift]
+        mass = 0.0
+        shape = {type = "box", half_extents = [2.0, 2.0, 0.25]}
+
+        [physics.shuttle]
+        mass = 0.0
+        shape = {type = "box", half_extents = [0.5, 0.5, 0.5]}
+
+        [physics.smooth_shuttle]
+        mass = 0.0
+        shape = {type = "box", half_extents = [0.5, 0.5, 0.5]}
+
+        [physics.circuit]
+        mass = 0.0
+        shape = {type = "box", half_extents = [0.5, 0.5, 0.5]}
+
+        [physics.pusher]
+        mass = 0.0
+        shape = {type = "box", half_extents = [0.5, 2.0, 1.0]}
+
+        [physics.crate]
+        mass = 1.0
+        shape = {type = "box", half_extents = [0.5, 0.5, 0.5]}
+
+        [physics.biped]
+        mass = 1.0
+        shape = {type = "capsule", radius = 0.4, half_height = 0.5}
+        character = {}
+
+        [mover.lift]
+        waypoints = [[0.0, 0.0, 4.0]]
+        speed = 1.0
+        mode = "once"
+
+        [mover.shuttle]
+        waypoints = [[4.0, 0.0, 0.0]]
+        speed = 2.0
+        wait = 0.5
+
+        [mover.smooth_shuttle]
+        waypoints = [[4.0, 0.0, 0.0]]
+        speed = 2.0
+        easing = "smooth"
+
+        [mover.circuit]
+        waypoints = [[2.0, 0.0, 0.0], [2.0, 2.0, 0.0]]
+        speed = 2.0
+        mode = "loop"
+
+        [mover.pusher]
+        waypoints = [[6.0, 0.0, 0.0]]
+        speed = 2.0
+        mode = "once"
+
+        [object.player]
+        physics = "biped"
+
+        [object.floor]
+        physics = "floor"
+        model = "maps/cube.gltf"
+        colour = [0.5, 0.5, 0.5]
+
+        [object.lift]
+        physics = "lift"
+        mover = "lift"
+        model = "maps/cube.gltf"
+        colour = [0.5, 0.5, 0.5]
+
+        [object.shuttle]
+        physics = "shuttle"
+        mover = "shuttle"
+        model = "maps/cube.gltf"
+        colour = [0.5, 0.5, 0.5]
+
+        [object.smooth_shuttle]
+        physics = "smooth_shuttle"
+        mover = "smooth_shuttle"
+        model = "maps/cube.gltf"
+        colour = [0.5, 0.5, 0.5]
+
+        [object.circuit]
+        physics = "circuit"
+        mover = "circuit"
+        model = "maps/cube.gltf"
+        colour = [0.5, 0.5, 0.5]
+
+        [object.pusher]
+        physics = "pusher"
+        mover = "pusher"
+        model = "maps/cube.gltf"
+        colour = [0.5, 0.5, 0.5]
+
+        [object.crate]
+        physics = "crate"
+        model = "maps/cube.gltf"
+        colour = [0.0, 0.0, 1.0]
+
+        [scenario]
+        player_location = {pos = [-1.0, 0.0, 1.5]}
+
+        [[scenario.scenery]]
+        object_type = "floor"
+        position = {pos = [0.0, 0.0, -1.0]}
+
+        [[scenario.scenery]]
+        object_type = "lift"
+        position = {pos = [0.0, 0.0, 0.25]}
+
+        [[scenario.scenery]]
+        object_type = "crate"
+        position = {pos = [1.0, 0.0, 1.0]}
+
+        [[scenario.scenery]]
+        object_type = "shuttle"
+        position = {pos = [0.0, 20.0, 10.0]}
+
+        [[scenario.scenery]]
+        object_type = "smooth_shuttle"
+        position = {pos = [0.0, 25.0, 10.0]}
+
+        [[scenario.scenery]]
+        object_type = "circuit"
+        position = {pos = [0.0, 30.0, 10.0]}
+
+        [[scenario.scenery]]
+        object_type = "pusher"
+        position = {pos = [0.0, -10.0, 1.0]}
+
+        [[scenario.scenery]]
+        object_type = "crate"
+        position = {pos = [2.0, -10.0, 0.5]}
+    "#;
+
+    fn position(state: &GameState, id: SaltyId) -> Vector3<f32> {
+        state.objects.get(id).unwrap().transform.position
+    }
+
+    #[test]
+    fn test_mover_paths() {
+        let map = test_map(MOVER_MAP);
+        let mut state = GameState::init(&map);
+        let shuttle = find(&state, "shuttle");
+        let smooth_shuttle = find(&state, "smooth_shuttle");
+        let circuit = find(&state, "circuit");
+
+        //halfway along the first leg, where only the smooth shuttle is moving at full speed
+        state.advance_to_tick(&map, 60);
+        assert!(realcmp(position(&state, shuttle).x, 2.0, 0.01), "shuttle at {:?}", position(&state, shuttle));
+        assert!(realcmp(position(&state, smooth_shuttle).x, 2.0, 0.01));
+        assert!(realcmp(position(&state, circuit).x, 2.0, 0.01));
+        let physics = |state: &GameState, id: SaltyId| *state.physics.get(state.objects.get(id).unwrap().physics_id).unwrap();
+        assert!(realcmp(physics(&state, shuttle).velocity.x, 2.0, 0.01));
+        assert!(physics(&state, smooth_shuttle).velocity.x > 2.5);
+
+        //the smooth shuttle starts slowly, then both arrive at once
+        state = GameState::init(&map);
+        state.advance_to_tick(&map, 30);
+        assert!(position(&state, smooth_shuttle).x < 0.8);
+        state.advance_to_tick(&map, 120);
+        assert!(realcmp(position(&state, shuttle).x, 4.0, 0.01));
+        assert!(realcmp(position(&state, smooth_shuttle).x, 4.0, 0.01));
+        assert!(realcmp(position(&state, circuit).y, 32.0, 0.01));
+
+        //waiting at the end before heading back, while the smooth shuttle doesn't wait
+        state.advance_to_tick(&map, 150);
+        assert!(realcmp(position(&state, shuttle).x, 4.0, 0.01));
+        assert!(position(&state, smooth_shuttle).x < 3.5);
+        state.advance_to_tick(&map, 180);
+        assert!(realcmp(position(&state, shuttle).x, 3.0, 0.01), "shuttle at {:?}", position(&state, shuttle));
+
+        //the loop goes straight back to the start
+        let diagonal = 8f32.sqrt() / 2.0;
+        state.advance_to_tick(&map, 120 + (diagonal * 60.0) as u32);
+        assert!((position(&state, circuit) - Vector3::new(0.0, 30.0, 10.0)).magnitude() < 0.05);
+    }
+
+    #[test]
+    fn test_mover_carries_and_pushes() {
+        let map = test_map(MOVER_MAP);
+        let mut state = GameState::init(&map);
+        let lift = find(&state, "lift");
+        let player = state.player_control.target_object;
+        let riding = state.objects.iter()
+            .find(|(_, object)| String::from(object.tag) == "crate" && object.transform.position.x == 1.0)
+            .map(|(id, _)| id)
+            .unwrap();
+        let pushed = state.objects.iter()
+            .find(|(_, object)| String::from(object.tag) == "crate" && object.transform.position.x == 2.0)
+            .map(|(id, _)| id)
+            .unwrap();
+
+        //the lift rises 4m over 4 seconds, taking the player and crate with it
+        state.advance_to_tick(&map, 300);
+        assert!(realcmp(position(&state, lift).z, 4.25, 0.001));
+        let character = state.physics.get(state.objects.get(player).unwrap().physics_id).unwrap().character.unwrap();
+        assert!(character.grounded);
+        assert_eq!(lift, character.ground_object);
+        assert!(realcmp(position(&state, player).z, 4.5 + 0.9, 0.05), "player at {:?}", position(&state, player));
+        assert!(realcmp(position(&state, riding).z, 5.0, 0.05), "crate at {:?}", position(&state, riding));
+        assert!(realcmp(position(&state, riding).x, 1.0, 0.05));
+
+        //the pusher shoves the other crate ahead of it until it stops
+        let pushed_to = position(&state, pushed);
+        assert!(pushed_to.x > 6.9, "crate pushed to {:?}", pushed_to);
+        assert!(realcmp(pushed_to.z, 0.5, 0.05));
+    }
+
+    #[test]
+    fn test_mover_found_after_moving() {
+        let map = test_map(MOVER_MAP);
+        let mut state = GameState::init(&map);
+        let shuttle = find(&state, "shuttle");
+        state.update_fixed(&map);
+
+        //a second's movement in one go leaves it well clear of where the broadphase last saw it
+        state.update_movers(&map, 1.0);
+        let above = position(&state, shuttle) + Vector3::unit_z() * 2.0;
+        let hit = state.raycast(&map, above, -Vector3::unit_z(), 5.0, &QueryFilter::default());
+        assert_eq!(Some(shuttle), hit.map(|hit| hit.object));
+    }
+}
@@ -9,6 +9,8 @@ state_nodef! {
         pub tag: TagId,
         pub transform: Transform,
         pub physics_id: SaltyId,
+        // The vehicle this is being carried in, which takes it out of the world until it climbs out
+        pub riding: SaltyId,
     }
 }
 
@@ -18,6 +20,7 @@ impl Default for ObjectState {
             tag: TagId::default(),
             transform: Transform::default(),
             physics_id: NONE,
+            riding: NONE,
         }
     }
 }
@@ -27,15 +30,16 @@ impl ObjectState {
         if let Some(object_tag) = map.object.get(object_tag_id) {
             
             let physics_id = if let Some(physics_tag_id) = object_tag.physics {
-                PhysicsState::init(game_state, map, &physics_tag_id, transform)
+                PhysicsState::init(game_state, map, &physics_tag_id, object_tag.mover, transform)
             } else {
                 NONE
             };
 
             let object_state = ObjectState {
-                tag: object_tag_id.clone(),
+                tag: *object_tag_id,
                 transform,
                 physics_id,
+                riding: NONE,
             };
             //todo: cleanup if this fails
             return game_state.objects.add(object_state).unwrap();
@@ -47,6 +51,14 @@ impl ObjectState {
     pub fn cleanup(game_state: &mut GameState, object_id: SaltyId) {
         if let Some(object) = game_state.objects.remove(object_id) {
             game_state.physics.remove(object.physics_id);
+            game_state.broadphase.remove(object_id);
+            let joint_ids: Vec<SaltyId> = game_state.joints.iter()
+                .filter(|(_, joint)| joint.object_a == object_id || joint.object_b == object_id)
+                .map(|(id, _)| id)
+                .collect();
+            for joint_id in joint_ids {
+                game_state.joints.remove(joint_id);
+            }
         }
     }
 }
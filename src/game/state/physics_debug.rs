@@ -0,0 +1,187 @@
+use cgmath::{prelude::*, Vector3};
+use super::prelude::*;
+use super::game_state::TICK_DURATION_SEC;
+use crate::game::physics::Collider;
+use crate::game::physics::solver::{self, BodyShape};
+use crate::util::debug_draw::{DebugCommand, DebugDraw};
+
+const STATIC_COLOUR: Vector3<f32> = Vector3::new(0.2, 0.4, 1.0);
+// Characters and movers
+const KINEMATIC_COLOUR: Vector3<f32> = Vector3::new(1.0, 0.6, 0.0);
+const AWAKE_COLOUR: Vector3<f32> = Vector3::new(0.0, 1.0, 0.0);
+const ASLEEP_COLOUR: Vector3<f32> = Vector3::new(0.3, 0.3, 0.3);
+const BROADPHASE_COLOUR: Vector3<f32> = Vector3::new(0.5, 0.0, 0.5);
+const CONTACT_COLOUR: Vector3<f32> = Vector3::new(1.0, 0.0, 0.0);
+const VELOCITY_COLOUR: Vector3<f32> = Vector3::new(1.0, 1.0, 0.0);
+const CONTACT_SIZE: f32 = 0.1;
+const NORMAL_LENGTH: f32 = 0.5;
+// Velocities are drawn as how far they'd carry a body in this long
+const VELOCITY_SECONDS: f32 = 0.25;
+// Slower than this and there's nothing to see
+const MIN_DRAWN_SPEED: f32 = 0.01;
+
+impl GameState {
+    /// Draws the world as the physics sees it: every collider coloured by how it moves, the
+    /// broadphase boxes around them, the contacts between bodies and their velocities.
+    /// Whatever the camera is attached to is left out, apart from its contacts.
+    pub fn draw_physics(&self, map: &Map, draw: &mut DebugDraw) {
+        if !draw.enabled {
+            return;
+        }
+        let (body_ids, bodies) = self.bodies(map);
+        for (id, body) in body_ids.iter().zip(bodies.iter()) {
+            //the camera's own object would only be seen from the inside
+            if *id == self.camera.object_attachment {
+                continue;
+            }
+            let physics_state = match self.object_physics(*id) {
+                Some(physics_state) => physics_state,
+                None => continue,
+            };
+            let colour = if physics_state.is_kinematic() {
+                KINEMATIC_COLOUR
+            } else if body.inverse_mass == 0.0 {
+                STATIC_COLOUR
+            } else if physics_state.asleep {
+                ASLEEP_COLOUR
+            } else {
+                AWAKE_COLOUR
+            };
+            match body.shape {
+                BodyShape::Convex(collider) => draw_collider(draw, &collider, colour),
+                BodyShape::Mesh(instance) => {
+                    let place = |vertex| instance.rotation.rotate_vector(vertex) + instance.position;
+                    for triangle in instance.mesh.triangles() {
+                        let [a, b, c] = triangle.vertices.map(place);
+                        draw.line(a, b, colour);
+                        draw.line(b, c, colour);
+                        draw.line(c, a, colour);
+                    }
+                },
+            }
+            if body.velocity.magnitude() > MIN_DRAWN_SPEED {
+                draw.arrow(body.shape.position(), body.velocity * VELOCITY_SECONDS, VELOCITY_COLOUR);
+            }
+        }
+
+        self.broadphase.boxes(|_, min, max| draw.bounds(min, max, BROADPHASE_COLOUR));
+
+        //found again rather than kept from the last tick, so they match the bodies as drawn
+        let mut body_indices: Vec<Option<usize>> = vec![None; self.objects.capacity() as usize];
+        for (i, id) in body_ids.iter().enumerate() {
+            body_indices[id.index()] = Some(i);
+        }
+        let mut pairs: Vec<(usize, usize)> = Vec::new();
+        self.broadphase.pairs(|a, b| {
+            if let (Some(i), Some(j)) = (body_indices[a.index()], body_indices[b.index()]) {
+                pairs.push((i, j));
+            }
+        });
+        for c in solver::find_contacts(&bodies, &pairs, TICK_DURATION_SEC) {
+            draw.point(c.contact.point, CONTACT_SIZE, CONTACT_COLOUR);
+            draw.arrow(c.contact.point, c.contact.normal * NORMAL_LENGTH, CONTACT_COLOUR);
+        }
+    }
+}
+
+fn draw_collider(draw: &mut DebugDraw, collider: &Collider, colour: Vector3<f32>) {
+    let command = match *collider {
+        Collider::Sphere(sphere) => DebugCommand::Sphere {
+            center: sphere.position,
+            radius: sphere.radius,
+        },
+        Collider::Aabb(aabb) => {
+            draw.bounds(aabb.min(), aabb.max(), colour);
+            return;
+        },
+        Collider::OrientedBox(oriented_box) => DebugCommand::Box {
+            center: oriented_box.position,
+            half_extents: oriented_box.half_extents,
+            rotation: oriented_box.rotation,
+        },
+        Collider::Capsule(capsule) => DebugCommand::Capsule {
+            center: capsule.position,
+            rotation: capsule.rotation,
+            radius: capsule.radius,
+            half_height: capsule.half_height,
+        },
+        //planes go on forever, so just show where and which way they face
+        Collider::Plane(plane) => {
+            let point = plane.normal * -plane.d;
+            draw.point(point, CONTACT_SIZE, colour);
+            draw.arrow(point, plane.normal, colour);
+            return;
+        },
+    };
+    draw.push(command, colour);
+}
+
+mod tests {
+    use super::*;
+    use super::super::test_map::test_map;
+
+    const TEST_MAP: &str = r#"
+        [physics.floor]
+        mass = 0.0
+        shape = {type = "box", half_extents = [10.0, 10.0, 1.0]}
+
+        [physics.ball]
+        mass = 1.0
+        shape = {type = "sphere", radius = 0.5}
+
+        [object.floor]
+        physics = "floor"
+        model = "maps/cube.gltf"
+        colour = [0.5, 0.5, 0.5]
+
+        [object.ball]
+        physics = "ball"
+        model = "maps/cube.gltf"
+        colour = [0.0, 0.0, 1.0]
+
+        [[scenario.scenery]]
+        object_type = "floor"
+        position = {pos = [0.0, 0.0, -1.0]}
+
+        [[scenario.scenery]]
+        object_type = "ball"
+        position = {pos = [0.0, 0.0, 2.0]}
+    "#;
+
+    fn count(draw: &DebugDraw, colour: Vector3<f32>) -> usize {
+        draw.commands().iter().filter(|(_, c)| *c == colour).count()
+    }
+
+    #[test]
+    fn test_draw_physics() {
+        let map = test_map(TEST_MAP);
+        let mut state = GameState::init(&map);
+        let mut draw = DebugDraw::new();
+        state.draw_physics(&map, &mut draw);
+        assert!(draw.is_empty());
+
+        //falling, the ball has a velocity but hasn't touched anything yet
+        draw.toggle();
+        state.advance_to_tick(&map, 20);
+        state.draw_physics(&map, &mut draw);
+        assert_eq!(1, count(&draw, STATIC_COLOUR));
+        assert_eq!(1, count(&draw, AWAKE_COLOUR));
+        assert_eq!(2, count(&draw, BROADPHASE_COLOUR));
+        assert_eq!(1, count(&draw, VELOCITY_COLOUR));
+        assert_eq!(0, count(&draw, CONTACT_COLOUR));
+
+        //resting on the floor, touching it at the ball's lowest point
+        draw.clear();
+        state.advance_to_tick(&map, 120);
+        state.draw_physics(&map, &mut draw);
+        let contact = draw.commands().iter().find_map(|(command, _)| match command {
+            DebugCommand::Point { position, .. } => Some(*position),
+            _ => None,
+        });
+        let contact = contact.expect("no contact drawn");
+        assert!((contact - Vector3::new(0.0, 0.0, 0.0)).magnitude() < 0.05, "contact at {:?}", contact);
+        assert!(draw.commands().iter().any(|(command, colour)| {
+            matches!(command, DebugCommand::Arrow { vector, .. } if vector.z.abs() > 0.45) && *colour == CONTACT_COLOUR
+        }));
+    }
+}
@@ -1,13 +1,38 @@
 use cgmath::{prelude::*, Vector3, Quaternion};
 use super::transform::Transform;
+use super::character::CharacterState;
+use super::mover::MoverState;
 use super::prelude::*;
+use crate::game::physics::{Collider, CollisionFilter, DEFAULT_LAYER};
+
+// Acceleration of gravity at a gravity scale of 1, in metres per second squared
+pub const EARTH_GRAVITY: f32 = 9.81;
 
 state_nodef! {
     pub struct PhysicsState {
         pub tag: TagId,
         pub prev_transform: Transform,
         pub velocity: Vector3<f32>,
-        pub angular_velocity: Quaternion<f32>,
+        // World space axis scaled by radians per second
+        pub angular_velocity: Vector3<f32>,
+        // Reciprocals of the principal moments of inertia about the collider's axes, or zero
+        // for bodies without a shape or mass
+        pub inverse_inertia: Vector3<f32>,
+        // Centered on the origin, see `world_collider`
+        pub collider: Option<Collider>,
+        // Bit flags of the collision layers this body belongs to
+        pub layer: u32,
+        // Bit flags of the collision layers this body collides with
+        pub mask: u32,
+        pub character: Option<CharacterState>,
+        pub mover: Option<MoverState>,
+        // Asleep bodies are left out of the simulation until something disturbs them
+        pub asleep: bool,
+        // Consecutive fixed updates spent moving slowly enough to fall asleep
+        pub rest_ticks: u32,
+        // The water this is in, if any, and whether it's at least half underwater
+        pub water: SaltyId,
+        pub submerged: bool,
     }
 }
 
@@ -17,18 +42,91 @@ impl Default for PhysicsState {
             tag: TagId::default(),
             prev_transform: Transform::default(),
             velocity: Vector3::zero(),
-            angular_velocity: Quaternion::zero(),
+            angular_velocity: Vector3::zero(),
+            inverse_inertia: Vector3::zero(),
+            collider: None,
+            layer: DEFAULT_LAYER,
+            mask: u32::MAX,
+            character: None,
+            mover: None,
+            asleep: false,
+            rest_ticks: 0,
+            water: NONE,
+            submerged: false,
         }
     }
 }
 
 impl PhysicsState {
-    pub fn init(game_state: &mut GameState, _map: &Map, physics_tag_id: &TagId, transform: Transform) -> SaltyId {
+    pub fn init(game_state: &mut GameState, map: &Map, physics_tag_id: &TagId, mover_tag_id: Option<TagId>, transform: Transform) -> SaltyId {
+        let physics_tag = map.get_physics(physics_tag_id);
+        let collider = physics_tag
+            .and_then(|physics_tag| physics_tag.shape)
+            .and_then(|shape| shape.to_collider());
+        let character = match (physics_tag.and_then(|physics_tag| physics_tag.character.as_ref()), collider) {
+            (Some(_), Some(standing)) => Some(CharacterState::init(standing)),
+            _ => None,
+        };
+        let mover = mover_tag_id.map(|mover_tag_id| MoverState::init(mover_tag_id, transform.position));
+
+        let inertia = match (physics_tag, collider) {
+            (Some(physics_tag), Some(collider)) => collider.inertia(physics_tag.mass),
+            _ => Vector3::zero(),
+        };
+        let inverse_inertia = if inertia.x > 0.0 && inertia.y > 0.0 && inertia.z > 0.0 {
+            Vector3::new(1.0 / inertia.x, 1.0 / inertia.y, 1.0 / inertia.z)
+        } else {
+            Vector3::zero()
+        };
+
+        //layer names are checked when the map loads
+        let filter = physics_tag
+            .and_then(|physics_tag| physics_tag.collision_filter(&map.globals).ok())
+            .unwrap_or_default();
+
         game_state.physics.add(PhysicsState {
-            tag: physics_tag_id.clone(),
+            tag: *physics_tag_id,
             prev_transform: transform,
             velocity: Vector3::zero(),
-            angular_velocity: Quaternion::zero(),
+            angular_velocity: Vector3::zero(),
+            inverse_inertia,
+            collider,
+            layer: filter.layer,
+            mask: filter.mask,
+            character,
+            mover,
+            asleep: false,
+            rest_ticks: 0,
+            water: NONE,
+            submerged: false,
         }).unwrap()
     }
-}
\ No newline at end of file
+
+    pub fn wake(&mut self) {
+        self.asleep = false;
+        self.rest_ticks = 0;
+    }
+
+    /// Whether this moves itself rather than being moved by gravity and the solver.
+    pub fn is_kinematic(&self) -> bool {
+        self.character.is_some() || self.mover.is_some()
+    }
+
+    pub fn collision_filter(&self) -> CollisionFilter {
+        CollisionFilter {
+            layer: self.layer,
+            mask: self.mask,
+        }
+    }
+
+    /// This object's collider placed at its transform and moving with its velocity. Characters
+    /// stay upright whichever way they face.
+    pub fn world_collider(&self, transform: &Transform) -> Option<Collider> {
+        let rotation = if self.character.is_some() {
+            Quaternion::one()
+        } else {
+            transform.rotation
+        };
+        self.collider.map(|collider| collider.placed(transform.position, rotation, self.velocity))
+    }
+}
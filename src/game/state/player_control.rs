@@ -6,6 +6,8 @@ use cgmath::{Euler, InnerSpace, Vector3, Rad, Quaternion};
 state_nodef! {
     pub struct PlayerControl {
         pub target_object: SaltyId,
+        // What the player spawned as, which they go back to on unpossessing
+        pub own_object: SaltyId,
         pub forward: bool,
         pub back: bool,
         pub right: bool,
@@ -23,6 +25,7 @@ impl Default for PlayerControl {
     fn default() -> Self {
         PlayerControl {
             target_object: NONE,
+            own_object: NONE,
             forward: false,
             back: false,
             right: false,
@@ -64,6 +67,19 @@ impl PlayerControl {
         result.normalize()
     }
 
+    /// Lets go of every held control and aims the way `rotation` faces, for switching what's
+    /// controlled without carrying over keys held for the last thing.
+    pub fn reset(&mut self, rotation: Quaternion<f32>) {
+        let facing = rotation.rotate_vector(Vector3::unit_x());
+        *self = PlayerControl {
+            target_object: self.target_object,
+            own_object: self.own_object,
+            yaw: -facing.y.atan2(facing.x),
+            pitch: (-facing.z).clamp(-1.0, 1.0).asin(),
+            ..PlayerControl::default()
+        };
+    }
+
     pub fn aim_delta(&mut self, d_yaw: f32, d_pitch: f32) {
         self.yaw += d_yaw;
         self.pitch = (self.pitch + d_pitch).clamp(-FRAC_PI_2, FRAC_PI_2);
@@ -73,6 +89,6 @@ impl PlayerControl {
         let yaw_q: Quaternion<f32> = Quaternion::from_angle_z(Rad(-self.yaw));
         let pitch_q: Quaternion<f32> = Quaternion::from_angle_y(Rad(self.pitch));
         let roll_q: Quaternion<f32> = Quaternion::from_angle_x(Rad(self.roll));
-        return yaw_q * pitch_q * roll_q;
+        yaw_q * pitch_q * roll_q
     }
 }
\ No newline at end of file
@@ -0,0 +1,275 @@
+use cgmath::{prelude::*, Quaternion, Vector3};
+use super::prelude::*;
+use super::query::QueryFilter;
+use super::transform::Transform;
+
+// Furthest away the player can use something from
+const USE_DISTANCE: f32 = 3.0;
+// Room left between a vehicle and the player climbing out of it
+const EXIT_GAP: f32 = 0.2;
+
+impl GameState {
+    /// Gives the player control of an object, with the camera following it. Held controls are
+    /// let go and the aim starts the way the object faces. Returns false if there's no such object.
+    pub fn possess(&mut self, object: SaltyId) -> bool {
+        let rotation = match self.objects.get(object) {
+            Some(object_state) => object_state.transform.rotation,
+            None => return false,
+        };
+        self.player_control.target_object = object;
+        self.player_control.reset(rotation);
+        self.camera.object_attachment = object;
+        true
+    }
+
+    /// Watches an object through the camera without controlling anything, or returns false if
+    /// there's no such object.
+    pub fn spectate(&mut self, object: SaltyId) -> bool {
+        if self.objects.get(object).is_none() {
+            return false;
+        }
+        self.player_control.target_object = NONE;
+        self.player_control.reset(Quaternion::one());
+        self.camera.object_attachment = object;
+        true
+    }
+
+    /// Gives the player back the object they spawned as. Riding in a vehicle climbs out beside it,
+    /// so returns false without leaving if there's no room.
+    pub fn unpossess(&mut self, map: &Map) -> bool {
+        let own_object = self.player_control.own_object;
+        let riding = self.objects.get(own_object).map_or(NONE, |object_state| object_state.riding);
+        if riding.is_some() && !self.climb_out(map, riding) {
+            return false;
+        }
+        self.possess(own_object)
+    }
+
+    /// What the use action does: lets go of whatever the player has possessed or is watching,
+    /// otherwise possesses the possessable object they're looking at up close.
+    pub fn use_target(&mut self, map: &Map) -> bool {
+        let own_object = self.player_control.own_object;
+        if self.player_control.target_object != own_object || self.camera.object_attachment != own_object {
+            return self.unpossess(map);
+        }
+        let position = match self.objects.get(own_object) {
+            Some(object_state) => object_state.transform.position,
+            None => return false,
+        };
+        let direction = self.player_control.get_aim_rot().rotate_vector(Vector3::unit_x());
+        let filter = QueryFilter::default().excluding(own_object);
+        let hit = match self.raycast(map, position, direction, USE_DISTANCE, &filter) {
+            Some(hit) => hit,
+            None => return false,
+        };
+        let possessable = self.objects.get(hit.object)
+            .and_then(|object_state| map.get_object(&object_state.tag))
+            .is_some_and(|object_tag| object_tag.possessable());
+        if !possessable || !self.possess(hit.object) {
+            return false;
+        }
+        if self.vehicle_tag(map, hit.object).is_some() {
+            self.get_in(hit.object);
+        }
+        true
+    }
+
+    /// Takes the player's own object out of the world while it rides in a vehicle, so it can't be
+    /// hit, pushed or seen. It's left asleep where it was until it climbs out.
+    fn get_in(&mut self, vehicle: SaltyId) {
+        let own_object = self.player_control.own_object;
+        if let Some(object_state) = self.objects.get_mut(own_object) {
+            object_state.riding = vehicle;
+        }
+        self.broadphase.remove(own_object);
+        if let Some(physics_state) = self.object_physics_mut(own_object) {
+            physics_state.velocity = Vector3::zero();
+            physics_state.angular_velocity = Vector3::zero();
+            physics_state.asleep = true;
+        }
+    }
+
+    /// Moves the player's own object out beside a vehicle, trying either side, then behind, then
+    /// on top. Returns false if they're all blocked.
+    fn climb_out(&mut self, map: &Map, vehicle: SaltyId) -> bool {
+        let own_object = self.player_control.own_object;
+        let (vehicle_state, own_state) = match (self.objects.get(vehicle), self.objects.get(own_object)) {
+            (Some(vehicle_state), Some(own_state)) => (*vehicle_state, *own_state),
+            _ => return false,
+        };
+        let reach = |object: SaltyId, transform: &Transform| {
+            self.object_physics(object)
+                .and_then(|physics_state| physics_state.world_collider(transform))
+                .and_then(|collider| collider.as_convex().map(|convex| convex.bounds()))
+                .map_or(0.0, |(min, max)| (max - min).magnitude() / 2.0)
+        };
+        let distance = reach(vehicle, &vehicle_state.transform) + reach(own_object, &own_state.transform) + EXIT_GAP;
+
+        let rotation = vehicle_state.transform.rotation;
+        let side = rotation.rotate_vector(Vector3::unit_y());
+        let back = -rotation.rotate_vector(Vector3::unit_x());
+        let filter = QueryFilter::default()
+            .excluding(own_object)
+            .with_mask(self.object_physics(own_object).map_or(u32::MAX, |physics_state| physics_state.mask));
+        for direction in [side, -side, back, Vector3::unit_z()] {
+            let transform = Transform {
+                position: vehicle_state.transform.position + direction * distance,
+                ..own_state.transform
+            };
+            let collider = self.object_physics(own_object).and_then(|physics_state| physics_state.world_collider(&transform));
+            if collider.is_some_and(|collider| self.shape_cast(map, &collider, Vector3::unit_z(), 0.0, &filter).is_some()) {
+                continue;
+            }
+            let object_state = self.objects.get_mut(own_object).unwrap();
+            object_state.transform = transform;
+            object_state.riding = NONE;
+            if let Some(physics_state) = self.object_physics_mut(own_object) {
+                physics_state.prev_transform = transform;
+                physics_state.velocity = Vector3::zero();
+                physics_state.wake();
+            }
+            return true;
+        }
+        false
+    }
+}
+
+mod tests {
+    use super::*;
+    use super::super::test_map::{find, test_map};
+
+    const POSSESSION_MAP: &str = r#"
+        [physics.floor]
+        mass = 0.0
+        shape = {type = "box", half_extents = [50.0, 50.0, 1.0]}
+
+        [physics.biped]
+        mass = 1.0
+        shape = {type = "capsule", radius = 0.5, half_height = 0.5}
+        character = {}
+
+        [physics.kart]
+        mass = 200.0
+        shape = {type = "box", half_extents = [1.5, 0.8, 0.6]}
+
+        [vehicle.kart]
+        suspension_points = [[1.2, 0.7, -0.6], [1.2, -0.7, -0.6], [-1.2, 0.7, -0.6], [-1.2, -0.7, -0.6]]
+
+        [physics.turret]
+        mass = 0.0
+        shape = {type = "box", half_extents = [0.5, 0.5, 0.5]}
+
+        [object.floor]
+        physics = "floor"
+        model = "maps/cube.gltf"
+        colour = [0.5, 0.5, 0.5]
+
+        [object.player]
+        physics = "biped"
+
+        [object.kart]
+        physics = "kart"
+        vehicle = "kart"
+        model = "maps/cube.gltf"
+        colour = [1.0, 1.0, 0.0]
+        possessable = true
+
+        [object.turret]
+        physics = "turret"
+        model = "maps/cube.gltf"
+        colour = [0.0, 1.0, 0.0]
+        possessable = true
+
+        [object.crate]
+        physics = "turret"
+        model = "maps/cube.gltf"
+        colour = [0.0, 0.0, 1.0]
+
+        [scenario]
+        player_location = {pos = [0.0, 0.0, 1.0]}
+
+        [[scenario.scenery]]
+        object_type = "floor"
+        position = {pos = [0.0, 0.0, -1.0]}
+
+        [[scenario.scenery]]
+        object_type = "kart"
+        position = {pos = [3.0, 0.0, 1.0]}
+
+        [[scenario.scenery]]
+        object_type = "turret"
+        position = {pos = [-2.0, 0.0, 1.0]}
+
+        [[scenario.scenery]]
+        object_type = "crate"
+        position = {pos = [0.0, 2.0, 1.0]}
+    "#;
+
+    #[test]
+    fn test_possession() {
+        let map = test_map(POSSESSION_MAP);
+        let mut state = GameState::init(&map);
+        let player = state.player_control.own_object;
+        let (kart, turret) = (find(&state, "kart"), find(&state, "turret"));
+        assert_eq!(player, state.player_control.target_object);
+        assert_eq!(player, state.camera.object_attachment);
+        state.advance_to_tick(&map, 60);
+
+        //looking at the kart and getting in, without carrying over the keys held on foot
+        state.player_control.forward = true;
+        assert!(state.use_target(&map));
+        assert_eq!(kart, state.player_control.target_object);
+        assert_eq!(kart, state.camera.object_attachment);
+        assert!(!state.player_control.forward);
+
+        //riding along, with the player out of the way rather than left standing where they got in
+        assert_eq!(kart, state.objects.get(player).unwrap().riding);
+        let standing = state.objects.get(player).unwrap().transform.position;
+        state.player_control.forward = true;
+        state.update_variable(&map);
+        state.advance_to_tick(&map, 180);
+        let parked = state.objects.get(kart).unwrap().transform.position;
+        assert!(parked.x > 10.0, "kart at {:?}", parked);
+        assert!(state.body(&map, state.objects.get(player).unwrap()).is_none());
+        let hit = state.raycast(&map, standing + Vector3::unit_z() * 5.0, -Vector3::unit_z(), 10.0, &QueryFilter::default());
+        assert_ne!(player, hit.unwrap().object);
+
+        //and climbing out beside it
+        state.player_control.forward = false;
+        assert!(state.use_target(&map));
+        assert_eq!(player, state.player_control.target_object);
+        assert_eq!(player, state.camera.object_attachment);
+        let climbed_out = state.objects.get(player).unwrap().transform.position;
+        assert!((climbed_out - parked).magnitude() < 4.0, "climbed out at {:?}", climbed_out);
+        assert_eq!(NONE, state.objects.get(player).unwrap().riding);
+        assert!(state.body(&map, state.objects.get(player).unwrap()).is_some());
+        state.advance_to_tick(&map, 240);
+        assert!(state.object_physics(player).unwrap().character.unwrap().grounded);
+
+        //only possessable things can be used, like turrets, which stay put as they turn to aim
+        let standing = Vector3::new(0.0, 0.0, 1.0);
+        state.objects.get_mut(player).unwrap().transform.position = standing;
+        state.player_control.yaw = -std::f32::consts::FRAC_PI_2;
+        assert!(!state.use_target(&map));
+        assert_eq!(player, state.player_control.target_object);
+        state.player_control.yaw = std::f32::consts::PI;
+        assert!(state.use_target(&map));
+        assert_eq!(turret, state.player_control.target_object);
+        state.player_control.aim_delta(1.0, 0.0);
+        state.update_variable(&map);
+        let facing = state.objects.get(turret).unwrap().transform.rotation.rotate_vector(Vector3::unit_x());
+        assert!(facing.y < -0.5, "facing {:?}", facing);
+        assert!(state.use_target(&map));
+        assert_eq!(player, state.player_control.target_object);
+        assert_eq!(standing, state.objects.get(player).unwrap().transform.position);
+
+        //spectating just moves the camera, until used again
+        assert!(state.spectate(kart));
+        assert_eq!(NONE, state.player_control.target_object);
+        assert_eq!(kart, state.camera.object_attachment);
+        state.update_variable(&map);
+        assert!(state.use_target(&map));
+        assert_eq!(player, state.camera.object_attachment);
+        assert!(!state.possess(NONE));
+    }
+}
@@ -0,0 +1,230 @@
+use cgmath::{prelude::*, Vector3};
+use super::prelude::*;
+use super::object_state::ObjectState;
+use crate::game::physics::{CollideWith, Collider, Contact, Convex, Sphere};
+use crate::game::physics::solver::BodyShape;
+
+/// Which objects a query can hit.
+#[derive(Copy, Clone)]
+pub struct QueryFilter {
+    // Bodies are only hit if they're on one of these layers
+    pub mask: u32,
+    // Usually the object doing the querying, so a shot doesn't hit its shooter
+    pub exclude: SaltyId,
+}
+
+impl Default for QueryFilter {
+    fn default() -> QueryFilter {
+        QueryFilter {
+            mask: u32::MAX,
+            exclude: NONE,
+        }
+    }
+}
+
+impl QueryFilter {
+    pub fn excluding(self, exclude: SaltyId) -> QueryFilter {
+        QueryFilter {
+            exclude,
+            ..self
+        }
+    }
+
+    pub fn with_mask(self, mask: u32) -> QueryFilter {
+        QueryFilter {
+            mask,
+            ..self
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct RayHit {
+    pub object: SaltyId,
+    pub point: Vector3<f32>,
+    // Surface normal of what was hit, facing back along the ray
+    pub normal: Vector3<f32>,
+    // Along the ray to the point of contact, or to the cast sphere's center at contact
+    pub distance: f32,
+}
+
+impl GameState {
+    /// The nearest object along a ray within `max_distance`.
+    pub fn raycast(&self, map: &Map, origin: Vector3<f32>, direction: Vector3<f32>, max_distance: f32, filter: &QueryFilter) -> Option<RayHit> {
+        self.sphere_cast(map, origin, direction, 0.0, max_distance, filter)
+    }
+
+    /// The nearest object a sphere moving along a ray from `origin` would hit within
+    /// `max_distance`. Objects the sphere already overlaps are hit at zero distance.
+    pub fn sphere_cast(&self, map: &Map, origin: Vector3<f32>, direction: Vector3<f32>, radius: f32, max_distance: f32, filter: &QueryFilter) -> Option<RayHit> {
+        let sphere = Collider::Sphere(Sphere {
+            radius,
+            position: origin,
+            velocity: Vector3::zero(),
+        });
+        self.shape_cast(map, &sphere, direction, max_distance, filter)
+    }
+
+    /// Like `sphere_cast`, for any shape. The shape starts wherever it's placed, and the distance
+    /// is how far it moved before hitting.
+    pub fn shape_cast(&self, map: &Map, shape: &Collider, direction: Vector3<f32>, max_distance: f32, filter: &QueryFilter) -> Option<RayHit> {
+        if direction.magnitude2() == 0.0 {
+            return None;
+        }
+        //moving at unit speed makes time of contact the distance travelled
+        let cast = shape.placed(shape.position(), shape.rotation(), direction.normalize());
+        let convex = cast.as_convex()?;
+        let (min, max) = convex.swept_bounds(max_distance);
+
+        let mut nearest: Option<RayHit> = None;
+        self.broadphase.query(min, max, |id| {
+            if id == filter.exclude {
+                return;
+            }
+            if let Some(contact) = self.cast_against(map, id, &cast, max_distance, filter) {
+                if nearest.is_none_or(|hit| contact.toc < hit.distance) {
+                    nearest = Some(RayHit {
+                        object: id,
+                        point: contact.point,
+                        normal: contact.normal,
+                        distance: contact.toc,
+                    });
+                }
+            }
+        });
+        nearest
+    }
+
+    pub(crate) fn cast_against(&self, map: &Map, id: SaltyId, cast: &Collider, max_distance: f32, filter: &QueryFilter) -> Option<Contact> {
+        let object_state: &ObjectState = self.objects.get(id)?;
+        let physics_state = self.physics.get(object_state.physics_id)?;
+        if physics_state.layer & filter.mask == 0 {
+            return None;
+        }
+        //the world holds still while the query moves through it
+        match self.body(map, object_state)?.shape {
+            BodyShape::Convex(collider) => {
+                let still = collider.placed(collider.position(), collider.rotation(), Vector3::zero());
+                cast.contact(&still)
+            },
+            BodyShape::Mesh(mesh) => mesh.sweep(cast.as_convex()?, max_distance),
+        }.filter(|contact| contact.toc <= max_distance)
+    }
+}
+
+mod tests {
+    use super::*;
+    use super::super::test_map::{find, test_map};
+    use crate::math::realcmp;
+
+    const TEST_MAP: &str = r#"
+        [physics.floor]
+        mass = 0.0
+        shape = {type = "box", half_extents = [10.0, 10.0, 1.0]}
+
+        [physics.crate]
+        mass = 1.0
+        shape = {type = "box", half_extents = [1.0, 1.0, 1.0]}
+
+        [physics.block]
+        mass = 0.0
+        shape = {type = "mesh", model = "maps/cube.gltf"}
+
+        [object.floor]
+        physics = "floor"
+        model = "maps/cube.gltf"
+        colour = [0.5, 0.5, 0.5]
+
+        [object.crate]
+        physics = "crate"
+        model = "maps/cube.gltf"
+        colour = [0.0, 0.0, 1.0]
+
+        [object.block]
+        physics = "block"
+        model = "maps/cube.gltf"
+        colour = [0.0, 1.0, 0.0]
+
+        [[scenario.scenery]]
+        object_type = "floor"
+        position = {pos = [0.0, 0.0, -1.0]}
+
+        [[scenario.scenery]]
+        object_type = "crate"
+        position = {pos = [5.0, 0.0, 1.0]}
+
+        [[scenario.scenery]]
+        object_type = "block"
+        position = {pos = [-5.0, 0.0, 1.0]}
+    "#;
+
+    fn load() -> (Map, Box<GameState>) {
+        let mut map = test_map(TEST_MAP);
+        map.load_collision_meshes();
+        let state = GameState::init(&map);
+        (map, state)
+    }
+
+    #[test]
+    fn test_raycast() {
+        let (map, state) = load();
+        let filter = QueryFilter::default();
+
+        let hit = state.raycast(&map, Vector3::new(0.0, 0.0, 5.0), -Vector3::unit_z(), 100.0, &filter).unwrap();
+        assert_eq!(find(&state, "floor"), hit.object);
+        assert!(realcmp(hit.distance, 5.0, 0.001));
+        assert!(realcmp(hit.normal.z, 1.0, 0.001));
+        assert!(realcmp(hit.point.z, 0.0, 0.001));
+
+        let crate_id = find(&state, "crate");
+        let hit = state.raycast(&map, Vector3::new(0.0, 0.0, 1.0), Vector3::unit_x(), 100.0, &filter).unwrap();
+        assert_eq!(crate_id, hit.object);
+        assert!(realcmp(hit.distance, 4.0, 0.001));
+        assert!(realcmp(hit.normal.x, -1.0, 0.001));
+        assert!(state.raycast(&map, Vector3::new(0.0, 0.0, 1.0), Vector3::unit_x(), 3.0, &filter).is_none());
+
+        //static mesh geometry is hit too
+        let hit = state.raycast(&map, Vector3::new(-5.0, 5.0, 1.0), -Vector3::unit_y(), 100.0, &filter).unwrap();
+        assert_eq!(find(&state, "block"), hit.object);
+        assert!(realcmp(hit.distance, 4.0, 0.001));
+
+        //facing back along the ray, whichever way the triangles wind
+        assert!(realcmp(hit.normal.y, 1.0, 0.001));
+        for (origin, direction) in [
+            (Vector3::new(-5.3, 0.4, 5.0), -Vector3::unit_z()),
+            (Vector3::new(-10.0, 0.2, 1.3), Vector3::unit_x()),
+        ] {
+            let hit = state.raycast(&map, origin, direction, 100.0, &QueryFilter::default().excluding(crate_id)).unwrap();
+            assert!(realcmp(hit.normal.dot(direction), -1.0, 0.001), "{:?} facing {:?}", direction, hit.normal);
+        }
+    }
+
+    #[test]
+    fn test_raycast_filter() {
+        let (map, mut state) = load();
+        let crate_id = find(&state, "crate");
+        let origin = Vector3::new(0.0, 0.0, 1.0);
+
+        let excluding = QueryFilter::default().excluding(crate_id);
+        assert!(state.raycast(&map, origin, Vector3::unit_x(), 100.0, &excluding).is_none());
+
+        let physics_id = state.objects.get(crate_id).unwrap().physics_id;
+        state.physics.get_mut(physics_id).unwrap().layer = 2;
+        assert!(state.raycast(&map, origin, Vector3::unit_x(), 100.0, &QueryFilter::default().with_mask(1)).is_none());
+        assert!(state.raycast(&map, origin, Vector3::unit_x(), 100.0, &QueryFilter::default().with_mask(2)).is_some());
+    }
+
+    #[test]
+    fn test_sphere_cast() {
+        let (map, state) = load();
+        let filter = QueryFilter::default();
+        let hit = state.sphere_cast(&map, Vector3::new(0.0, 0.0, 1.0), Vector3::unit_x(), 0.5, 100.0, &filter).unwrap();
+        assert_eq!(find(&state, "crate"), hit.object);
+        assert!(realcmp(hit.distance, 3.5, 0.001));
+        assert!(realcmp(hit.point.x, 4.0, 0.001));
+
+        //already touching the floor
+        let hit = state.sphere_cast(&map, Vector3::new(0.0, 0.0, 0.25), Vector3::unit_y(), 0.5, 100.0, &filter).unwrap();
+        assert_eq!(0.0, hit.distance);
+    }
+}
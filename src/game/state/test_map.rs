@@ -0,0 +1,71 @@
+use super::prelude::*;
+
+// What every test map starts from: a player without physics hovering over the origin
+const BASE_MAP: &str = r#"
+    [object.player]
+    model = "maps/cube.gltf"
+    colour = [1.0, 0.0, 0.0]
+
+    [globals]
+    gravity_scale = 1.0
+    player_object = "player"
+    player_accel = 20.0
+
+    [scenario]
+    player_location = {pos = [0.0, 0.0, 20.0]}
+"#;
+
+/// Builds a map for a test from the base map, with the tables in `extra` merged over it. Tests
+/// only give the tags and scenery they need, and any globals or player settings they change.
+pub fn test_map(extra: &str) -> Map {
+    let mut map: toml::Value = toml::from_str(BASE_MAP).unwrap();
+    merge(&mut map, toml::from_str(extra).unwrap());
+    map.try_into().unwrap()
+}
+
+fn merge(base: &mut toml::Value, extra: toml::Value) {
+    match (base, extra) {
+        (toml::Value::Table(base), toml::Value::Table(extra)) => {
+            for (key, value) in extra {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    },
+                }
+            }
+        },
+        (base, extra) => *base = extra,
+    }
+}
+
+/// The first object placed from the given object tag.
+pub fn find(state: &GameState, object_type: &str) -> SaltyId {
+    state.objects.iter()
+        .find(|(_, object)| String::from(object.tag) == object_type)
+        .map(|(id, _)| id)
+        .unwrap()
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let map = test_map(r#"
+            [physics.biped]
+            mass = 1.0
+
+            [object.player]
+            physics = "biped"
+
+            [globals]
+            gravity_scale = 0.5
+        "#);
+        let player = map.get_object(&map.globals.player_object).unwrap();
+        assert_eq!("biped", String::from(player.physics.unwrap()));
+        assert_eq!([1.0, 0.0, 0.0], player.colour);
+        assert_eq!(0.5, map.globals.gravity_scale);
+        assert_eq!(20.0, map.globals.player_accel);
+    }
+}
@@ -0,0 +1,238 @@
+use cgmath::{prelude::*, Vector3};
+use super::prelude::*;
+use crate::game::physics::{CollideWith, Collider, Convex, Sphere};
+use crate::game::tags::{TagString, TriggerPlacement};
+
+// Most objects a trigger keeps track of at once; any more go unnoticed until there's room
+pub const MAX_OCCUPANTS: usize = 32;
+
+state_nodef! {
+    pub struct TriggerState {
+        pub name: TagString,
+        // Placed in the world
+        pub collider: Collider,
+        // Bit flags of the collision layers detected
+        pub mask: u32,
+        // Objects inside as of the last fixed update, with unused slots left as NONE
+        pub occupants: [SaltyId; MAX_OCCUPANTS],
+    }
+}
+
+impl Default for TriggerState {
+    fn default() -> Self {
+        TriggerState {
+            name: TagString::default(),
+            collider: Collider::Sphere(Sphere {
+                radius: 0.0,
+                position: Vector3::zero(),
+                velocity: Vector3::zero(),
+            }),
+            mask: u32::MAX,
+            occupants: [NONE; MAX_OCCUPANTS],
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TriggerEventKind {
+    Enter,
+    Exit,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct TriggerEvent {
+    pub trigger: SaltyId,
+    pub object: SaltyId,
+    pub kind: TriggerEventKind,
+    pub tick: u32,
+}
+
+impl TriggerState {
+    pub fn init(game_state: &mut GameState, map: &Map, placement: &TriggerPlacement) -> SaltyId {
+        let transform = placement.position.to_transform();
+        let collider = match placement.shape.to_collider() {
+            Some(collider) => collider.placed(transform.position, transform.rotation, Vector3::zero()),
+            //shapes are checked when the map loads
+            None => return NONE,
+        };
+        //layer names are checked when the map loads
+        let mask = placement.mask.as_ref()
+            .map(|mask| map.globals.layer_bits(mask).unwrap_or(0))
+            .unwrap_or(u32::MAX);
+
+        game_state.triggers.add(TriggerState {
+            name: placement.name,
+            collider,
+            mask,
+            occupants: [NONE; MAX_OCCUPANTS],
+        }).unwrap_or(NONE)
+    }
+}
+
+impl GameState {
+    /// Compares what's inside each trigger now with the last fixed update, sending enter and exit
+    /// events for the differences. Objects staying inside are found with `trigger_occupants`.
+    pub(crate) fn update_triggers(&mut self) {
+        let trigger_ids: Vec<SaltyId> = self.triggers.iter().map(|(id, _)| id).collect();
+        for trigger_id in trigger_ids {
+            let mut trigger = *self.triggers.get(trigger_id).unwrap();
+            let (min, max) = match trigger.collider.as_convex() {
+                Some(convex) => convex.bounds(),
+                None => continue,
+            };
+
+            let mut inside = [NONE; MAX_OCCUPANTS];
+            let mut count = 0;
+            self.broadphase.query(min, max, |id| {
+                if count < MAX_OCCUPANTS && self.touches_trigger(&trigger, id) {
+                    inside[count] = id;
+                    count += 1;
+                }
+            });
+
+            for &object in &inside[..count] {
+                if !trigger.occupants.contains(&object) {
+                    self.push_trigger_event(trigger_id, object, TriggerEventKind::Enter);
+                }
+            }
+            for &object in trigger.occupants.iter().filter(|id| id.is_some()) {
+                if !inside[..count].contains(&object) {
+                    self.push_trigger_event(trigger_id, object, TriggerEventKind::Exit);
+                }
+            }
+            trigger.occupants = inside;
+            *self.triggers.get_mut(trigger_id).unwrap() = trigger;
+        }
+    }
+
+    fn touches_trigger(&self, trigger: &TriggerState, id: SaltyId) -> bool {
+        let object_state = match self.objects.get(id) {
+            Some(object_state) => object_state,
+            None => return false,
+        };
+        match self.physics.get(object_state.physics_id) {
+            Some(physics_state) if physics_state.layer & trigger.mask != 0 => physics_state
+                .world_collider(&object_state.transform)
+                .is_some_and(|collider| trigger.collider.intersects(&collider)),
+            _ => false,
+        }
+    }
+
+    fn push_trigger_event(&mut self, trigger: SaltyId, object: SaltyId, kind: TriggerEventKind) {
+        self.trigger_events.push(TriggerEvent {
+            trigger,
+            object,
+            kind,
+            tick: self.tick,
+        });
+    }
+
+    /// Trigger events since the last call, oldest first. If they aren't taken often enough the
+    /// oldest are dropped to make room.
+    pub fn take_trigger_events(&mut self) -> Vec<TriggerEvent> {
+        let events = self.trigger_events.iter().copied().collect();
+        self.trigger_events.clear();
+        events
+    }
+
+    /// Objects inside the trigger as of the last fixed update.
+    pub fn trigger_occupants(&self, trigger: SaltyId) -> impl Iterator<Item = SaltyId> + '_ {
+        self.triggers.get(trigger)
+            .into_iter()
+            .flat_map(|trigger| trigger.occupants.iter().copied())
+            .filter(|id| id.is_some())
+    }
+
+    pub fn find_trigger(&self, name: &TagString) -> Option<SaltyId> {
+        self.triggers.iter()
+            .find(|(_, trigger)| trigger.name == *name)
+            .map(|(id, _)| id)
+    }
+}
+
+mod tests {
+    use super::*;
+    use super::super::test_map::test_map;
+
+    const TEST_MAP: &str = r#"
+        [physics.crate]
+        mass = 1.0
+        shape = {type = "box", half_extents = [1.0, 1.0, 1.0]}
+
+        [object.crate]
+        physics = "crate"
+        model = "maps/cube.gltf"
+        colour = [0.0, 0.0, 1.0]
+
+        [globals]
+        collision_layers = ["player"]
+
+        [[scenario.scenery]]
+        object_type = "crate"
+        position = {pos = [0.0, 0.0, 5.0]}
+
+        [[scenario.triggers]]
+        name = "checkpoint"
+        position = {pos = [0.0, 0.0, 0.0]}
+        shape = {type = "box", half_extents = [2.0, 2.0, 1.0]}
+
+        [[scenario.triggers]]
+        name = "players_only"
+        position = {pos = [0.0, 0.0, 0.0]}
+        shape = {type = "sphere", radius = 2.0}
+        mask = ["player"]
+    "#;
+
+    #[test]
+    fn test_trigger_events() {
+        let map = test_map(TEST_MAP);
+        let mut state = GameState::init(&map);
+        let crate_id = state.objects.iter()
+            .find(|(_, object)| object.physics_id.is_some())
+            .map(|(id, _)| id)
+            .unwrap();
+        let checkpoint = state.triggers.iter()
+            .find(|(_, trigger)| String::from(trigger.name) == "checkpoint")
+            .map(|(id, _)| id)
+            .unwrap();
+        assert_eq!(Some(checkpoint), state.find_trigger(&state.triggers.get(checkpoint).unwrap().name));
+
+        //the crate falls through the checkpoint, but isn't on a layer the other trigger sees
+        let mut events = Vec::new();
+        let mut inside_ticks = 0;
+        for tick in 1..=90 {
+            state.advance_to_tick(&map, tick);
+            events.extend(state.take_trigger_events());
+            let occupants: Vec<SaltyId> = state.trigger_occupants(checkpoint).collect();
+            if !occupants.is_empty() {
+                assert_eq!(vec![crate_id], occupants);
+                inside_ticks += 1;
+            }
+        }
+        assert!(events.iter().all(|event| event.trigger == checkpoint && event.object == crate_id));
+        let kinds: Vec<TriggerEventKind> = events.iter().map(|event| event.kind).collect();
+        assert_eq!(vec![TriggerEventKind::Enter, TriggerEventKind::Exit], kinds);
+        assert_eq!(events[1].tick - events[0].tick, inside_ticks);
+
+        //entered as the crate's bottom reached the top of the box, 3m down
+        let fall_time = events[0].tick as f32 / 60.0;
+        assert!((0.5 * 9.81 * fall_time * fall_time - 3.0).abs() < 0.3, "entered on tick {}", events[0].tick);
+        assert!(state.take_trigger_events().is_empty());
+    }
+
+    #[test]
+    fn test_mesh_trigger_rejected() {
+        let map = test_map(TEST_MAP);
+        map.scenario.check_triggers().unwrap();
+
+        let mesh_trigger = r#"
+            [[scenario.triggers]]
+            name = "terrain"
+            position = {pos = [0.0, 0.0, 0.0]}
+            shape = {type = "mesh", model = "maps/cube.gltf"}
+        "#;
+        let map = test_map(&format!("{}{}", TEST_MAP, mesh_trigger));
+        let error = map.scenario.check_triggers().unwrap_err();
+        assert!(error.contains("terrain"), "{}", error);
+    }
+}
@@ -0,0 +1,230 @@
+use cgmath::{prelude::*, Vector3};
+use super::prelude::*;
+use super::query::QueryFilter;
+use crate::game::tags::Vehicle;
+
+// Forward speed in metres per second where steering reaches its full turn rate, so vehicles
+// can't spin on the spot
+const FULL_STEER_SPEED: f32 = 5.0;
+// How quickly the turn rate catches up with the steering, per second
+const STEER_RESPONSE: f32 = 8.0;
+// Fraction of the engine's pull available in reverse
+const REVERSE_SCALE: f32 = 0.5;
+
+impl GameState {
+    /// Holds every vehicle up on its suspension for one tick, then drives and steers the ones
+    /// with wheels on the ground. Only the possessed vehicle listens to the controls. Vehicles
+    /// just have their velocities changed, leaving the solver to move them like any other body.
+    pub(crate) fn update_vehicles(&mut self, map: &Map, dt: f32) {
+        let ids: Vec<SaltyId> = self.objects.iter()
+            .map(|(id, _)| id)
+            .filter(|id| self.vehicle_tag(map, *id).is_some())
+            .collect();
+        for id in ids {
+            self.update_vehicle(map, id, dt);
+        }
+    }
+
+    /// The vehicle tag named by the object's tag, if it's a vehicle.
+    pub(crate) fn vehicle_tag<'m>(&self, map: &'m Map, object: SaltyId) -> Option<&'m Vehicle> {
+        let object_state = self.objects.get(object)?;
+        map.get_object(&object_state.tag)
+            .and_then(|object_tag| object_tag.vehicle)
+            .and_then(|vehicle| map.get_vehicle(&vehicle))
+    }
+
+    fn update_vehicle(&mut self, map: &Map, id: SaltyId, dt: f32) {
+        let tag = match self.vehicle_tag(map, id) {
+            Some(tag) => tag,
+            None => return,
+        };
+        let object_state = *self.objects.get(id).unwrap();
+        //objects with vehicles are checked for physics when the map loads
+        let mut physics_state = *self.physics.get(object_state.physics_id).unwrap();
+        let body = match self.body(map, &object_state) {
+            Some(body) if body.inverse_mass > 0.0 => body,
+            _ => return,
+        };
+        let controls = if id == self.player_control.target_object {
+            Some(self.player_control)
+        } else {
+            None
+        };
+        let (throttle, steer) = controls.map_or((0.0, 0.0), |controls| {
+            (axis(controls.forward, controls.back), axis(controls.left, controls.right))
+        });
+        //parked vehicles stay asleep until driven off or knocked
+        if physics_state.asleep {
+            if throttle == 0.0 && steer == 0.0 {
+                return;
+            }
+            physics_state.wake();
+        }
+
+        let rotation = object_state.transform.rotation;
+        let up = rotation.rotate_vector(Vector3::unit_z());
+        let velocity = physics_state.velocity;
+        let angular_velocity = physics_state.angular_velocity;
+        let mut velocity_change = Vector3::zero();
+        let mut angular_change = Vector3::zero();
+
+        //springs push up at each point touching the ground, so the vehicle pitches over bumps
+        //and leans into corners
+        let filter = QueryFilter::default().excluding(id).with_mask(physics_state.mask);
+        let length = tag.suspension_length();
+        let share = 1.0 / tag.suspension_points.len().max(1) as f32;
+        let mut grounded = 0;
+        let mut ground_normal = Vector3::zero();
+        for point in &tag.suspension_points {
+            let offset = rotation.rotate_vector(Vector3::from(*point));
+            let hit = match self.raycast(map, object_state.transform.position + offset, -up, length, &filter) {
+                Some(hit) => hit,
+                None => continue,
+            };
+            grounded += 1;
+            ground_normal += hit.normal;
+            let compression = length - hit.distance;
+            let closing = -(velocity + angular_velocity.cross(offset)).dot(up);
+            //springs only push, however fast the ground falls away
+            let push = ((tag.spring() * compression + tag.damper() * closing) * share).max(0.0) * dt;
+            velocity_change += up * push;
+            angular_change += body.turn_by(offset.cross(up * push / body.inverse_mass));
+        }
+        //rays starting inside the ground can't tell which way it faces
+        let normal = if ground_normal.dot(up) > 0.0 {
+            ground_normal.normalize()
+        } else {
+            up
+        };
+        //along the ground the way the vehicle faces, unless it's nose down
+        let forward = rotation.rotate_vector(Vector3::unit_x());
+        let forward = forward - normal * forward.dot(normal);
+        if grounded > 0 && forward.magnitude2() > 0.0 {
+            let traction = grounded as f32 * share;
+            let forward = forward.normalize();
+            let side = normal.cross(forward);
+            let speed = velocity.dot(forward);
+
+            let drive = if throttle * speed < 0.0 {
+                //braking, which stops short of reversing
+                (-speed).clamp(-tag.brake() * dt, tag.brake() * dt)
+            } else if throttle > 0.0 {
+                tag.engine(speed) * dt
+            } else if throttle < 0.0 {
+                -tag.engine(-speed) * REVERSE_SCALE * dt
+            } else {
+                0.0
+            };
+            velocity_change += forward * drive * traction;
+
+            //tyres stop sideways sliding, up to a point
+            let grip = tag.grip() * traction * dt;
+            velocity_change -= side * velocity.dot(side).clamp(-grip, grip);
+
+            //steering turns the other way in reverse, and no tighter than the tyres can hold
+            let mut turn_rate = tag.turn_rate();
+            if speed.abs() > 0.0 {
+                turn_rate = turn_rate.min(tag.grip() / speed.abs());
+            }
+            let turn = steer * turn_rate * (speed / FULL_STEER_SPEED).clamp(-1.0, 1.0);
+            let turning = angular_velocity.dot(normal);
+            angular_change += normal * (turn - turning) * (STEER_RESPONSE * dt).min(1.0) * traction;
+        }
+
+        physics_state.velocity += velocity_change;
+        physics_state.angular_velocity += angular_change;
+        *self.physics.get_mut(object_state.physics_id).unwrap() = physics_state;
+    }
+}
+
+/// -1, 0 or 1 from a pair of opposing controls.
+fn axis(positive: bool, negative: bool) -> f32 {
+    match (positive, negative) {
+        (true, false) => 1.0,
+        (false, true) => -1.0,
+        _ => 0.0,
+    }
+}
+
+mod tests {
+    use super::*;
+    use super::super::test_map::test_map;
+    use crate::game::state::physics_state::EARTH_GRAVITY;
+
+    const VEHICLE_MAP: &str = r#"
+        [physics.floor]
+        mass = 0.0
+        shape = {type = "box", half_extents = [200.0, 200.0, 1.0]}
+
+        [physics.kart]
+        mass = 200.0
+        shape = {type = "box", half_extents = [1.5, 0.8, 0.3]}
+        linear_damping = 0.1
+
+        [vehicle.kart]
+        suspension_points = [[1.2, 0.7, -0.3], [1.2, -0.7, -0.3], [-1.2, 0.7, -0.3], [-1.2, -0.7, -0.3]]
+
+        [object.floor]
+        physics = "floor"
+        model = "maps/cube.gltf"
+        colour = [0.5, 0.5, 0.5]
+
+        [object.kart]
+        physics = "kart"
+        vehicle = "kart"
+        model = "maps/cube.gltf"
+        colour = [1.0, 0.0, 0.0]
+
+        [globals]
+        player_object = "kart"
+
+        [scenario]
+        player_location = {pos = [0.0, 0.0, 1.0]}
+
+        [[scenario.scenery]]
+        object_type = "floor"
+        position = {pos = [0.0, 0.0, -1.0]}
+    "#;
+
+    #[test]
+    fn test_vehicle() {
+        let map = test_map(VEHICLE_MAP);
+        let mut state = GameState::init(&map);
+        let kart = state.player_control.target_object;
+        let transform = |state: &GameState| state.objects.get(kart).unwrap().transform;
+        let velocity = |state: &GameState| state.object_physics(kart).unwrap().velocity;
+
+        //settling on its springs, compressed until they hold up its weight
+        state.advance_to_tick(&map, 180);
+        let tag = state.vehicle_tag(&map, kart).unwrap();
+        let height = 0.3 + tag.suspension_length() - EARTH_GRAVITY / tag.spring();
+        assert!((transform(&state).position.z - height).abs() < 0.05, "resting at {:?}", transform(&state).position);
+        assert!(velocity(&state).magnitude() < 0.1);
+
+        //driving off in a straight line
+        state.player_control.forward = true;
+        state.advance_to_tick(&map, 360);
+        let driving = velocity(&state);
+        assert!(driving.x > 10.0, "driving at {:?}", driving);
+        assert!(driving.y.abs() < 0.01 && transform(&state).position.y.abs() < 0.01);
+
+        //turning left, with the tyres carrying it round rather than sliding on
+        state.player_control.left = true;
+        state.advance_to_tick(&map, 420);
+        let facing = transform(&state).rotation.rotate_vector(Vector3::unit_x());
+        assert!(facing.y > 0.5, "facing {:?}", facing);
+        let turning = velocity(&state);
+        assert!(turning.normalize().dot(facing) > 0.9, "sliding at {:?} facing {:?}", turning, facing);
+
+        //and braking to a stop without going backwards
+        state.player_control.left = false;
+        state.player_control.forward = false;
+        state.player_control.back = true;
+        let speed = velocity(&state).magnitude();
+        state.advance_to_tick(&map, 440);
+        assert!(velocity(&state).magnitude() < speed - 3.0);
+        state.advance_to_tick(&map, 540);
+        let forward = transform(&state).rotation.rotate_vector(Vector3::unit_x());
+        assert!(velocity(&state).dot(forward) < 0.0, "still going forward at {:?}", velocity(&state));
+    }
+}
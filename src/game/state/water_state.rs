@@ -0,0 +1,218 @@
+use cgmath::{prelude::*, Vector3};
+use super::prelude::*;
+use super::physics_state::EARTH_GRAVITY;
+use crate::game::tags::WaterPlacement;
+
+// Objects count as submerged once this much of them is underwater
+const SUBMERGED_FRACTION: f32 = 0.5;
+
+state_nodef! {
+    pub struct WaterState {
+        pub min: Vector3<f32>,
+        // The top is the water's surface
+        pub max: Vector3<f32>,
+        pub density: f32,
+        pub current: Vector3<f32>,
+        pub drag: f32,
+        pub fog_colour: [f32; 4],
+        pub fog_max_distance: f32,
+    }
+}
+
+impl Default for WaterState {
+    fn default() -> Self {
+        WaterState {
+            min: Vector3::zero(),
+            max: Vector3::zero(),
+            density: 0.0,
+            current: Vector3::zero(),
+            drag: 0.0,
+            fog_colour: [0.0; 4],
+            fog_max_distance: 0.0,
+        }
+    }
+}
+
+impl WaterState {
+    pub fn init(game_state: &mut GameState, placement: &WaterPlacement) -> SaltyId {
+        let position = placement.position.to_pos();
+        let half_extents = Vector3::from(placement.half_extents);
+        game_state.waters.add(WaterState {
+            min: position - half_extents,
+            max: position + half_extents,
+            density: placement.density(),
+            current: placement.current(),
+            drag: placement.drag(),
+            fog_colour: placement.fog_colour(),
+            fog_max_distance: placement.fog_max_distance(),
+        }).unwrap_or(NONE)
+    }
+
+    /// How much of something with the given bounds is underwater, from 0 to 1. Only the depth is
+    /// considered, so shapes narrower at the bottom than the top float a little low.
+    pub fn immersion(&self, min: Vector3<f32>, max: Vector3<f32>) -> f32 {
+        let center = (min + max) / 2.0;
+        let inside = center.x >= self.min.x && center.x <= self.max.x &&
+            center.y >= self.min.y && center.y <= self.max.y;
+        let height = max.z - min.z;
+        if !inside || height <= 0.0 {
+            return 0.0;
+        }
+        let wet = max.z.min(self.max.z) - min.z.max(self.min.z);
+        (wet / height).clamp(0.0, 1.0)
+    }
+}
+
+impl GameState {
+    /// Finds which water each physics object is in, then floats and drags along the bodies which
+    /// aren't characters or movers. Characters swim by themselves.
+    pub(crate) fn update_water(&mut self, map: &Map, dt: f32) {
+        let gravity = EARTH_GRAVITY * self.gravity;
+        for (_id, object_state) in self.objects.iter() {
+            let physics_state = match self.physics.get_mut(object_state.physics_id) {
+                Some(physics_state) => physics_state,
+                None => continue,
+            };
+            let collider = physics_state.world_collider(&object_state.transform);
+            let (min, max) = match collider.as_ref().and_then(|collider| collider.as_convex()) {
+                Some(convex) => convex.bounds(),
+                None => continue,
+            };
+            let deepest = self.waters.iter()
+                .map(|(water_id, water)| (water_id, water, water.immersion(min, max)))
+                .filter(|(_, _, immersion)| *immersion > 0.0)
+                .max_by(|(_, _, a), (_, _, b)| a.total_cmp(b));
+            let (water_id, water, immersion) = match deepest {
+                Some(deepest) => deepest,
+                None => {
+                    physics_state.water = NONE;
+                    physics_state.submerged = false;
+                    continue;
+                },
+            };
+            physics_state.water = water_id;
+            physics_state.submerged = immersion >= SUBMERGED_FRACTION;
+
+            let inverse_mass = map.get_physics(&physics_state.tag).map_or(0.0, |physics_tag| physics_tag.inverse_mass());
+            if physics_state.is_kinematic() || physics_state.asleep || inverse_mass == 0.0 {
+                continue;
+            }
+            //the weight of the water pushed aside, pushing back up
+            let displaced = water.density * collider.unwrap().volume() * immersion;
+            physics_state.velocity.z += displaced * gravity * inverse_mass * dt;
+            let drag = (water.drag * immersion * dt).min(1.0);
+            physics_state.velocity += (water.current - physics_state.velocity) * drag;
+            physics_state.angular_velocity *= 1.0 - drag;
+        }
+    }
+}
+
+mod tests {
+    use super::*;
+    use super::super::test_map::test_map;
+
+    const WATER_MAP: &str = r#"
+        [physics.floor]
+        mass = 0.0
+        shape = {type = "box", half_extents = [50.0, 50.0, 1.0]}
+
+        [physics.light_crate]
+        mass = 4000.0
+        shape = {type = "box", half_extents = [1.0, 1.0, 1.0]}
+
+        [physics.heavy_crate]
+        mass = 16000.0
+        shape = {type = "box", half_extents = [1.0, 1.0, 1.0]}
+
+        [physics.biped]
+        mass = 80.0
+        shape = {type = "capsule", radius = 0.5, half_height = 0.5}
+        character = {}
+
+        [object.player]
+        physics = "biped"
+
+        [object.floor]
+        physics = "floor"
+        model = "maps/cube.gltf"
+        colour = [0.5, 0.5, 0.5]
+
+        [object.light_crate]
+        physics = "light_crate"
+        model = "maps/cube.gltf"
+        colour = [0.0, 0.0, 1.0]
+
+        [object.heavy_crate]
+        physics = "heavy_crate"
+        model = "maps/cube.gltf"
+        colour = [0.0, 0.0, 1.0]
+
+        [scenario]
+        player_location = {pos = [-5.0, -5.0, -5.0]}
+
+        [[scenario.scenery]]
+        object_type = "floor"
+        position = {pos = [0.0, 0.0, -11.0]}
+
+        [[scenario.scenery]]
+        object_type = "light_crate"
+        position = {pos = [0.0, 0.0, 2.0]}
+
+        [[scenario.scenery]]
+        object_type = "heavy_crate"
+        position = {pos = [5.0, 0.0, 2.0]}
+
+        [[scenario.scenery]]
+        object_type = "light_crate"
+        position = {pos = [30.0, 0.0, 0.0]}
+
+        [[scenario.water]]
+        position = {pos = [0.0, 0.0, -5.0]}
+        half_extents = [10.0, 10.0, 5.0]
+
+        [[scenario.water]]
+        position = {pos = [30.0, 0.0, -5.0]}
+        half_extents = [10.0, 10.0, 5.0]
+        current = [1.0, 0.0, 0.0]
+    "#;
+
+    fn object_at(state: &GameState, tag: &str, x: f32) -> SaltyId {
+        state.objects.iter()
+            .find(|(_, object)| String::from(object.tag) == tag && object.transform.position.x == x)
+            .map(|(id, _)| id)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_buoyancy() {
+        let map = test_map(WATER_MAP);
+        let mut state = GameState::init(&map);
+        let floating = object_at(&state, "light_crate", 0.0);
+        let sinking = object_at(&state, "heavy_crate", 5.0);
+        let drifting = object_at(&state, "light_crate", 30.0);
+        let player = state.player_control.target_object;
+        let physics = |state: &GameState, id: SaltyId| *state.physics.get(state.objects.get(id).unwrap().physics_id).unwrap();
+        let position = |state: &GameState, id: SaltyId| state.objects.get(id).unwrap().transform.position;
+
+        //the player sinks slowly rather than falling, and swims up when jumping
+        state.advance_to_tick(&map, 60);
+        assert!(physics(&state, player).submerged);
+        assert!((physics(&state, player).velocity.z + 1.0).abs() < 0.01);
+        state.player_control.up = true;
+        state.advance_to_tick(&map, 90);
+        assert!(physics(&state, player).velocity.z > 0.0);
+
+        //half as dense as the water floats half out of it, twice as dense sinks to the bottom
+        state.advance_to_tick(&map, 600);
+        assert!(position(&state, floating).z.abs() < 0.1, "floating at {:?}", position(&state, floating));
+        assert!(physics(&state, floating).water.is_some());
+        assert!((position(&state, sinking).z + 9.0).abs() < 0.1, "sank to {:?}", position(&state, sinking));
+        assert!(physics(&state, sinking).submerged);
+        assert_eq!(physics(&state, sinking).water, physics(&state, floating).water);
+
+        //carried along by the current
+        assert!(position(&state, drifting).x > 32.0);
+        assert!(physics(&state, drifting).velocity.x > 0.5);
+        assert_ne!(physics(&state, drifting).water, physics(&state, floating).water);
+    }
+}
@@ -5,12 +5,34 @@ tag! {
         pub gravity_scale: f32,
         pub player_object: TagId,
         pub player_accel: f32,
-        pub player_drag_scale: f32,
         pub v_fov: Option<f32>,
+        // Names of collision layers besides "default", which is always the first
+        pub collision_layers: Option<Vec<TagString>>,
     }
 }
 
 impl Globals {
+    /// Bit flags of the named collision layers, numbered in the order `collision_layers` lists
+    /// them after "default".
+    pub fn layer_bits(&self, names: &[TagString]) -> Result<u32, String> {
+        let layers = self.collision_layers.as_deref().unwrap_or(&[]);
+        if layers.len() >= 32 {
+            return Err(format!("Too many collision layers: {} of at most 31", layers.len()));
+        }
+        let mut bits = 0;
+        for name in names {
+            let name = String::from(*name);
+            if name == "default" {
+                bits |= 1;
+            } else if let Some(i) = layers.iter().position(|layer| String::from(*layer) == name) {
+                bits |= 2 << i;
+            } else {
+                return Err(format!("Unknown collision layer: {}", name));
+            }
+        }
+        Ok(bits)
+    }
+
     pub fn v_fov_as_radians(&self) -> f32 {
         self.v_fov.unwrap_or(70.0).to_radians()
     }
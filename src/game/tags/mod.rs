@@ -4,24 +4,29 @@ use std::fs::File;
 use std::io::prelude::*;
 use toml;
 use serde::{Deserializer, Deserialize};
+use crate::game::physics::TriangleMesh;
 
 mod scenario;
 mod globals;
 mod object;
 mod physics;
+mod mover;
+mod vehicle;
 
 pub use scenario::*;
 pub use globals::*;
 pub use object::*;
 pub use physics::*;
+pub use mover::*;
+pub use vehicle::*;
 
 #[derive(Eq, PartialEq, Hash, Copy, Clone, Default)]
 pub struct TagString([u8; 32]);
 pub type TagId = TagString;
 
-impl Into<String> for TagString {
-    fn into(self) -> String {
-        String::from_utf8((self.0).iter().filter(|c| **c != 0u8).map(|c| *c).collect()).expect("msg: &str")
+impl From<TagString> for String {
+    fn from(tag: TagString) -> String {
+        String::from_utf8((tag.0).iter().filter(|c| **c != 0u8).copied().collect()).expect("msg: &str")
     }
 }
 
@@ -48,6 +53,13 @@ pub struct Map {
     pub scenario: scenario::Scenario,
     pub object: HashMap<TagId, Object>,
     pub physics: HashMap<TagId, Physics>,
+    #[serde(default)]
+    pub mover: HashMap<TagId, Mover>,
+    #[serde(default)]
+    pub vehicle: HashMap<TagId, Vehicle>,
+    // Built from the models of mesh-shaped physics tags when loading, keyed by model path
+    #[serde(skip)]
+    pub collision_meshes: HashMap<TagString, TriangleMesh>,
 }
 
 macro_rules! get_tag {
@@ -63,11 +75,54 @@ impl Map {
         let mut file = File::open(path).expect("Failed to open map file");
         let mut contents = String::new();
         file.read_to_string(&mut contents).expect("Failed to read map file");
-        toml::from_slice(contents.as_bytes()).expect("Failed to parse map file")
+        let mut map: Map = toml::from_slice(contents.as_bytes()).expect("Failed to parse map file");
+        for physics in map.physics.values() {
+            physics.collision_filter(&map.globals).expect("Failed to read collision layers");
+        }
+        for trigger in map.scenario.triggers.iter().flatten() {
+            if let Some(mask) = &trigger.mask {
+                map.globals.layer_bits(mask).expect("Failed to read trigger collision layers");
+            }
+        }
+        map.check_objects().expect("Failed to find object tags");
+        map.scenario.check_joints().expect("Failed to find jointed objects");
+        map.scenario.check_triggers().expect("Failed to place triggers");
+        map.load_collision_meshes();
+        map
+    }
+
+    /// Checks that the movers and vehicles objects name exist, and that those objects have the
+    /// physics they move with.
+    pub fn check_objects(&self) -> Result<(), String> {
+        for (id, object) in self.object.iter() {
+            if let Some(mover) = object.mover.filter(|mover| !self.mover.contains_key(mover)) {
+                return Err(format!("No mover is named {}", String::from(mover)));
+            }
+            if let Some(vehicle) = object.vehicle.filter(|vehicle| !self.vehicle.contains_key(vehicle)) {
+                return Err(format!("No vehicle is named {}", String::from(vehicle)));
+            }
+            if (object.mover.is_some() || object.vehicle.is_some()) && object.physics.is_none() {
+                return Err(format!("Object {} has a mover or vehicle but no physics", String::from(*id)));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn load_collision_meshes(&mut self) {
+        for physics in self.physics.values() {
+            if let Some(Shape::Mesh { model }) = physics.shape {
+                self.collision_meshes.entry(model).or_insert_with(|| {
+                    TriangleMesh::from_gltf(&String::from(model)).expect("Failed to load collision mesh")
+                });
+            }
+        }
     }
 
     get_tag!(get_object, object, Object);
     get_tag!(get_physics, physics, Physics);
+    get_tag!(get_mover, mover, Mover);
+    get_tag!(get_vehicle, vehicle, Vehicle);
+    get_tag!(get_collision_mesh, collision_meshes, TriangleMesh);
 }
 
 mod prelude {
@@ -0,0 +1,55 @@
+use super::prelude::*;
+
+/// How movers speed up and slow down along each leg of their path.
+#[derive(Copy, Clone, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    Linear,
+    // Eases in and out of every waypoint
+    Smooth,
+}
+
+/// What movers do on reaching the end of their path.
+#[derive(Copy, Clone, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MoverMode {
+    // Stops there
+    Once,
+    // Carries on back to the start
+    Loop,
+    // Goes back along the path the way it came
+    PingPong,
+}
+
+tag! {
+    /// Drives an object along a path, for moving platforms, lifts and sliding doors. Movers are
+    /// kinematic: they push bodies aside and carry whatever rides on them, but nothing pushes back.
+    pub struct Mover {
+        // Offsets from where the object is placed, which is where the path starts
+        pub waypoints: Vec<[f32; 3]>,
+        // Metres per second, on average when easing
+        pub speed: Option<f32>,
+        pub easing: Option<Easing>,
+        pub mode: Option<MoverMode>,
+        // Seconds paused at each waypoint, including the start
+        pub wait: Option<f32>,
+    }
+}
+
+impl Mover {
+    pub fn speed(&self) -> f32 {
+        self.speed.unwrap_or(2.0).max(0.0)
+    }
+
+    pub fn easing(&self) -> Easing {
+        self.easing.unwrap_or(Easing::Linear)
+    }
+
+    pub fn mode(&self) -> MoverMode {
+        self.mode.unwrap_or(MoverMode::PingPong)
+    }
+
+    pub fn wait(&self) -> f32 {
+        self.wait.unwrap_or(0.0).max(0.0)
+    }
+}
@@ -3,7 +3,19 @@ use super::prelude::*;
 tag! {
     pub struct Object {
         pub physics: Option<TagId>,
+        // Path followed by the object, which needs physics to push and carry things
+        pub mover: Option<TagId>,
+        // Suspension and engine for driving the object, which needs physics too
+        pub vehicle: Option<TagId>,
         pub model: TagString,
         pub colour: [f32; 3],
+        // Whether the player can take control of it with the use action, like vehicles and turrets
+        pub possessable: Option<bool>,
+    }
+}
+
+impl Object {
+    pub fn possessable(&self) -> bool {
+        self.possessable.unwrap_or(false)
     }
 }
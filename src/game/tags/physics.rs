@@ -1,7 +1,174 @@
+use cgmath::{prelude::*, Quaternion, Vector3};
 use super::prelude::*;
+use super::Globals;
+use crate::game::physics::{Capsule, Collider, CollisionFilter, OrientedBox, Sphere};
+
+/// Collision shape of a physics object, centered on the object's origin.
+#[derive(Copy, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Shape {
+    Sphere {
+        radius: f32,
+    },
+    Box {
+        half_extents: [f32; 3],
+    },
+    // Upright along the object's Z axis
+    Capsule {
+        radius: f32,
+        half_height: f32,
+    },
+    // Static triangle mesh collision from a glTF model, always treated as immovable
+    Mesh {
+        model: TagString,
+    },
+}
+
+impl Shape {
+    /// Builds a collider at the origin, to be moved into place with `Collider::placed`.
+    pub fn to_collider(self) -> Option<Collider> {
+        match self {
+            Shape::Sphere { radius } => Some(Collider::Sphere(Sphere {
+                radius,
+                position: Vector3::zero(),
+                velocity: Vector3::zero(),
+            })),
+            Shape::Box { half_extents } => Some(Collider::OrientedBox(OrientedBox {
+                half_extents: half_extents.into(),
+                position: Vector3::zero(),
+                rotation: Quaternion::one(),
+                velocity: Vector3::zero(),
+            })),
+            Shape::Capsule { radius, half_height } => Some(Collider::Capsule(Capsule {
+                radius,
+                half_height,
+                position: Vector3::zero(),
+                rotation: Quaternion::one(),
+                velocity: Vector3::zero(),
+            })),
+            //meshes aren't convex, see `Map::get_collision_mesh`
+            Shape::Mesh { .. } => None,
+        }
+    }
+}
+
+tag! {
+    /// Walking movement for objects the player or AI steer, as opposed to free flight.
+    pub struct Character {
+        pub walk_speed: Option<f32>,
+        // Upwards speed on jumping
+        pub jump_speed: Option<f32>,
+        // Tallest ledge walked up without jumping
+        pub step_height: Option<f32>,
+        // Steepest walkable slope in degrees
+        pub max_slope: Option<f32>,
+        // Fraction of ground acceleration available in the air
+        pub air_control: Option<f32>,
+        // Fraction of standing height and speed when crouched
+        pub crouch_scale: Option<f32>,
+    }
+}
+
+impl Character {
+    pub fn walk_speed(&self) -> f32 {
+        self.walk_speed.unwrap_or(6.0)
+    }
+
+    pub fn jump_speed(&self) -> f32 {
+        self.jump_speed.unwrap_or(5.0)
+    }
+
+    pub fn step_height(&self) -> f32 {
+        self.step_height.unwrap_or(0.4)
+    }
+
+    /// Cosine of the steepest walkable slope, for comparing against a ground normal's Z. Kept
+    /// short of vertical so walls are never ground.
+    pub fn min_ground_normal_z(&self) -> f32 {
+        self.max_slope.unwrap_or(45.0).clamp(0.0, 89.0).to_radians().cos()
+    }
+
+    pub fn air_control(&self) -> f32 {
+        self.air_control.unwrap_or(0.2).clamp(0.0, 1.0)
+    }
+
+    pub fn crouch_scale(&self) -> f32 {
+        self.crouch_scale.unwrap_or(0.5).clamp(0.1, 1.0)
+    }
+}
 
 tag! {
     pub struct Physics {
+        // Zero or less for static objects which never move
         pub mass: f32,
+        pub shape: Option<Shape>,
+        // Fraction of approach speed kept when bouncing, from 0 to 1
+        pub restitution: Option<f32>,
+        pub friction: Option<f32>,
+        // Moves with the character controller if present, otherwise flies freely
+        pub character: Option<Character>,
+        // Names of the collision layers this is on, just "default" if not given
+        pub layers: Option<Vec<TagString>>,
+        // Names of the layers this collides with, every layer if not given
+        pub mask: Option<Vec<TagString>>,
+        // Swept from where it was each tick so it can't pass through thin objects, for projectiles
+        pub fast: Option<bool>,
+        // How quickly speed dies away, like air resistance: at 1 it falls to about a third each second
+        pub linear_damping: Option<f32>,
+        // How quickly spin dies away, so rolling things come to a stop
+        pub angular_damping: Option<f32>,
+        // Terminal velocity in metres per second, unlimited if not given
+        pub max_speed: Option<f32>,
+        // Multiplies the map's gravity, negative for things which float up
+        pub gravity_scale: Option<f32>,
+    }
+}
+
+impl Physics {
+    pub fn inverse_mass(&self) -> f32 {
+        if self.mass > 0.0 {
+            1.0 / self.mass
+        } else {
+            0.0
+        }
+    }
+
+    pub fn restitution(&self) -> f32 {
+        self.restitution.unwrap_or(0.2).clamp(0.0, 1.0)
+    }
+
+    pub fn friction(&self) -> f32 {
+        self.friction.unwrap_or(0.5).max(0.0)
+    }
+
+    pub fn fast(&self) -> bool {
+        self.fast.unwrap_or(false)
+    }
+
+    pub fn linear_damping(&self) -> f32 {
+        self.linear_damping.unwrap_or(0.0).max(0.0)
+    }
+
+    pub fn angular_damping(&self) -> f32 {
+        self.angular_damping.unwrap_or(0.0).max(0.0)
+    }
+
+    pub fn max_speed(&self) -> f32 {
+        self.max_speed.unwrap_or(f32::INFINITY).max(0.0)
+    }
+
+    pub fn gravity_scale(&self) -> f32 {
+        self.gravity_scale.unwrap_or(1.0)
+    }
+
+    pub fn collision_filter(&self, globals: &Globals) -> Result<CollisionFilter, String> {
+        let mut filter = CollisionFilter::default();
+        if let Some(layers) = &self.layers {
+            filter.layer = globals.layer_bits(layers)?;
+        }
+        if let Some(mask) = &self.mask {
+            filter.mask = globals.layer_bits(mask)?;
+        }
+        Ok(filter)
     }
 }
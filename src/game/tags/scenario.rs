@@ -1,4 +1,5 @@
 use super::prelude::*;
+use super::Shape;
 use cgmath::{prelude::*, Vector3, Quaternion, Rad};
 use crate::game::state::transform::Transform;
 
@@ -22,7 +23,7 @@ impl Placement {
             let yaw_q: Quaternion<f32> = Quaternion::from_angle_z(Rad(-yaw.to_radians()));
             return yaw_q * pitch_q * roll_q;
         }
-        Quaternion::zero()
+        Quaternion::one()
     }
 
     pub fn to_transform(&self) -> Transform {
@@ -35,11 +36,99 @@ impl Placement {
 
 tag! {
     pub struct SceneryPlacement {
+        // For referring to this object elsewhere in the scenario, such as from joints
+        pub name: Option<TagString>,
         pub position: Placement,
         pub object_type: TagId,
     }
 }
 
+/// How a joint connects its objects. Anchors and axes are given in world space as placed.
+#[derive(Copy, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JointType {
+    BallSocket,
+    Hinge {
+        axis: [f32; 3],
+    },
+    // Keeps the anchors no further apart than they were placed, like a rope
+    Distance,
+    Fixed,
+}
+
+tag! {
+    /// Joins a named scenery object to another, or to the world.
+    pub struct JointPlacement {
+        pub joint: JointType,
+        pub object: TagString,
+        // Joined to the world if not given
+        pub other: Option<TagString>,
+        pub anchor: [f32; 3],
+        // Where the joint attaches to the other object, or the world; defaults to `anchor`
+        pub other_anchor: Option<[f32; 3]>,
+    }
+}
+
+tag! {
+    /// A volume which reports objects entering and leaving it, and knows which are inside.
+    pub struct TriggerPlacement {
+        pub name: TagString,
+        pub position: Placement,
+        // Sphere, box or capsule; meshes can't be triggers
+        pub shape: Shape,
+        // Names of the collision layers detected, every layer if not given
+        pub mask: Option<Vec<TagString>>,
+    }
+}
+
+tag! {
+    /// A box of water which floats, slows and carries along the objects in it. Water surfaces are
+    /// flat, so any rotation in the placement is ignored.
+    pub struct WaterPlacement {
+        pub position: Placement,
+        pub half_extents: [f32; 3],
+        // In kg/m³, so fresh water is 1000
+        pub density: Option<f32>,
+        // Velocity of the flowing water, which drag pulls objects towards
+        pub current: Option<[f32; 3]>,
+        // Fraction of the difference from the current's velocity lost per second when fully submerged
+        pub drag: Option<f32>,
+        // Replaces the scenario's fog while the camera is underwater
+        pub fog_colour: Option<[f32; 4]>,
+        pub fog_max_distance: Option<f32>,
+    }
+}
+
+impl WaterPlacement {
+    pub fn density(&self) -> f32 {
+        self.density.unwrap_or(1000.0).max(0.0)
+    }
+
+    pub fn current(&self) -> Vector3<f32> {
+        self.current.map(Vector3::from).unwrap_or_else(Vector3::zero)
+    }
+
+    pub fn drag(&self) -> f32 {
+        self.drag.unwrap_or(2.0).max(0.0)
+    }
+
+    pub fn fog_colour(&self) -> [f32; 4] {
+        self.fog_colour.unwrap_or([0.05, 0.2, 0.3, 1.0])
+    }
+
+    pub fn fog_max_distance(&self) -> f32 {
+        self.fog_max_distance.unwrap_or(10.0)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Tonemap {
+    None,
+    Reinhard,
+    Aces,
+}
+
 tag! {
     pub struct Scenario {
         pub sun_direction: Option<[f32; 3]>,
@@ -47,7 +136,44 @@ tag! {
         pub fog_colour: Option<[f32; 4]>,
         pub fog_min_distance: Option<f32>,
         pub fog_max_distance: Option<f32>,
+        pub tonemap: Option<Tonemap>,
+        // In stops; acts as exposure compensation when auto exposure is enabled
+        pub exposure: Option<f32>,
+        pub auto_exposure: Option<bool>,
+        // How quickly auto exposure adapts to the scene, per second
+        pub auto_exposure_speed: Option<f32>,
+        pub bloom_intensity: Option<f32>,
+        // Brightness in HDR units above which pixels start to bloom
+        pub bloom_threshold: Option<f32>,
         pub player_location: Placement,
         pub scenery: Option<Vec<SceneryPlacement>>,
+        pub triggers: Option<Vec<TriggerPlacement>>,
+        pub joints: Option<Vec<JointPlacement>>,
+        pub water: Option<Vec<WaterPlacement>>,
+    }
+}
+
+impl Scenario {
+    /// Checks that every jointed object is named by some scenery.
+    pub fn check_joints(&self) -> Result<(), String> {
+        for joint in self.joints.iter().flatten() {
+            for name in std::iter::once(joint.object).chain(joint.other) {
+                let named = self.scenery.iter().flatten().any(|scenery| scenery.name == Some(name));
+                if !named {
+                    return Err(format!("No scenery is named {}", String::from(name)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that every trigger has a shape it can be placed with, which rules out meshes.
+    pub fn check_triggers(&self) -> Result<(), String> {
+        for trigger in self.triggers.iter().flatten() {
+            if trigger.shape.to_collider().is_none() {
+                return Err(format!("Trigger {} isn't a sphere, box or capsule", String::from(trigger.name)));
+            }
+        }
+        Ok(())
     }
 }
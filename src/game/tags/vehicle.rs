@@ -0,0 +1,72 @@
+use super::prelude::*;
+
+tag! {
+    /// Raycast suspension, tyres and an engine for driveable objects. Vehicles are otherwise
+    /// ordinary bodies which the solver pushes around, held off the ground by a spring under each
+    /// suspension point. Hovercraft are the same with long soft springs and little grip.
+    pub struct Vehicle {
+        // Where each suspension ray starts, relative to the object, reaching down its Z axis
+        pub suspension_points: Vec<[f32; 3]>,
+        // Furthest the suspension reaches below each point
+        pub suspension_length: Option<f32>,
+        // Upwards acceleration per metre the suspension is compressed, shared between the points
+        pub spring: Option<f32>,
+        // Upwards acceleration per metre per second the suspension is compressing, shared likewise
+        pub damper: Option<f32>,
+        // Most sideways acceleration the ground gives before the vehicle slides
+        pub grip: Option<f32>,
+        // Forward acceleration at each forward speed, as [speed, acceleration] in order of speed
+        pub engine: Option<Vec<[f32; 2]>>,
+        // Deceleration while braking
+        pub brake: Option<f32>,
+        // Fastest turn in degrees per second
+        pub turn_rate: Option<f32>,
+    }
+}
+
+impl Vehicle {
+    pub fn suspension_length(&self) -> f32 {
+        self.suspension_length.unwrap_or(0.6).max(0.0)
+    }
+
+    pub fn spring(&self) -> f32 {
+        self.spring.unwrap_or(40.0).max(0.0)
+    }
+
+    pub fn damper(&self) -> f32 {
+        self.damper.unwrap_or(8.0).max(0.0)
+    }
+
+    pub fn grip(&self) -> f32 {
+        self.grip.unwrap_or(15.0).max(0.0)
+    }
+
+    /// Forward acceleration from the engine at a forward speed, following the engine curve
+    /// between its points and levelling off past either end.
+    pub fn engine(&self, speed: f32) -> f32 {
+        let curve: &[[f32; 2]] = match &self.engine {
+            Some(curve) if !curve.is_empty() => curve,
+            _ => &[[0.0, 10.0], [20.0, 6.0], [30.0, 0.0]],
+        };
+        let first = curve[0];
+        if speed <= first[0] {
+            return first[1];
+        }
+        for pair in curve.windows(2) {
+            let ([s0, a0], [s1, a1]) = (pair[0], pair[1]);
+            if speed <= s1 {
+                let t = if s1 > s0 { (speed - s0) / (s1 - s0) } else { 1.0 };
+                return a0 + (a1 - a0) * t;
+            }
+        }
+        curve[curve.len() - 1][1]
+    }
+
+    pub fn brake(&self) -> f32 {
+        self.brake.unwrap_or(15.0).max(0.0)
+    }
+
+    pub fn turn_rate(&self) -> f32 {
+        self.turn_rate.unwrap_or(120.0).max(0.0).to_radians()
+    }
+}
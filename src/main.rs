@@ -10,20 +10,47 @@ mod config;
 
 use game::{Game, actions::PlayerAction};
 use config::Config;
-use render::{Window, run_event_loop, Renderer};
-use pollster;
-use env_logger;
+use render::{Window, run_event_loop, Renderer, HeadlessRenderer};
 
 const WINDOW_TITLE: &str = "redrock";
 const WINDOW_SIZE: [u32; 2] = [900, 600];
+const MAP_PATH: &str = "maps/example.toml";
+const CONFIG_PATH: &str = "config.toml";
+
+fn arg_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter().position(|arg| arg == name).and_then(|i| args.get(i + 1)).map(String::as_str)
+}
+
+// e.g. cargo run -- --render thumbnail.png --tick 120 --size 320x200 --software --debug
+fn render_headless(args: &[String], output_path: &str) {
+    let map_path = arg_value(args, "--map").unwrap_or(MAP_PATH);
+    let tick: u32 = arg_value(args, "--tick").map(|tick| tick.parse().expect("Invalid --tick")).unwrap_or(0);
+    let [width, height] = arg_value(args, "--size").map(|size| {
+        let mut dimensions = size.split('x').map(|d| d.parse::<u32>().expect("Invalid --size, expected WxH"));
+        [dimensions.next().unwrap(), dimensions.next().expect("Invalid --size, expected WxH")]
+    }).unwrap_or(WINDOW_SIZE);
+    let software = args.iter().any(|arg| arg == "--software");
+
+    let mut game = Game::load_map(map_path);
+    game.debug_draw.enabled = args.iter().any(|arg| arg == "--debug");
+    game.advance_to_tick(tick);
+    let mut renderer = pollster::block_on(HeadlessRenderer::new(width, height, software)).expect("Failed to create renderer");
+    renderer.render_to_file(&game, output_path).expect("Failed to render");
+}
 
 fn main() {
     env_logger::init();
-    let mut game = Game::load_map("maps/example.toml");
-    let config = Config::load("config.toml");
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(output_path) = arg_value(&args, "--render") {
+        render_headless(&args, output_path);
+        return;
+    }
+
+    let mut game = Game::load_map(MAP_PATH);
+    let mut config = Config::load(CONFIG_PATH);
 
     let mut window = Window::new(WINDOW_TITLE, WINDOW_SIZE[0], WINDOW_SIZE[1]);
-    let mut renderer = pollster::block_on(Renderer::new(&window));
+    let mut renderer = pollster::block_on(Renderer::new(&window, config.render_scale()));
 
     run_event_loop(window, move |mut inputs, resize| -> bool {
         if let Some((width, height)) = resize {
@@ -35,6 +62,14 @@ fn main() {
             .filter_map(|input| config.map_to_action(input))
             .collect();
 
+        if actions.iter().any(|action| matches!(action, PlayerAction::Screenshot)) {
+            renderer.request_screenshot(config.screenshot_scale());
+        }
+        if actions.iter().any(|action| matches!(action, PlayerAction::ReloadConfig)) {
+            config = Config::load(CONFIG_PATH);
+            renderer.set_render_scale(config.render_scale());
+        }
+
         let keep_running = game.update(&actions);
         renderer.render(&game);
 
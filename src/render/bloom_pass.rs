@@ -0,0 +1,239 @@
+use crate::game::tags::Scenario;
+use super::common::{create_buffer, bytes_slice, FullscreenQuad};
+use super::gpu_types::*;
+use super::model_pass::MODEL_OUTPUT;
+use super::render_graph::{RenderGraph, RenderNode, TargetDesc, Frame};
+
+pub const BLOOM: &str = "bloom";
+const MAX_BLOOM_LEVELS: u32 = 6;
+const BLOOM_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+const DEFAULT_BLOOM_THRESHOLD: f32 = 1.0;
+
+#[derive(Copy, Clone, Default)]
+#[repr(C, align(16))]
+struct BloomUniform {
+    threshold: GpuFloat,
+    knee: GpuFloat,
+}
+
+/// Extracts the HDR energy above a threshold at half resolution, blurs it by walking down a
+/// chain of halving targets and back up again, leaving the result in the first for the post pass.
+pub struct BloomPass {
+    quad: FullscreenQuad,
+    bind_group_layout: wgpu::BindGroupLayout,
+    prefilter_pipeline: wgpu::RenderPipeline,
+    downsample_pipeline: wgpu::RenderPipeline,
+    upsample_pipeline: wgpu::RenderPipeline,
+    bloom_buffer: wgpu::Buffer,
+    input_bind_group: Option<wgpu::BindGroup>,
+    level_bind_groups: Vec<wgpu::BindGroup>,
+}
+
+impl BloomPass {
+    pub fn new(device: &wgpu::Device, graph: &mut RenderGraph) -> BloomPass {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("bloom_shader.wgsl"));
+        let quad = FullscreenQuad::new(device);
+
+        let bloom_buffer = create_buffer(
+            device,
+            wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            &[BloomUniform::default()]
+        );
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bloom bind group layout"),
+            entries: &[
+                //source texture
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None
+                },
+                //source sampler
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None
+                },
+                //bloom settings
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("bloom pipeline layout"),
+            bind_group_layouts: &[
+                &bind_group_layout
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let create_pipeline = |label: &str, entry_point: &str, blend: wgpu::BlendState| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vertex_main",
+                    buffers: &[FullscreenQuad::VERTEX_LAYOUT],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: BLOOM_FORMAT,
+                        blend: Some(blend),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })]
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Cw,
+                    cull_mode: Some(wgpu::Face::Back),
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+            })
+        };
+
+        let prefilter_pipeline = create_pipeline("bloom prefilter pipeline", "prefilter_main", wgpu::BlendState::REPLACE);
+        let downsample_pipeline = create_pipeline("bloom downsample pipeline", "downsample_main", wgpu::BlendState::REPLACE);
+        let upsample_pipeline = create_pipeline("bloom upsample pipeline", "upsample_main", wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::REPLACE,
+        });
+
+        graph.add_target(
+            device,
+            BLOOM,
+            TargetDesc::relative(0.5, BLOOM_FORMAT).with_levels(MAX_BLOOM_LEVELS)
+        );
+
+        BloomPass {
+            quad,
+            bind_group_layout,
+            prefilter_pipeline,
+            downsample_pipeline,
+            upsample_pipeline,
+            bloom_buffer,
+            input_bind_group: None,
+            level_bind_groups: Vec::new(),
+        }
+    }
+
+    pub fn intensity(scenario: &Scenario) -> f32 {
+        scenario.bloom_intensity.unwrap_or(0.0)
+    }
+
+    fn create_bind_group(&self, device: &wgpu::Device, view: &wgpu::TextureView, sampler: &wgpu::Sampler) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bloom bind group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.bloom_buffer.as_entire_binding(),
+                },
+            ]
+        })
+    }
+
+    fn draw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::RenderPipeline,
+        input: &wgpu::BindGroup,
+        output_view: &wgpu::TextureView,
+        clear: bool,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("bloom pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: if clear { wgpu::LoadOp::Clear(wgpu::Color::BLACK) } else { wgpu::LoadOp::Load },
+                    store: true,
+                }
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, input, &[]);
+        self.quad.draw(&mut render_pass);
+    }
+}
+
+impl RenderNode for BloomPass {
+    fn rebind(&mut self, device: &wgpu::Device, graph: &RenderGraph) {
+        let input = graph.target(MODEL_OUTPUT);
+        self.input_bind_group = Some(self.create_bind_group(device, &input.view, &input.sampler));
+
+        self.level_bind_groups = graph.target_levels(BLOOM).iter().map(|level| {
+            self.create_bind_group(device, &level.view, &level.sampler)
+        }).collect();
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let scenario = &frame.game.map.scenario;
+        if Self::intensity(scenario) <= 0.0 {
+            return;
+        }
+        let input_bind_group = match &self.input_bind_group {
+            Some(bind_group) => bind_group,
+            None => return,
+        };
+
+        let threshold = scenario.bloom_threshold.unwrap_or(DEFAULT_BLOOM_THRESHOLD);
+        let bloom_uniform = BloomUniform {
+            threshold: threshold.into(),
+            knee: (threshold * 0.5).into(),
+        };
+        frame.queue.write_buffer(&self.bloom_buffer, 0, bytes_slice(&[bloom_uniform]));
+
+        let levels = frame.graph.target_levels(BLOOM);
+        self.draw(frame.encoder, &self.prefilter_pipeline, input_bind_group, &levels[0].view, true);
+        for (level, prev_level_bind_group) in levels[1..].iter().zip(&self.level_bind_groups) {
+            self.draw(frame.encoder, &self.downsample_pipeline, prev_level_bind_group, &level.view, true);
+        }
+        for (level, next_level_bind_group) in levels.iter().zip(&self.level_bind_groups[1..]).rev() {
+            self.draw(frame.encoder, &self.upsample_pipeline, next_level_bind_group, &level.view, false);
+        }
+    }
+}
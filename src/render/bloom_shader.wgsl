@@ -0,0 +1,83 @@
+struct VertexInput {
+  @location(0) position: vec2<f32>,
+}
+
+struct FragmentInput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) sample_position: vec2<f32>,
+}
+
+struct BloomUniform {
+  threshold: f32,
+  knee: f32,
+}
+
+@group(0) @binding(0)
+var source_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler: sampler;
+@group(0) @binding(2)
+var<uniform> bloom: BloomUniform;
+
+@vertex
+fn vertex_main(vert: VertexInput) -> FragmentInput {
+  var out: FragmentInput;
+  out.clip_position = vec4<f32>(
+    vert.position.x * 2.0 - 1.0,
+    vert.position.y * -2.0 + 1.0,
+    1.0,
+    1.0
+  );
+  out.sample_position = vec2<f32>(
+    vert.position.x,
+    vert.position.y,
+  );
+  return out;
+}
+
+fn source_texel_size() -> vec2<f32> {
+  return 1.0 / vec2<f32>(textureDimensions(source_texture));
+}
+
+//4 bilinear taps at the texel corners average a 4x4 block of the source
+fn sample_box(uv: vec2<f32>) -> vec3<f32> {
+  let d: vec4<f32> = source_texel_size().xyxy * vec4<f32>(-1.0, -1.0, 1.0, 1.0);
+  var sum: vec3<f32> = textureSample(source_texture, source_sampler, uv + d.xy).rgb;
+  sum += textureSample(source_texture, source_sampler, uv + d.zy).rgb;
+  sum += textureSample(source_texture, source_sampler, uv + d.xw).rgb;
+  sum += textureSample(source_texture, source_sampler, uv + d.zw).rgb;
+  return sum * 0.25;
+}
+
+//keeps only the energy above the threshold, with a quadratic knee to avoid a hard cutoff
+@fragment
+fn prefilter_main(in: FragmentInput) -> @location(0) vec4<f32> {
+  let colour: vec3<f32> = sample_box(in.sample_position);
+  let brightness: f32 = max(colour.r, max(colour.g, colour.b));
+  let soft: f32 = clamp(brightness - bloom.threshold + bloom.knee, 0.0, 2.0 * bloom.knee);
+  let soft_curve: f32 = soft * soft / (4.0 * bloom.knee + 0.00001);
+  let contribution: f32 = max(soft_curve, brightness - bloom.threshold) / max(brightness, 0.00001);
+  return vec4<f32>(colour * contribution, 1.0);
+}
+
+@fragment
+fn downsample_main(in: FragmentInput) -> @location(0) vec4<f32> {
+  return vec4<f32>(sample_box(in.sample_position), 1.0);
+}
+
+//3x3 tent filter, blended additively onto the next larger level
+@fragment
+fn upsample_main(in: FragmentInput) -> @location(0) vec4<f32> {
+  let d: vec4<f32> = source_texel_size().xyxy * vec4<f32>(1.0, 1.0, -1.0, 0.0);
+  let uv: vec2<f32> = in.sample_position;
+  var sum: vec3<f32> = textureSample(source_texture, source_sampler, uv - d.xy).rgb;
+  sum += textureSample(source_texture, source_sampler, uv - d.wy).rgb * 2.0;
+  sum += textureSample(source_texture, source_sampler, uv - d.zy).rgb;
+  sum += textureSample(source_texture, source_sampler, uv + d.zw).rgb * 2.0;
+  sum += textureSample(source_texture, source_sampler, uv).rgb * 4.0;
+  sum += textureSample(source_texture, source_sampler, uv + d.xw).rgb * 2.0;
+  sum += textureSample(source_texture, source_sampler, uv + d.zy).rgb;
+  sum += textureSample(source_texture, source_sampler, uv + d.wy).rgb * 2.0;
+  sum += textureSample(source_texture, source_sampler, uv + d.xy).rgb;
+  return vec4<f32>(sum / 16.0, 1.0);
+}
@@ -1,4 +1,3 @@
-use wgpu;
 use wgpu::util::{DeviceExt, BufferInitDescriptor};
 
 pub fn create_buffer<T>(device: &wgpu::Device, usage: wgpu::BufferUsages, contents: &[T]) -> wgpu::Buffer {
@@ -11,6 +10,108 @@ pub fn create_buffer<T>(device: &wgpu::Device, usage: wgpu::BufferUsages, conten
 
 pub fn bytes_slice<T>(data: &[T]) -> &[u8] {
     unsafe {
-        std::slice::from_raw_parts(data.as_ptr() as *const u8, data.len() * std::mem::size_of::<T>())
+        std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data))
     }
-}
\ No newline at end of file
+}
+
+/// A unit quad covering the whole viewport, shared by the fullscreen passes.
+pub struct FullscreenQuad {
+    vertices_buffer: wgpu::Buffer,
+    indices_buffer: wgpu::Buffer,
+}
+
+impl FullscreenQuad {
+    pub const VERTEX_LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &[
+            //position
+            wgpu::VertexAttribute {
+                offset: 0,
+                format: wgpu::VertexFormat::Float32x2,
+                shader_location: 0,
+            },
+        ]
+    };
+
+    pub fn new(device: &wgpu::Device) -> FullscreenQuad {
+        let vertices_buffer = create_buffer(device, wgpu::BufferUsages::VERTEX, &[
+            [0f32, 0f32],
+            [1f32, 0f32],
+            [1f32, 1f32],
+            [0f32, 1f32],
+        ]);
+
+        let indices_buffer = create_buffer(device, wgpu::BufferUsages::INDEX, &[
+            0u16,
+            1u16,
+            2u16,
+            0u16,
+            2u16,
+            3u16,
+        ]);
+
+        FullscreenQuad {
+            vertices_buffer,
+            indices_buffer,
+        }
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_vertex_buffer(0, self.vertices_buffer.slice(..));
+        render_pass.set_index_buffer(self.indices_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(0..6, 0, 0..1);
+    }
+}
+
+/// Layout for a single filterable texture and its sampler, as sampled by the fullscreen passes.
+pub fn create_texture_bind_group_layout(device: &wgpu::Device, label: &str) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some(label),
+        entries: &[
+            //texture
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None
+            },
+            //sampler
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None
+            },
+        ],
+    })
+}
+
+pub fn create_texture_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+    label: &str,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some(label),
+        layout,
+        entries: &[
+            //texture
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(view),
+            },
+            //sampler
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ]
+    })
+}
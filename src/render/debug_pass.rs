@@ -0,0 +1,221 @@
+use cgmath::{prelude::*, Matrix4, Vector3};
+use super::common::{create_buffer, bytes_slice};
+use super::gpu_types::*;
+use super::model_pass::{ModelPass, MODEL_OUTPUT, MODEL_DEPTH};
+use super::render_graph::{RenderGraph, RenderNode, Frame};
+
+// Lines past this many each frame aren't drawn
+const MAX_LINES: usize = 32768;
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+struct LineVertex {
+    position: Vector3<f32>,
+    colour: Vector3<f32>,
+}
+
+impl Default for LineVertex {
+    fn default() -> LineVertex {
+        LineVertex {
+            position: Vector3::zero(),
+            colour: Vector3::zero(),
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+#[repr(C, align(16))]
+struct CameraUniform {
+    view_proj: GpuMat4,
+    world_position: GpuVec3,
+}
+
+/// Draws the game's debug lines over the models, hidden behind whatever is in front of them.
+/// Drawn into the HDR scene before post processing, so bright lines bloom like anything else.
+pub struct DebugPass {
+    camera_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    vertices: Vec<LineVertex>,
+}
+
+impl DebugPass {
+    pub fn new(device: &wgpu::Device) -> DebugPass {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("debug_shader.wgsl"));
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<LineVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                //position
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                    shader_location: 0,
+                },
+                //colour
+                wgpu::VertexAttribute {
+                    offset: 12,
+                    format: wgpu::VertexFormat::Float32x3,
+                    shader_location: 1,
+                },
+            ]
+        };
+
+        let vertex_buffer = create_buffer(
+            device,
+            wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            &vec![LineVertex::default(); MAX_LINES * 2]
+        );
+
+        let camera_buffer = create_buffer(
+            device,
+            wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            &[CameraUniform {
+                view_proj: Matrix4::<f32>::one().into(),
+                world_position: Vector3::<f32>::zero().into(),
+            }]
+        );
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("debug bind group layout"),
+            entries: &[
+                //camera
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None
+                },
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("debug bind group"),
+            layout: &bind_group_layout,
+            entries: &[
+                //camera
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: camera_buffer.as_entire_binding(),
+                },
+            ]
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("debug pipeline layout"),
+            bind_group_layouts: &[
+                &bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("debug pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vertex_main",
+                buffers: &[vertex_buffer_layout],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fragment_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::Rgba16Float,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })]
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            //lines are pulled in front of the surfaces they lie on, and never hide each other
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        DebugPass {
+            camera_buffer,
+            bind_group,
+            pipeline,
+            vertex_buffer,
+            vertices: Vec::with_capacity(MAX_LINES * 2),
+        }
+    }
+}
+
+impl RenderNode for DebugPass {
+    fn rebind(&mut self, _device: &wgpu::Device, _graph: &RenderGraph) {}
+
+    fn render(&mut self, frame: &mut Frame) {
+        let Frame {queue, game, graph, ..} = *frame;
+        if !game.debug_draw.enabled || game.debug_draw.is_empty() {
+            return;
+        }
+
+        self.vertices.clear();
+        let vertices = &mut self.vertices;
+        game.debug_draw.for_each_line(|start, end, colour| {
+            if vertices.len() < MAX_LINES * 2 {
+                vertices.push(LineVertex { position: start, colour });
+                vertices.push(LineVertex { position: end, colour });
+            }
+        });
+        queue.write_buffer(&self.vertex_buffer, 0, bytes_slice(&self.vertices));
+
+        let (width, height) = graph.render_size();
+        let camera_transform = ModelPass::camera_transform(game);
+        let camera_uniform = CameraUniform {
+            view_proj: GpuMat4(game.state.camera.to_camera_matrix(width, height, &camera_transform)),
+            world_position: GpuVec3(camera_transform.position),
+        };
+        queue.write_buffer(&self.camera_buffer, 0, bytes_slice(&[camera_uniform]));
+
+        let mut render_pass = frame.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("debug pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &graph.target(MODEL_OUTPUT).view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                }
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &graph.target(MODEL_DEPTH).view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.vertices.len() as u32, 0..1);
+    }
+}
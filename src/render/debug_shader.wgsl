@@ -0,0 +1,34 @@
+struct VertexInput {
+  @location(0) position: vec3<f32>,
+  @location(1) colour: vec3<f32>,
+}
+
+struct FragmentInput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) colour: vec3<f32>,
+}
+
+struct CameraUniform {
+  view_proj: mat4x4<f32>,
+  world_position: vec3<f32>,
+}
+
+//fraction of the way to the camera lines are pulled, so they show on the surfaces they outline
+let PULL: f32 = 0.002;
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@vertex
+fn vertex_main(vert: VertexInput) -> FragmentInput {
+  var out: FragmentInput;
+  let position = mix(vert.position, camera.world_position, PULL);
+  out.clip_position = camera.view_proj * vec4<f32>(position, 1.0);
+  out.colour = vert.colour;
+  return out;
+}
+
+@fragment
+fn fragment_main(in: FragmentInput) -> @location(0) vec4<f32> {
+  return vec4<f32>(in.colour, 1.0);
+}
@@ -0,0 +1,161 @@
+use crate::game::Game;
+use super::model_pass::ModelPass;
+use super::debug_pass::DebugPass;
+use super::luminance_pass::{LuminancePass, ADAPTED_LUMINANCE};
+use super::bloom_pass::BloomPass;
+use super::post_pass::PostPass;
+use super::render_graph::{RenderGraph, RenderNode, Frame};
+
+/// The render graph and the nodes which draw a game into it, independent of whether the final
+/// output is a window surface or an offscreen texture.
+pub struct FrameRenderer {
+    graph: RenderGraph,
+    // Run in order each frame
+    nodes: Vec<Box<dyn RenderNode>>,
+}
+
+impl FrameRenderer {
+    pub fn new(device: &wgpu::Device, width: u32, height: u32, render_scale: f32, output_format: wgpu::TextureFormat) -> FrameRenderer {
+        Self::build(device, width, height, render_scale, output_format, true)
+    }
+
+    /// Like `new`, but without measuring the scene for auto exposure. The adapted luminance is
+    /// copied in from another renderer with `share_exposure` instead.
+    pub fn sharing_exposure(device: &wgpu::Device, width: u32, height: u32, render_scale: f32, output_format: wgpu::TextureFormat) -> FrameRenderer {
+        Self::build(device, width, height, render_scale, output_format, false)
+    }
+
+    fn build(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        render_scale: f32,
+        output_format: wgpu::TextureFormat,
+        measure_luminance: bool,
+    ) -> FrameRenderer {
+        let mut graph = RenderGraph::new(width, height, render_scale, device.limits().max_texture_dimension_2d);
+        let mut nodes: Vec<Box<dyn RenderNode>> = vec![
+            Box::new(ModelPass::new(device, &mut graph)),
+            Box::new(DebugPass::new(device)),
+        ];
+        if measure_luminance {
+            nodes.push(Box::new(LuminancePass::new(device, &mut graph)));
+        } else {
+            LuminancePass::add_adapted_target(device, &mut graph);
+        }
+        nodes.push(Box::new(BloomPass::new(device, &mut graph)));
+        //last, since it's the only node writing the output
+        nodes.push(Box::new(PostPass::new(device, output_format)));
+        let mut frame_renderer = FrameRenderer {
+            graph,
+            nodes,
+        };
+        frame_renderer.rebind_nodes(device);
+        frame_renderer
+    }
+
+    fn rebind_nodes(&mut self, device: &wgpu::Device) {
+        for node in self.nodes.iter_mut() {
+            node.rebind(device, &self.graph);
+        }
+    }
+
+    pub fn output_size(&self) -> (u32, u32) {
+        self.graph.output_size()
+    }
+
+    pub fn render_scale(&self) -> f32 {
+        self.graph.render_scale()
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        if self.graph.resize(device, width, height) {
+            self.rebind_nodes(device);
+        }
+    }
+
+    pub fn set_render_scale(&mut self, device: &wgpu::Device, render_scale: f32) {
+        if self.graph.set_render_scale(device, render_scale) {
+            self.rebind_nodes(device);
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        game: &Game,
+        output_view: &wgpu::TextureView,
+    ) {
+        let mut frame = Frame {
+            device,
+            queue,
+            encoder,
+            graph: &self.graph,
+            game,
+            output_view,
+        };
+        for node in self.nodes.iter_mut() {
+            node.render(&mut frame);
+        }
+    }
+
+    /// Writes the output of the frame just rendered into another view as well, by running only
+    /// the last node again rather than drawing the whole scene twice.
+    pub fn render_output(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        game: &Game,
+        output_view: &wgpu::TextureView,
+    ) {
+        let mut frame = Frame {
+            device,
+            queue,
+            encoder,
+            graph: &self.graph,
+            game,
+            output_view,
+        };
+        if let Some(node) = self.nodes.last_mut() {
+            node.render(&mut frame);
+        }
+    }
+
+    /// Records a copy of another renderer's adapted luminance into this one's, so both expose
+    /// the scene the same way.
+    pub fn share_exposure(&self, encoder: &mut wgpu::CommandEncoder, source: &FrameRenderer) {
+        let adapted = self.graph.target(ADAPTED_LUMINANCE);
+        encoder.copy_texture_to_texture(
+            source.graph.target(ADAPTED_LUMINANCE).texture.as_image_copy(),
+            adapted.texture.as_image_copy(),
+            wgpu::Extent3d {
+                width: adapted.width,
+                height: adapted.height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
+
+pub async fn request_device(adapter: &wgpu::Adapter) -> (wgpu::Device, wgpu::Queue) {
+    println!("Found adapter {}", adapter.get_info().name);
+    // adapter.limits();
+    // adapter.features();
+
+    let (device, queue) = adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: None,
+            features: wgpu::Features::empty(),
+            // features: wgpu::Features::NON_FILL_POLYGON_MODE,
+            limits: wgpu::Limits::downlevel_defaults(),
+        },
+        None
+    ).await.expect("Failed to request device");
+    device.on_uncaptured_error(|err| {
+        dbg!(err);
+    });
+    (device, queue)
+}
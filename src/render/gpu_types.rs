@@ -12,6 +12,17 @@ impl From<f32> for GpuFloat {
   }
 }
 
+#[derive(Copy, Clone, Default)]
+#[repr(C, align(4))]
+pub struct GpuUint(pub u32);
+
+impl From<u32> for GpuUint {
+  #[inline]
+  fn from(v: u32) -> GpuUint {
+    GpuUint(v)
+  }
+}
+
 #[derive(Copy, Clone)]
 #[repr(C, align(16))]
 pub struct GpuVec3(pub Vector3<f32>);
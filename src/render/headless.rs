@@ -0,0 +1,97 @@
+use image::RgbaImage;
+use crate::game::Game;
+use super::frame_renderer::{FrameRenderer, request_device};
+use super::readback::Readback;
+use super::texture::Texture;
+
+const OUTPUT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Renders into an offscreen texture instead of a window surface, for golden images and
+/// thumbnails. Frames are read back to the CPU synchronously.
+pub struct HeadlessRenderer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    frame_renderer: FrameRenderer,
+    output: Texture,
+}
+
+impl HeadlessRenderer {
+    /// With `software` set, only a CPU adapter such as llvmpipe or WARP is accepted, so output
+    /// doesn't depend on the GPU of the machine running it.
+    pub async fn new(width: u32, height: u32, software: bool) -> Result<HeadlessRenderer, String> {
+        let backend = wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::all());
+        let instance = wgpu::Instance::new(backend);
+        let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: None,
+            force_fallback_adapter: software,
+        }).await.ok_or_else(|| String::from("Failed to find adapter for headless rendering"))?;
+        let (device, queue) = request_device(&adapter).await;
+
+        let frame_renderer = FrameRenderer::new(&device, width, height, 1.0, OUTPUT_FORMAT);
+        let output = Self::create_output(&device, &frame_renderer);
+
+        Ok(HeadlessRenderer {
+            device,
+            queue,
+            frame_renderer,
+            output,
+        })
+    }
+
+    fn create_output(device: &wgpu::Device, frame_renderer: &FrameRenderer) -> Texture {
+        let (width, height) = frame_renderer.output_size();
+        Texture::create(
+            device,
+            width,
+            height,
+            OUTPUT_FORMAT,
+            wgpu::AddressMode::ClampToEdge,
+            None
+        )
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.frame_renderer.resize(&self.device, width, height);
+        self.output = Self::create_output(&self.device, &self.frame_renderer);
+    }
+
+    pub fn render(&mut self, game: &Game) -> RgbaImage {
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("headless encoder"),
+        });
+        self.frame_renderer.render(&self.device, &self.queue, &mut encoder, game, &self.output.view);
+        let readback = Readback::copy_texture(&self.device, &mut encoder, &self.output);
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        readback.map(|result| result.expect("Failed to map readback buffer"));
+        self.device.poll(wgpu::Maintain::Wait);
+        readback.to_image()
+    }
+
+    pub fn render_to_file(&mut self, game: &Game, path: &str) -> Result<(), String> {
+        self.render(game).save(path).map_err(|err| format!("Failed to write {}: {}", path, err))
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_headless_render() {
+        let mut game = Game::load_map("maps/example.toml");
+        game.advance_to_tick(30);
+        let mut renderer = match pollster::block_on(HeadlessRenderer::new(64, 48, true)) {
+            Ok(renderer) => renderer,
+            Err(err) => {
+                println!("Skipping headless render test: {}", err);
+                return;
+            }
+        };
+        let image = renderer.render(&game);
+        assert_eq!((64, 48), image.dimensions());
+        //objects should be drawn over the fog clear colour
+        let first = image.get_pixel(0, 0);
+        assert!(image.pixels().any(|pixel| pixel != first));
+    }
+}
@@ -0,0 +1,209 @@
+use std::time::Instant;
+use crate::game::tags::Scenario;
+use super::common::{FullscreenQuad, create_texture_bind_group_layout, create_texture_bind_group};
+use super::model_pass::MODEL_OUTPUT;
+use super::render_graph::{RenderGraph, RenderNode, TargetDesc, Frame};
+
+pub const LUMINANCE: &str = "luminance";
+pub const ADAPTED_LUMINANCE: &str = "adapted_luminance";
+const LUMINANCE_SIZE: u32 = 256;
+const LUMINANCE_LEVELS: u32 = 9; //256 down to 1x1
+const LUMINANCE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R16Float;
+const DEFAULT_AUTO_EXPOSURE_SPEED: f32 = 1.5;
+
+/// Measures the average log luminance of the HDR scene by downsampling it to a single texel,
+/// then blends that measurement over time into the adapted luminance target for the post pass.
+pub struct LuminancePass {
+    quad: FullscreenQuad,
+    bind_group_layout: wgpu::BindGroupLayout,
+    log_luminance_pipeline: wgpu::RenderPipeline,
+    downsample_pipeline: wgpu::RenderPipeline,
+    adapt_pipeline: wgpu::RenderPipeline,
+    input_bind_group: Option<wgpu::BindGroup>,
+    level_bind_groups: Vec<wgpu::BindGroup>,
+    prev_frame: Option<Instant>,
+}
+
+impl LuminancePass {
+    pub fn new(device: &wgpu::Device, graph: &mut RenderGraph) -> LuminancePass {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("luminance_shader.wgsl"));
+        let quad = FullscreenQuad::new(device);
+        let bind_group_layout = create_texture_bind_group_layout(device, "luminance bind group layout");
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("luminance pipeline layout"),
+            bind_group_layouts: &[
+                &bind_group_layout
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let create_pipeline = |label: &str, entry_point: &str, blend: wgpu::BlendState| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vertex_main",
+                    buffers: &[FullscreenQuad::VERTEX_LAYOUT],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: LUMINANCE_FORMAT,
+                        blend: Some(blend),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })]
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Cw,
+                    cull_mode: Some(wgpu::Face::Back),
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+            })
+        };
+
+        let log_luminance_pipeline = create_pipeline("log luminance pipeline", "log_luminance_main", wgpu::BlendState::REPLACE);
+        let downsample_pipeline = create_pipeline("luminance downsample pipeline", "downsample_main", wgpu::BlendState::REPLACE);
+        //lerps from the previously adapted value towards the new measurement by the blend constant
+        let adapt_pipeline = create_pipeline("luminance adapt pipeline", "downsample_main", wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::Constant,
+                dst_factor: wgpu::BlendFactor::OneMinusConstant,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::REPLACE,
+        });
+
+        graph.add_target(
+            device,
+            LUMINANCE,
+            TargetDesc::fixed(LUMINANCE_SIZE, LUMINANCE_SIZE, LUMINANCE_FORMAT).with_levels(LUMINANCE_LEVELS)
+        );
+        Self::add_adapted_target(device, graph);
+
+        LuminancePass {
+            quad,
+            bind_group_layout,
+            log_luminance_pipeline,
+            downsample_pipeline,
+            adapt_pipeline,
+            input_bind_group: None,
+            level_bind_groups: Vec::new(),
+            prev_frame: None,
+        }
+    }
+
+    /// Adds just the target the post pass reads the adapted luminance from, for graphs which
+    /// have it filled in from elsewhere rather than measuring their own.
+    pub fn add_adapted_target(device: &wgpu::Device, graph: &mut RenderGraph) {
+        graph.add_target(device, ADAPTED_LUMINANCE, TargetDesc::fixed(1, 1, LUMINANCE_FORMAT));
+    }
+
+    /// Auto exposure adaptation speed per second, or None if the scenario uses manual exposure.
+    pub fn adaptation_speed(scenario: &Scenario) -> Option<f32> {
+        if scenario.auto_exposure.unwrap_or(false) {
+            return Some(scenario.auto_exposure_speed.unwrap_or(DEFAULT_AUTO_EXPOSURE_SPEED));
+        }
+        None
+    }
+
+    fn draw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::RenderPipeline,
+        input: &wgpu::BindGroup,
+        output_view: &wgpu::TextureView,
+        blend_constant: Option<f64>,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("luminance pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                }
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(pipeline);
+        if let Some(constant) = blend_constant {
+            render_pass.set_blend_constant(wgpu::Color {
+                r: constant,
+                g: constant,
+                b: constant,
+                a: constant,
+            });
+        }
+        render_pass.set_bind_group(0, input, &[]);
+        self.quad.draw(&mut render_pass);
+    }
+}
+
+impl RenderNode for LuminancePass {
+    fn rebind(&mut self, device: &wgpu::Device, graph: &RenderGraph) {
+        let input = graph.target(MODEL_OUTPUT);
+        self.input_bind_group = Some(create_texture_bind_group(
+            device,
+            &self.bind_group_layout,
+            &input.view,
+            &input.sampler,
+            "luminance input bind group"
+        ));
+
+        self.level_bind_groups = graph.target_levels(LUMINANCE).iter().map(|level| {
+            create_texture_bind_group(device, &self.bind_group_layout, &level.view, &level.sampler, "luminance level bind group")
+        }).collect();
+        //the adapted target may have been recreated, so start adapting from scratch
+        self.prev_frame = None;
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let adaptation_speed = match Self::adaptation_speed(&frame.game.map.scenario) {
+            Some(speed) => speed,
+            None => {
+                self.prev_frame = None;
+                return;
+            }
+        };
+        let input_bind_group = match &self.input_bind_group {
+            Some(bind_group) => bind_group,
+            None => return,
+        };
+
+        let now = Instant::now();
+        //snap straight to the measured value on the first frame
+        let adaptation = match self.prev_frame {
+            Some(prev_frame) => 1.0 - (-adaptation_speed * now.duration_since(prev_frame).as_secs_f32()).exp(),
+            None => 1.0,
+        };
+        self.prev_frame = Some(now);
+
+        let levels = frame.graph.target_levels(LUMINANCE);
+        self.draw(frame.encoder, &self.log_luminance_pipeline, input_bind_group, &levels[0].view, None);
+        for (level, prev_level_bind_group) in levels[1..].iter().zip(&self.level_bind_groups) {
+            self.draw(frame.encoder, &self.downsample_pipeline, prev_level_bind_group, &level.view, None);
+        }
+        self.draw(
+            frame.encoder,
+            &self.adapt_pipeline,
+            &self.level_bind_groups[levels.len() - 1],
+            &frame.graph.target(ADAPTED_LUMINANCE).view,
+            Some(adaptation as f64)
+        );
+    }
+}
@@ -0,0 +1,44 @@
+struct VertexInput {
+  @location(0) position: vec2<f32>,
+}
+
+struct FragmentInput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) sample_position: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var source_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler: sampler;
+
+@vertex
+fn vertex_main(vert: VertexInput) -> FragmentInput {
+  var out: FragmentInput;
+  out.clip_position = vec4<f32>(
+    vert.position.x * 2.0 - 1.0,
+    vert.position.y * -2.0 + 1.0,
+    1.0,
+    1.0
+  );
+  out.sample_position = vec2<f32>(
+    vert.position.x,
+    vert.position.y,
+  );
+  return out;
+}
+
+//averaging log luminance gives the geometric mean, so a few very bright pixels don't dominate
+@fragment
+fn log_luminance_main(in: FragmentInput) -> @location(0) vec4<f32> {
+  let colour: vec3<f32> = textureSample(source_texture, source_sampler, in.sample_position).rgb;
+  let luminance: f32 = dot(colour, vec3<f32>(0.2126, 0.7152, 0.0722));
+  return vec4<f32>(log(max(luminance, 0.0001)), 0.0, 0.0, 1.0);
+}
+
+//sampling between texels of the previous level averages a 2x2 block
+@fragment
+fn downsample_main(in: FragmentInput) -> @location(0) vec4<f32> {
+  let average: f32 = textureSample(source_texture, source_sampler, in.sample_position).r;
+  return vec4<f32>(average, 0.0, 0.0, 1.0);
+}
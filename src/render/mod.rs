@@ -1,11 +1,20 @@
 mod renderer;
+mod frame_renderer;
+mod headless;
+mod readback;
+mod screenshot;
 mod window;
 mod model;
 mod gpu_types;
 mod model_pass;
+mod debug_pass;
+mod luminance_pass;
+mod bloom_pass;
 mod post_pass;
 mod common;
+mod render_graph;
 mod texture;
 
 pub use self::renderer::*;
 pub use self::window::*;
+pub use self::headless::HeadlessRenderer;
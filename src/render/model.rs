@@ -1,6 +1,5 @@
 use cgmath::{Matrix4, Matrix3, Vector3, Vector2, prelude::*};
 use std::vec::Vec;
-use gltf;
 
 #[derive(Copy, Clone)]
 #[repr(C)]
@@ -34,9 +33,10 @@ impl Model {
     pub fn from_gltf(path: &str) -> Result<Model, String> {
         if let Ok((file, buffers, _images)) = gltf::import(path) {
             if let Some(scene) = file.default_scene() {
-                if let Some(root_node) = scene.nodes().find(|n| n.name().map_or(false, |name| name == "root")) {
+                if let Some(root_node) = scene.nodes().find(|n| n.name() == Some("root")) {
                     //todo: use the hierarchy -- for now we just use the root node's mesh
                     let mesh = root_node.mesh().unwrap();
+                    #[allow(clippy::never_loop)]
                     for primitive in mesh.primitives() {
                         let primitive_reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
                         let _material_name = primitive.material().name().unwrap_or("default");
@@ -130,11 +130,11 @@ impl Model {
         }
     }
 
-    pub fn vertices_slice<'a>(&'a self) -> &'a[Vertex] {
+    pub fn vertices_slice(&self) -> &[Vertex] {
         &self.vertices
     }
 
-    pub fn indices_slice<'a>(&'a self) -> &'a[u16] {
+    pub fn indices_slice(&self) -> &[u16] {
         &self.indices
     }
 }
@@ -144,6 +144,7 @@ mod tests {
 
     #[test]
     fn test_mesh_size() {
-        assert_eq!(24, std::mem::size_of::<Vertex>());
+        //position, normal, tangent and bitangent, then uv
+        assert_eq!(56, std::mem::size_of::<Vertex>());
     }
 }
\ No newline at end of file
@@ -1,16 +1,18 @@
 use cgmath::{prelude::*, Matrix4, Vector3, Vector4, Matrix3};
 use std::collections::HashMap;
-use wgpu;
 
 use crate::game::Game;
-use crate::game::state::{transform::Transform, object_state::ObjectState};
+use crate::game::state::{transform::Transform, object_state::ObjectState, water_state::WaterState};
 
 use super::common::{create_buffer, bytes_slice};
 use super::texture::Texture;
 use super::model::{Vertex, Model};
 use super::gpu_types::*;
+use super::render_graph::{RenderGraph, RenderNode, TargetDesc, Frame};
 
 const MAX_INSTANCES: usize = 128;
+pub const MODEL_OUTPUT: &str = "model_output";
+pub const MODEL_DEPTH: &str = "model_depth";
 
 struct LoadedModel {
     vertex_buffer: wgpu::Buffer,
@@ -79,11 +81,10 @@ pub struct ModelPass {
     bind_group: wgpu::BindGroup,
     pipeline: wgpu::RenderPipeline,
     model_instances_buffer: wgpu::Buffer,
-    zbuffer: Texture,
 }
 
 impl ModelPass {
-    pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> ModelPass {
+    pub fn new(device: &wgpu::Device, graph: &mut RenderGraph) -> ModelPass {
         let shader = device.create_shader_module(wgpu::include_wgsl!("model_shader.wgsl"));
 
         let vert_buffer_layout = wgpu::VertexBufferLayout {
@@ -311,12 +312,16 @@ impl ModelPass {
             multiview: None,
         });
 
-        let zbuffer = Self::create_zbuffer_texture(device, config.width, config.height);
+        graph.add_target(device, MODEL_OUTPUT, TargetDesc::relative(1.0, wgpu::TextureFormat::Rgba16Float));
+        graph.add_target(
+            device,
+            MODEL_DEPTH,
+            TargetDesc::relative(1.0, wgpu::TextureFormat::Depth32Float).with_compare(wgpu::CompareFunction::LessEqual)
+        );
 
         ModelPass {
             models: HashMap::new(),
             textures: HashMap::new(),
-            zbuffer,
             camera_buffer,
             environment_buffer,
             bind_group,
@@ -327,21 +332,6 @@ impl ModelPass {
     }
 
 
-    fn create_zbuffer_texture(device: &wgpu::Device, width: u32, height: u32) -> Texture {
-        Texture::create(
-            device,
-            width,
-            height,
-            wgpu::TextureFormat::Depth32Float,
-            wgpu::AddressMode::ClampToEdge,
-            Some(wgpu::CompareFunction::LessEqual)
-        )
-    }
-
-    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
-        self.zbuffer = Self::create_zbuffer_texture(device, config.width, config.height);
-    }
-
     fn load_texture(&mut self, path: &str, device: &wgpu::Device, queue: &wgpu::Queue, format: wgpu::TextureFormat) {
         if self.textures.contains_key(path) {
             return;
@@ -384,7 +374,45 @@ impl ModelPass {
         }
     }
 
-  pub fn render(&mut self, game: &Game, output_view: &wgpu::TextureView, queue: &mut wgpu::Queue, config: &wgpu::SurfaceConfiguration, device: &wgpu::Device) {
+  fn interpolate_object(game: &Game, object_state: &ObjectState, interpolation_fraction: f32) -> Transform {
+    if let Some(phys) = game.state.physics.get(object_state.physics_id) {
+        Transform::interpolate(&phys.prev_transform, &object_state.transform, interpolation_fraction)
+    } else {
+        object_state.transform
+    }
+}
+
+  fn camera_water(game: &Game) -> Option<&WaterState> {
+      let attached_obj = game.state.objects.get(game.state.camera.object_attachment)?;
+      let phys = game.state.physics.get(attached_obj.physics_id)?;
+      if !phys.submerged {
+          return None;
+      }
+      game.state.waters.get(phys.water)
+  }
+
+  fn interpolate_camera(game: &Game, object_state: &ObjectState, interpolation_fraction: f32) -> Transform {
+      let mut transform = Self::interpolate_object(game, object_state, interpolation_fraction);
+      transform.rotation = object_state.transform.rotation;
+      transform
+  }
+
+  /// Where the camera is this frame, shared with passes drawing over the models.
+  pub fn camera_transform(game: &Game) -> Transform {
+      let interpolation_fraction = game.state.get_tick_interpolation_fraction();
+      match game.state.objects.get(game.state.camera.object_attachment) {
+          Some(attached_obj) => Self::interpolate_camera(game, attached_obj, interpolation_fraction),
+          None => Transform::default(),
+      }
+  }
+}
+
+impl RenderNode for ModelPass {
+  fn rebind(&mut self, _device: &wgpu::Device, _graph: &RenderGraph) {}
+
+  fn render(&mut self, frame: &mut Frame) {
+    let Frame {device, queue, game, graph, ..} = *frame;
+    let (width, height) = graph.render_size();
     let interpolation_fraction = game.state.get_tick_interpolation_fraction();
 
     let diffuse_path = "maps/default_diffuse.tif";
@@ -393,30 +421,34 @@ impl ModelPass {
     self.load_texture(bump_path, device, queue, wgpu::TextureFormat::Rgba8Unorm);
 
     //load camera buffer
-    let camera_attachment = game.state.camera.object_attachment;
-    let mut camera_transform = Transform::default();
-    if camera_attachment.is_some() {
-        if let Some(attached_obj) = game.state.objects.get(camera_attachment) {
-            camera_transform = Self::interpolate_camera(game, attached_obj, interpolation_fraction);
-        }
-    }
+    let camera_transform = Self::camera_transform(game);
     let camera_uniform = CameraUniform {
-        view_proj: GpuMat4(game.state.camera.to_camera_matrix(config.width, config.height, &camera_transform)),
+        view_proj: GpuMat4(game.state.camera.to_camera_matrix(width, height, &camera_transform)),
         world_position: GpuVec3(camera_transform.position),
     };
     queue.write_buffer(&self.camera_buffer, 0, bytes_slice(&[camera_uniform]));
-    let environment_uniform = EnvironmentUniform {
+    let mut environment_uniform = EnvironmentUniform {
         fog_colour: game.map.scenario.fog_colour.unwrap_or([0.1, 0.1, 0.3, 0.8]).into(),
         fog_max_distance: game.map.scenario.fog_max_distance.unwrap_or(25f32).into(),
         fog_min_distance: game.map.scenario.fog_max_distance.unwrap_or(1f32).into(),
         sun_colour: game.map.scenario.sun_colour.unwrap_or([0.8, 0.8, 0.5]).into(),
         sun_direction: game.map.scenario.sun_direction.unwrap_or([0.1, 0.5, 1.0]).into(),
     };
+    //underwater, the water's own fog closes in
+    if let Some(water) = Self::camera_water(game) {
+        environment_uniform.fog_colour = water.fog_colour.into();
+        environment_uniform.fog_max_distance = water.fog_max_distance.into();
+        environment_uniform.fog_min_distance = 0f32.into();
+    }
     queue.write_buffer(&self.environment_buffer, 0, bytes_slice(&[environment_uniform]));
 
     //load model buffers
     let mut model_instances: HashMap<String, Vec<ModelInstance>> = HashMap::new();
     for (_id, object_state) in game.state.objects.iter() {
+        //riding inside a vehicle
+        if object_state.riding.is_some() {
+            continue;
+        }
         if let Some(object_tag) = game.map.object.get(&object_state.tag) {
             let transform = Self::interpolate_object(game, object_state, interpolation_fraction);
             let instance = ModelInstance {
@@ -433,13 +465,10 @@ impl ModelPass {
         }
     }
 
-    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-        label: Some("model encoder"),
-    });
-    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+    let mut render_pass = frame.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
         label: Some("model pass"),
         color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-            view: &output_view,
+            view: &graph.target(MODEL_OUTPUT).view,
             resolve_target: None,
             ops: wgpu::Operations {
                 load: wgpu::LoadOp::Clear(wgpu::Color {
@@ -452,7 +481,7 @@ impl ModelPass {
             }
         })],
         depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-            view: &self.zbuffer.view,
+            view: &graph.target(MODEL_DEPTH).view,
             depth_ops: Some(wgpu::Operations {
                 load: wgpu::LoadOp::Clear(1.0),
                 store: true,
@@ -494,21 +523,5 @@ impl ModelPass {
         }
     }
 
-    drop(render_pass);
-    queue.submit(std::iter::once(encoder.finish()));
-  }
-
-  fn interpolate_object(game: &Game, object_state: &ObjectState, interpolation_fraction: f32) -> Transform {
-    if let Some(phys) = game.state.physics.get(object_state.physics_id) {
-        Transform::interpolate(&phys.prev_transform, &object_state.transform, interpolation_fraction)
-    } else {
-        object_state.transform
-    }
-}
-
-  fn interpolate_camera(game: &Game, object_state: &ObjectState, interpolation_fraction: f32) -> Transform {
-      let mut transform = Self::interpolate_object(game, object_state, interpolation_fraction);
-      transform.rotation = object_state.transform.rotation;
-      transform
   }
 }
\ No newline at end of file
@@ -1,13 +1,16 @@
-use wgpu;
 use cgmath::{prelude::*, Vector4};
-use super::common::{create_buffer, bytes_slice};
-use super::texture::Texture;
+use crate::game::tags::{Scenario, Tonemap};
+use super::bloom_pass::{BloomPass, BLOOM};
+use super::luminance_pass::{LuminancePass, ADAPTED_LUMINANCE};
+use super::model_pass::MODEL_OUTPUT;
+use super::common::{create_buffer, bytes_slice, FullscreenQuad};
 use super::gpu_types::*;
+use super::render_graph::{RenderGraph, RenderNode, Frame};
 
 pub struct PostPass {
-    vertices_buffer: wgpu::Buffer,
-    indices_buffer: wgpu::Buffer,
-    bind_group: wgpu::BindGroup,
+    quad: FullscreenQuad,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: Option<wgpu::BindGroup>,
     pipeline: wgpu::RenderPipeline,
     effects_buffer: wgpu::Buffer,
 }
@@ -18,27 +21,17 @@ struct EffectsUniform {
     multiply_colour: GpuVec4,
     screen_colour: GpuVec4,
     blur_radius: GpuFloat,
+    exposure: GpuFloat,
+    auto_exposure: GpuUint,
+    tonemap: GpuUint,
+    bloom_intensity: GpuFloat,
 }
 
 impl PostPass {
-    pub fn new(device: &wgpu::Device, prev_pass_texture: &Texture, config: &wgpu::SurfaceConfiguration) -> PostPass {
+    pub fn new(device: &wgpu::Device, output_format: wgpu::TextureFormat) -> PostPass {
         let shader = device.create_shader_module(wgpu::include_wgsl!("post_shader.wgsl"));
 
-        let vertices_buffer = create_buffer(device, wgpu::BufferUsages::VERTEX, &[
-            [0f32, 0f32],
-            [1f32, 0f32],
-            [1f32, 1f32],
-            [0f32, 1f32],
-        ]);
-
-        let indices_buffer = create_buffer(device, wgpu::BufferUsages::INDEX, &[
-            0u16,
-            1u16,
-            2u16,
-            0u16,
-            2u16,
-            3u16,
-        ]);
+        let quad = FullscreenQuad::new(device);
 
         let effects_buffer = create_buffer(
             device,
@@ -46,19 +39,6 @@ impl PostPass {
             &[EffectsUniform::default()]
         );
 
-        let vert_buffer_layout = wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                //position
-                wgpu::VertexAttribute {
-                    offset: 0,
-                    format: wgpu::VertexFormat::Float32x2,
-                    shader_location: 0,
-                },
-            ]
-        };
-
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("post bind group layout"),
             entries: &[
@@ -91,28 +71,32 @@ impl PostPass {
                     },
                     count: None
                 },
-            ],
-        });
-
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("post bind group"),
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&prev_pass_texture.view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&prev_pass_texture.sampler),
+                //adapted log luminance
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None
                 },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: effects_buffer.as_entire_binding(),
+                //bloom
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None
                 },
-            ]
+            ],
         });
-        
+
+
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor  {
             label: Some("post pipeline layout"),
             bind_group_layouts: &[
@@ -127,13 +111,13 @@ impl PostPass {
             vertex: wgpu::VertexState {
                 module: &shader,
                 entry_point: "vertex_main",
-                buffers: &[vert_buffer_layout],
+                buffers: &[FullscreenQuad::VERTEX_LAYOUT],
             },
             fragment: Some(wgpu::FragmentState {
                 module: &shader,
                 entry_point: "fragment_main",
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
+                    format: output_format,
                     blend: Some(wgpu::BlendState::REPLACE),
                     write_mask: wgpu::ColorWrites::ALL,
                 })]
@@ -157,29 +141,73 @@ impl PostPass {
         });
 
         PostPass {
-            vertices_buffer,
-            indices_buffer,
-            bind_group,
+            quad,
+            bind_group_layout,
+            bind_group: None,
             pipeline,
             effects_buffer,
         }
     }
 
-    pub fn render(&self, device: &wgpu::Device, input_view: &wgpu::TextureView, output_view: &wgpu::TextureView, queue: &mut wgpu::Queue) {
+}
+
+impl RenderNode for PostPass {
+    fn rebind(&mut self, device: &wgpu::Device, graph: &RenderGraph) {
+        let prev_pass_texture = graph.target(MODEL_OUTPUT);
+        self.bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("post bind group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&prev_pass_texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&prev_pass_texture.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.effects_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&graph.target(ADAPTED_LUMINANCE).view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&graph.target(BLOOM).view),
+                },
+            ]
+        }));
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let bind_group = match &self.bind_group {
+            Some(bind_group) => bind_group,
+            None => return,
+        };
+        let scenario = &frame.game.map.scenario;
+        let tonemap = match scenario.tonemap.unwrap_or(Tonemap::None) {
+            Tonemap::None => 0,
+            Tonemap::Reinhard => 1,
+            Tonemap::Aces => 2,
+        };
         let effects_uniform = EffectsUniform {
             multiply_colour: Vector4::new(1.0, 0.0, 0.0, 0.0).into(),
             screen_colour: Vector4::new(1.0, 0.0, 0.0, 0.0).into(),
             blur_radius: GpuFloat(0.00),
+            exposure: scenario.exposure.unwrap_or(0.0).exp2().into(),
+            auto_exposure: (LuminancePass::adaptation_speed(scenario).is_some() as u32).into(),
+            tonemap: GpuUint(tonemap),
+            bloom_intensity: BloomPass::intensity(scenario).into(),
         };
-        queue.write_buffer(&self.effects_buffer, 0, bytes_slice(&[effects_uniform]));
-        
-        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-            label: Some("post encoder"),
-        });
-        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        frame.queue.write_buffer(&self.effects_buffer, 0, bytes_slice(&[effects_uniform]));
+
+        let mut render_pass = frame.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("post pass"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &output_view,
+                view: frame.output_view,
                 resolve_target: None,
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
@@ -190,13 +218,7 @@ impl PostPass {
         });
 
         render_pass.set_pipeline(&self.pipeline);
-        render_pass.set_bind_group(0, &self.bind_group, &[]);
-
-        render_pass.set_vertex_buffer(0, self.vertices_buffer.slice(..));
-        render_pass.set_index_buffer(self.indices_buffer.slice(..), wgpu::IndexFormat::Uint16);
-        render_pass.draw_indexed(0..6, 0, 0..1);
-
-        drop(render_pass);
-        queue.submit(std::iter::once(encoder.finish()));
+        render_pass.set_bind_group(0, bind_group, &[]);
+        self.quad.draw(&mut render_pass);
     }
-}
\ No newline at end of file
+}
@@ -12,6 +12,10 @@ struct EffectsUniform {
   multiply_colour: vec4<f32>,
   screen_colour: vec4<f32>,
   blur_radius: f32,
+  exposure: f32,
+  auto_exposure: u32,
+  tonemap: u32,
+  bloom_intensity: f32,
 }
 
 @group(0) @binding(0)
@@ -20,6 +24,28 @@ var prev_texture: texture_2d<f32>;
 var prev_sampler: sampler;
 @group(0) @binding(2)
 var<uniform> effects: EffectsUniform;
+@group(0) @binding(3)
+var adapted_luminance: texture_2d<f32>;
+@group(0) @binding(4)
+var bloom_texture: texture_2d<f32>;
+
+let TONEMAP_REINHARD: u32 = 1u;
+let TONEMAP_ACES: u32 = 2u;
+let AUTO_EXPOSURE_KEY: f32 = 0.18;
+
+fn tonemap_reinhard(colour: vec3<f32>) -> vec3<f32> {
+  return colour / (1.0 + colour);
+}
+
+//Narkowicz's curve fit of the ACES filmic reference transform
+fn tonemap_aces(colour: vec3<f32>) -> vec3<f32> {
+  let a: f32 = 2.51;
+  let b: f32 = 0.03;
+  let c: f32 = 2.43;
+  let d: f32 = 0.59;
+  let e: f32 = 0.14;
+  return saturate((colour * (a * colour + b)) / (colour * (c * colour + d) + e));
+}
 
 @vertex
 fn vertex_main(vert: VertexInput) -> FragmentInput {
@@ -59,6 +85,24 @@ fn fragment_main(in: FragmentInput) -> @location(0) vec4<f32> {
     final_colour = blurred;
   }
   
+  if (effects.bloom_intensity > 0.0) {
+    let bloom: vec3<f32> = textureSample(bloom_texture, prev_sampler, in.sample_position).rgb;
+    final_colour += bloom * effects.bloom_intensity;
+  }
+
+  var exposure: f32 = effects.exposure;
+  if (effects.auto_exposure != 0u) {
+    let average_luminance: f32 = exp(textureLoad(adapted_luminance, vec2<i32>(0, 0), 0).r);
+    exposure *= clamp(AUTO_EXPOSURE_KEY / average_luminance, 0.0625, 16.0);
+  }
+  final_colour *= exposure;
+
+  if (effects.tonemap == TONEMAP_REINHARD) {
+    final_colour = tonemap_reinhard(final_colour);
+  } else if (effects.tonemap == TONEMAP_ACES) {
+    final_colour = tonemap_aces(final_colour);
+  }
+
   final_colour = mix(final_colour, final_colour * effects.multiply_colour.rgb, effects.multiply_colour.a);
   final_colour = mix(final_colour, 1.0 - ((1.0 - final_colour) * (1.0 - effects.screen_colour.rgb)), effects.screen_colour.a);
   // final_colour = smoothstep(vec3<f32>(0.0, 0.0, 0.0), vec3<f32>(2.0, 2.0, 2.0), final_colour);
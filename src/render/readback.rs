@@ -0,0 +1,81 @@
+use image::RgbaImage;
+use super::texture::Texture;
+
+/// A CPU-readable copy of a colour texture. Rows are padded to wgpu's copy alignment in the
+/// buffer and unpadded when converted to an image.
+pub struct Readback {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    format: wgpu::TextureFormat,
+}
+
+impl Readback {
+    /// Records a copy of the texture into a new buffer. The copy happens when the
+    /// encoder is submitted, after which the buffer can be mapped.
+    pub fn copy_texture(device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, texture: &Texture) -> Readback {
+        let unpadded_bytes_per_row = texture.width * 4;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback buffer"),
+            size: (padded_bytes_per_row * texture.height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &texture.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(padded_bytes_per_row),
+                    rows_per_image: std::num::NonZeroU32::new(texture.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: texture.width,
+                height: texture.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        Readback {
+            buffer,
+            width: texture.width,
+            height: texture.height,
+            padded_bytes_per_row,
+            format: texture.format,
+        }
+    }
+
+    /// Starts mapping the buffer; the callback runs during a later `Device::poll`.
+    pub fn map(&self, on_mapped: impl FnOnce(Result<(), wgpu::BufferAsyncError>) + Send + 'static) {
+        self.buffer.slice(..).map_async(wgpu::MapMode::Read, on_mapped);
+    }
+
+    /// Converts the mapped contents to RGBA. Only valid once mapping has completed.
+    pub fn to_image(&self) -> RgbaImage {
+        let swap_red_blue = matches!(self.format, wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb);
+        let mut pixels: Vec<u8> = Vec::with_capacity((self.width * self.height * 4) as usize);
+        {
+            let mapped = self.buffer.slice(..).get_mapped_range();
+            for row in mapped.chunks(self.padded_bytes_per_row as usize) {
+                for pixel in row[..(self.width * 4) as usize].chunks(4) {
+                    if swap_red_blue {
+                        pixels.extend_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]);
+                    } else {
+                        pixels.extend_from_slice(pixel);
+                    }
+                }
+            }
+        }
+        self.buffer.unmap();
+        RgbaImage::from_raw(self.width, self.height, pixels).expect("Readback size mismatch")
+    }
+}
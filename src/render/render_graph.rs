@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+use crate::game::Game;
+use super::texture::Texture;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum TargetSize {
+    /// A fraction of the render resolution, which is the output size multiplied by the render scale
+    Relative(f32),
+    Fixed(u32, u32),
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct TargetDesc {
+    pub size: TargetSize,
+    pub format: wgpu::TextureFormat,
+    /// Number of textures in the chain, each half the size of the previous one. Capped to the
+    /// number of halvings the target's size allows.
+    pub max_levels: u32,
+    pub compare: Option<wgpu::CompareFunction>,
+}
+
+impl TargetDesc {
+    pub fn relative(scale: f32, format: wgpu::TextureFormat) -> TargetDesc {
+        TargetDesc {
+            size: TargetSize::Relative(scale),
+            format,
+            max_levels: 1,
+            compare: None,
+        }
+    }
+
+    pub fn fixed(width: u32, height: u32, format: wgpu::TextureFormat) -> TargetDesc {
+        TargetDesc {
+            size: TargetSize::Fixed(width, height),
+            format,
+            max_levels: 1,
+            compare: None,
+        }
+    }
+
+    /// Separate textures are used rather than mip levels, since not every backend can sample
+    /// from a single mip level while rendering to another.
+    pub fn with_levels(mut self, max_levels: u32) -> TargetDesc {
+        self.max_levels = max_levels;
+        self
+    }
+
+    pub fn with_compare(mut self, compare: wgpu::CompareFunction) -> TargetDesc {
+        self.compare = Some(compare);
+        self
+    }
+}
+
+struct Target {
+    desc: TargetDesc,
+    levels: Vec<Texture>,
+}
+
+/// Owns the intermediate textures passes render into and sample from. Targets sized relative to
+/// the output are recreated when it is resized or the render scale changes, after which every
+/// node must be rebound since its bind groups still reference the old textures.
+pub struct RenderGraph {
+    output_width: u32,
+    output_height: u32,
+    render_scale: f32,
+    // Widest or tallest texture the device can create, which no target may exceed
+    max_dimension: u32,
+    targets: HashMap<&'static str, Target>,
+}
+
+/// Everything a node needs to record its work for the current frame.
+pub struct Frame<'a> {
+    pub device: &'a wgpu::Device,
+    pub queue: &'a wgpu::Queue,
+    pub encoder: &'a mut wgpu::CommandEncoder,
+    pub graph: &'a RenderGraph,
+    pub game: &'a Game,
+    pub output_view: &'a wgpu::TextureView,
+}
+
+pub trait RenderNode {
+    /// Recreates any bind groups or views which reference graph targets.
+    fn rebind(&mut self, device: &wgpu::Device, graph: &RenderGraph);
+    fn render(&mut self, frame: &mut Frame);
+}
+
+impl RenderGraph {
+    pub fn new(output_width: u32, output_height: u32, render_scale: f32, max_dimension: u32) -> RenderGraph {
+        RenderGraph {
+            output_width: std::cmp::max(1, output_width),
+            output_height: std::cmp::max(1, output_height),
+            render_scale,
+            max_dimension,
+            targets: HashMap::new(),
+        }
+    }
+
+    pub fn output_size(&self) -> (u32, u32) {
+        (self.output_width, self.output_height)
+    }
+
+    pub fn render_scale(&self) -> f32 {
+        self.render_scale
+    }
+
+    pub fn render_size(&self) -> (u32, u32) {
+        self.scaled_size(self.render_scale)
+    }
+
+    /// Shrinks anything bigger than the device allows, keeping the output's aspect ratio.
+    fn scaled_size(&self, scale: f32) -> (u32, u32) {
+        let largest = std::cmp::max(self.output_width, self.output_height) as f32;
+        let scale = scale.min(self.max_dimension as f32 / largest);
+        let scaled = |size: u32| (size as f32 * scale).round() as u32;
+        (
+            scaled(self.output_width).clamp(1, self.max_dimension),
+            scaled(self.output_height).clamp(1, self.max_dimension),
+        )
+    }
+
+    fn create_levels(&self, device: &wgpu::Device, desc: &TargetDesc) -> Vec<Texture> {
+        let (width, height) = match desc.size {
+            TargetSize::Relative(scale) => self.scaled_size(scale * self.render_scale),
+            TargetSize::Fixed(width, height) => (width, height),
+        };
+        let full_chain = 32 - std::cmp::min(width, height).leading_zeros();
+        let level_count = std::cmp::min(full_chain, std::cmp::max(1, desc.max_levels));
+        (0..level_count).map(|level| {
+            Texture::create(
+                device,
+                std::cmp::max(1, width >> level),
+                std::cmp::max(1, height >> level),
+                desc.format,
+                wgpu::AddressMode::ClampToEdge,
+                desc.compare
+            )
+        }).collect()
+    }
+
+    pub fn add_target(&mut self, device: &wgpu::Device, name: &'static str, desc: TargetDesc) {
+        let levels = self.create_levels(device, &desc);
+        self.targets.insert(name, Target {
+            desc,
+            levels,
+        });
+    }
+
+    pub fn target(&self, name: &str) -> &Texture {
+        &self.target_levels(name)[0]
+    }
+
+    pub fn target_levels(&self, name: &str) -> &[Texture] {
+        match self.targets.get(name) {
+            Some(target) => &target.levels,
+            None => panic!("Render target {} was never added to the graph", name),
+        }
+    }
+
+    /// Returns true if targets were recreated and nodes need rebinding.
+    pub fn resize(&mut self, device: &wgpu::Device, output_width: u32, output_height: u32) -> bool {
+        let output_width = std::cmp::max(1, output_width);
+        let output_height = std::cmp::max(1, output_height);
+        if output_width == self.output_width && output_height == self.output_height {
+            return false;
+        }
+        self.output_width = output_width;
+        self.output_height = output_height;
+        self.recreate_relative(device);
+        true
+    }
+
+    /// Returns true if targets were recreated and nodes need rebinding.
+    pub fn set_render_scale(&mut self, device: &wgpu::Device, render_scale: f32) -> bool {
+        if render_scale == self.render_scale || render_scale <= 0.0 {
+            return false;
+        }
+        self.render_scale = render_scale;
+        self.recreate_relative(device);
+        true
+    }
+
+    fn recreate_relative(&mut self, device: &wgpu::Device) {
+        let recreated: Vec<(&'static str, Vec<Texture>)> = self.targets.iter()
+            .filter(|(_, target)| matches!(target.desc.size, TargetSize::Relative(_)))
+            .map(|(name, target)| (*name, self.create_levels(device, &target.desc)))
+            .collect();
+        for (name, levels) in recreated {
+            if let Some(target) = self.targets.get_mut(name) {
+                target.levels = levels;
+            }
+        }
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_size() {
+        let mut graph = RenderGraph::new(1920, 1080, 1.0, 2048);
+        assert_eq!((1920, 1080), graph.render_size());
+        assert_eq!((960, 540), graph.scaled_size(0.5));
+
+        //scaled past the device's limit, but not out of shape
+        graph.render_scale = 2.0;
+        assert_eq!((2048, 1152), graph.render_size());
+        assert_eq!((1920, 1080), graph.scaled_size(graph.render_scale * 0.5));
+    }
+}
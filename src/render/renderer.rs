@@ -1,14 +1,8 @@
-use std::collections::HashMap;
-use wgpu;
-use cgmath::{prelude::*, Matrix4, Vector3, Vector4};
 use crate::game::Game;
-use crate::game::state::{transform::Transform, object_state::ObjectState};
 use crate::render::Window;
 
-use super::texture::Texture;
-use super::model_pass::ModelPass;
-use super::post_pass::PostPass;
-use super::gpu_types::*;
+use super::frame_renderer::{FrameRenderer, request_device};
+use super::screenshot::Screenshots;
 
 pub struct Renderer {
     instance: wgpu::Instance,
@@ -17,14 +11,12 @@ pub struct Renderer {
     device: wgpu::Device,
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
-
-    model_pass: ModelPass,
-    model_pass_output: Texture,
-    post_pass: PostPass,
+    frame_renderer: FrameRenderer,
+    screenshots: Screenshots,
 }
 
 impl Renderer {
-    pub async fn new(window: &Window) -> Renderer {
+    pub async fn new(window: &Window, render_scale: f32) -> Renderer {
         let backend = wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::PRIMARY);
         let instance = wgpu::Instance::new(backend);
         let surface = unsafe { instance.create_surface(&window.window) };
@@ -34,23 +26,7 @@ impl Renderer {
             compatible_surface: Some(&surface),
             force_fallback_adapter: false,
         }).await.expect("Failed to find adapter");
-
-        println!("Found adapter {}", adapter.get_info().name);
-        // adapter.limits();
-        // adapter.features();
-
-        let (device, queue) = adapter.request_device(
-            &wgpu::DeviceDescriptor {
-                label: None,
-                features: wgpu::Features::empty(),
-                // features: wgpu::Features::NON_FILL_POLYGON_MODE,
-                limits: wgpu::Limits::downlevel_defaults(),
-            },
-            None
-        ).await.expect("Failed to request device");
-        device.on_uncaptured_error(|err| {
-            dbg!(err);
-        });
+        let (device, queue) = request_device(&adapter).await;
 
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
@@ -62,16 +38,8 @@ impl Renderer {
         };
         surface.configure(&device, &config);
 
-        let model_pass = ModelPass::new(&device, &config);
-        let model_pass_output = Texture::create(
-            &device,
-            config.width,
-            config.height,
-            wgpu::TextureFormat::Rgba16Float,
-            wgpu::AddressMode::ClampToEdge,
-            None
-        );
-        let post_pass = PostPass::new(&device, &model_pass_output, &config);
+        let frame_renderer = FrameRenderer::new(&device, config.width, config.height, render_scale, config.format);
+        let screenshots = Screenshots::new(config.format);
 
         Renderer {
             instance,
@@ -80,9 +48,8 @@ impl Renderer {
             device,
             queue,
             config,
-            model_pass,
-            model_pass_output,
-            post_pass,
+            frame_renderer,
+            screenshots,
         }
     }
 
@@ -90,27 +57,32 @@ impl Renderer {
         self.config.width = std::cmp::max(1, width);
         self.config.height = std::cmp::max(1, height);
         self.surface.configure(&self.device, &self.config);
-        // let model_pass_output = create_texture(
-        //     &self.device,
-        //     self.config.width,
-        //     self.config.height,
-        //     self.config.format,
-        //     wgpu::AddressMode::ClampToEdge,
-        //     None
-        // );
-        self.model_pass.resize(&self.device, &self.config);
-        // self.post_pass.resize //TODO
+        self.frame_renderer.resize(&self.device, self.config.width, self.config.height);
+    }
+
+    pub fn set_render_scale(&mut self, render_scale: f32) {
+        self.frame_renderer.set_render_scale(&self.device, render_scale);
+    }
+
+    /// Captures the next frame to a PNG, at a multiple of the window resolution.
+    pub fn request_screenshot(&mut self, scale: u32) {
+        self.screenshots.request(scale);
     }
 
     pub fn render(&mut self, game: &Game) {        
+        self.screenshots.poll(&self.device);
         if let Ok(output) = self.surface.get_current_texture() {
             let surface_output_view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
 
-            self.model_pass.render(game, &self.model_pass_output.view, &mut self.queue, &self.config, &self.device);
-            self.post_pass.render(&self.device, &self.model_pass_output.view, &surface_output_view, &mut self.queue);
+            let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("frame encoder"),
+            });
+            self.frame_renderer.render(&self.device, &self.queue, &mut encoder, game, &surface_output_view);
+            self.screenshots.capture(&self.device, &self.queue, &mut encoder, game, &mut self.frame_renderer);
+            self.queue.submit(std::iter::once(encoder.finish()));
+            self.screenshots.after_submit();
 
             output.present();
         }
     }
 }
-
@@ -0,0 +1,145 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::game::Game;
+use super::frame_renderer::FrameRenderer;
+use super::readback::Readback;
+use super::texture::Texture;
+
+const SCREENSHOT_DIR: &str = "screenshots";
+
+// How far mapping a screenshot's readback buffer has got, set from the map callback
+const MAPPING: u8 = 0;
+const MAPPED: u8 = 1;
+const MAP_FAILED: u8 = 2;
+
+struct PendingScreenshot {
+    readback: Readback,
+    map_state: Arc<AtomicU8>,
+    path: String,
+}
+
+/// Captures frames to PNG without stalling rendering. The window's frame is post processed into a
+/// texture which can be copied from, since the surface can't be, or rendered again at a multiple
+/// of the window resolution. It's read back asynchronously and written to disk on another thread
+/// once the GPU is done with it.
+pub struct Screenshots {
+    // Only for screenshots above the window resolution, so its targets can be sized independently
+    frame_renderer: Option<FrameRenderer>,
+    output: Option<Texture>,
+    // Matches the window's, so the post pass can draw into either
+    output_format: wgpu::TextureFormat,
+    requested_scale: Option<u32>,
+    submitted: Vec<PendingScreenshot>,
+    mapping: Vec<PendingScreenshot>,
+}
+
+impl Screenshots {
+    pub fn new(output_format: wgpu::TextureFormat) -> Screenshots {
+        Screenshots {
+            frame_renderer: None,
+            output: None,
+            output_format,
+            requested_scale: None,
+            submitted: Vec::new(),
+            mapping: Vec::new(),
+        }
+    }
+
+    pub fn request(&mut self, scale: u32) {
+        self.requested_scale = Some(std::cmp::max(1, scale));
+    }
+
+    /// Records readback of a requested screenshot into the frame's encoder. Must be called after
+    /// the window's frame has been recorded with the same encoder.
+    pub fn capture(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        game: &Game,
+        window_renderer: &mut FrameRenderer,
+    ) {
+        let scale = match self.requested_scale.take() {
+            Some(scale) => scale,
+            None => return,
+        };
+        let (window_width, window_height) = window_renderer.output_size();
+        //no bigger than the device can make the output texture
+        let max_scale = std::cmp::max(1, device.limits().max_texture_dimension_2d / std::cmp::max(window_width, window_height));
+        if scale > max_scale {
+            println!("Screenshot scale {} is too big for this device, using {}", scale, max_scale);
+        }
+        let scale = std::cmp::min(scale, max_scale);
+        let width = window_width * scale;
+        let height = window_height * scale;
+        let output = match self.output.take() {
+            Some(output) if output.width == width && output.height == height => output,
+            _ => Texture::create(device, width, height, self.output_format, wgpu::AddressMode::ClampToEdge, None),
+        };
+
+        if scale == 1 {
+            window_renderer.render_output(device, queue, encoder, game, &output.view);
+        } else {
+            let render_scale = window_renderer.render_scale();
+            let output_format = self.output_format;
+            let frame_renderer = self.frame_renderer.get_or_insert_with(|| {
+                FrameRenderer::sharing_exposure(device, width, height, render_scale, output_format)
+            });
+            frame_renderer.resize(device, width, height);
+            frame_renderer.set_render_scale(device, render_scale);
+            //exposed like the window's frame, rather than adapting from scratch
+            frame_renderer.share_exposure(encoder, window_renderer);
+            frame_renderer.render(device, queue, encoder, game, &output.view);
+        }
+        let readback = Readback::copy_texture(device, encoder, &output);
+        self.output = Some(output);
+
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|t| t.as_millis()).unwrap_or(0);
+        self.submitted.push(PendingScreenshot {
+            readback,
+            map_state: Arc::new(AtomicU8::new(MAPPING)),
+            path: format!("{}/redrock_{}.png", SCREENSHOT_DIR, timestamp),
+        });
+    }
+
+    /// Must be called after the encoder passed to `capture` has been submitted.
+    pub fn after_submit(&mut self) {
+        for screenshot in self.submitted.drain(..) {
+            let map_state = screenshot.map_state.clone();
+            screenshot.readback.map(move |result| {
+                map_state.store(if result.is_ok() { MAPPED } else { MAP_FAILED }, Ordering::Release);
+            });
+            self.mapping.push(screenshot);
+        }
+    }
+
+    /// Hands any screenshots the GPU has finished with to a thread for encoding, and drops any
+    /// which couldn't be read back.
+    pub fn poll(&mut self, device: &wgpu::Device) {
+        if self.mapping.is_empty() {
+            return;
+        }
+        device.poll(wgpu::Maintain::Poll);
+        let (done, mapping): (Vec<PendingScreenshot>, Vec<PendingScreenshot>) = self.mapping.drain(..)
+            .partition(|screenshot| screenshot.map_state.load(Ordering::Acquire) != MAPPING);
+        self.mapping = mapping;
+        for screenshot in done {
+            if screenshot.map_state.load(Ordering::Acquire) == MAP_FAILED {
+                println!("Failed to map screenshot buffer for {}", screenshot.path);
+                continue;
+            }
+            let image = screenshot.readback.to_image();
+            let path = screenshot.path;
+            std::thread::spawn(move || {
+                let result = std::fs::create_dir_all(SCREENSHOT_DIR)
+                    .map_err(|err| err.to_string())
+                    .and_then(|_| image.save(&path).map_err(|err| err.to_string()));
+                match result {
+                    Ok(()) => println!("Saved screenshot {}", path),
+                    Err(err) => println!("Failed to save screenshot {}: {}", path, err),
+                }
+            });
+        }
+    }
+}
@@ -70,15 +70,10 @@ pub fn run_event_loop(window: Window, mut game_frame: impl FnMut(&mut Vec<InputE
                     _ => ()
                 }
             },
-            Event::DeviceEvent {event: device_event, device_id: _} => {
-                match device_event {
-                    DeviceEvent::MouseMotion {delta} => {
-                        event_queue.push(InputEvent::Mouse {
-                            delta
-                        });
-                    },
-                    _ => ()
-                }
+            Event::DeviceEvent {event: DeviceEvent::MouseMotion {delta}, device_id: _} => {
+                event_queue.push(InputEvent::Mouse {
+                    delta
+                });
             },
             Event::MainEventsCleared => {
                 if !game_frame(&mut event_queue, resize) {
@@ -0,0 +1,255 @@
+use cgmath::{prelude::*, Quaternion, Rad, Vector3};
+
+// Segments in each circle of spheres and capsules
+const CIRCLE_SEGMENTS: usize = 16;
+// Length of arrow heads relative to the arrow
+const ARROW_HEAD: f32 = 0.2;
+
+/// A shape to draw in wireframe, in world space.
+#[derive(Copy, Clone, Debug)]
+pub enum DebugCommand {
+    Line {
+        start: Vector3<f32>,
+        end: Vector3<f32>,
+    },
+    // From `start` along `vector`, with a head at the far end
+    Arrow {
+        start: Vector3<f32>,
+        vector: Vector3<f32>,
+    },
+    // A small cross
+    Point {
+        position: Vector3<f32>,
+        size: f32,
+    },
+    Box {
+        center: Vector3<f32>,
+        half_extents: Vector3<f32>,
+        rotation: Quaternion<f32>,
+    },
+    Sphere {
+        center: Vector3<f32>,
+        radius: f32,
+    },
+    // Upright along the rotated Z axis, like capsule colliders
+    Capsule {
+        center: Vector3<f32>,
+        rotation: Quaternion<f32>,
+        radius: f32,
+        half_height: f32,
+    },
+}
+
+/// Shapes to draw over the scene for the current frame, which anything can push to. Pushes are
+/// ignored while it's disabled, so callers needn't check first.
+pub struct DebugDraw {
+    pub enabled: bool,
+    commands: Vec<(DebugCommand, Vector3<f32>)>,
+}
+
+impl Default for DebugDraw {
+    fn default() -> Self {
+        DebugDraw::new()
+    }
+}
+
+impl DebugDraw {
+    pub fn new() -> DebugDraw {
+        DebugDraw {
+            enabled: false,
+            commands: Vec::new(),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        self.commands.clear();
+    }
+
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn commands(&self) -> &[(DebugCommand, Vector3<f32>)] {
+        &self.commands
+    }
+
+    pub fn push(&mut self, command: DebugCommand, colour: Vector3<f32>) {
+        if self.enabled {
+            self.commands.push((command, colour));
+        }
+    }
+
+    pub fn line(&mut self, start: Vector3<f32>, end: Vector3<f32>, colour: Vector3<f32>) {
+        self.push(DebugCommand::Line { start, end }, colour);
+    }
+
+    pub fn arrow(&mut self, start: Vector3<f32>, vector: Vector3<f32>, colour: Vector3<f32>) {
+        self.push(DebugCommand::Arrow { start, vector }, colour);
+    }
+
+    pub fn point(&mut self, position: Vector3<f32>, size: f32, colour: Vector3<f32>) {
+        self.push(DebugCommand::Point { position, size }, colour);
+    }
+
+    pub fn bounds(&mut self, min: Vector3<f32>, max: Vector3<f32>, colour: Vector3<f32>) {
+        self.push(DebugCommand::Box {
+            center: (min + max) / 2.0,
+            half_extents: (max - min) / 2.0,
+            rotation: Quaternion::one(),
+        }, colour);
+    }
+
+    /// Breaks every command down into line segments, calling `visit` with each one's ends and
+    /// colour.
+    pub fn for_each_line(&self, mut visit: impl FnMut(Vector3<f32>, Vector3<f32>, Vector3<f32>)) {
+        for &(command, colour) in &self.commands {
+            let mut line = |start, end| visit(start, end, colour);
+            match command {
+                DebugCommand::Line { start, end } => line(start, end),
+                DebugCommand::Arrow { start, vector } => {
+                    let end = start + vector;
+                    line(start, end);
+                    let length = vector.magnitude();
+                    if length > 0.0 {
+                        let (side, up) = perpendiculars(vector / length);
+                        let back = -vector * ARROW_HEAD;
+                        for across in [side, -side, up, -up] {
+                            line(end, end + back + across * length * ARROW_HEAD * 0.5);
+                        }
+                    }
+                },
+                DebugCommand::Point { position, size } => {
+                    for axis in [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()] {
+                        line(position - axis * size, position + axis * size);
+                    }
+                },
+                DebugCommand::Box { center, half_extents, rotation } => {
+                    let corner = |i: usize| {
+                        let signs = Vector3::new(
+                            if i & 1 == 0 { -1.0 } else { 1.0 },
+                            if i & 2 == 0 { -1.0 } else { 1.0 },
+                            if i & 4 == 0 { -1.0 } else { 1.0 },
+                        );
+                        center + rotation.rotate_vector(half_extents.mul_element_wise(signs))
+                    };
+                    //corners one bit apart share an edge
+                    for i in 0..8 {
+                        for bit in [1, 2, 4] {
+                            if i & bit == 0 {
+                                line(corner(i), corner(i | bit));
+                            }
+                        }
+                    }
+                },
+                DebugCommand::Sphere { center, radius } => {
+                    circle(center, Vector3::unit_x() * radius, Vector3::unit_y() * radius, 1.0, &mut line);
+                    circle(center, Vector3::unit_y() * radius, Vector3::unit_z() * radius, 1.0, &mut line);
+                    circle(center, Vector3::unit_z() * radius, Vector3::unit_x() * radius, 1.0, &mut line);
+                },
+                DebugCommand::Capsule { center, rotation, radius, half_height } => {
+                    let x = rotation.rotate_vector(Vector3::unit_x()) * radius;
+                    let y = rotation.rotate_vector(Vector3::unit_y()) * radius;
+                    let z = rotation.rotate_vector(Vector3::unit_z());
+                    let top = center + z * half_height;
+                    let bottom = center - z * half_height;
+                    circle(top, x, y, 1.0, &mut line);
+                    circle(bottom, x, y, 1.0, &mut line);
+                    for side in [x, -x, y, -y] {
+                        line(bottom + side, top + side);
+                    }
+                    //domed ends, as half circles over the top and under the bottom
+                    let z = z * radius;
+                    for side in [x, y] {
+                        circle(top, side, z, 0.5, &mut line);
+                        circle(bottom, side, -z, 0.5, &mut line);
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// Two unit vectors at right angles to `direction` and each other.
+fn perpendiculars(direction: Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
+    let other = if direction.x.abs() < 0.9 { Vector3::unit_x() } else { Vector3::unit_y() };
+    let side = direction.cross(other).normalize();
+    (side, direction.cross(side))
+}
+
+/// Draws `fraction` of the way round the ellipse through `center + u` and `center + v`.
+fn circle(center: Vector3<f32>, u: Vector3<f32>, v: Vector3<f32>, fraction: f32, line: &mut impl FnMut(Vector3<f32>, Vector3<f32>)) {
+    let segments = ((CIRCLE_SEGMENTS as f32 * fraction).ceil() as usize).max(1);
+    let point = |i: usize| {
+        let angle = Rad(std::f32::consts::TAU * fraction * i as f32 / segments as f32);
+        center + u * angle.cos() + v * angle.sin()
+    };
+    for i in 0..segments {
+        line(point(i), point(i + 1));
+    }
+}
+
+mod tests {
+    use super::*;
+
+    fn lines(draw: &DebugDraw) -> Vec<(Vector3<f32>, Vector3<f32>, Vector3<f32>)> {
+        let mut lines = Vec::new();
+        draw.for_each_line(|start, end, colour| lines.push((start, end, colour)));
+        lines
+    }
+
+    #[test]
+    fn test_debug_draw() {
+        let red = Vector3::new(1.0, 0.0, 0.0);
+        let mut draw = DebugDraw::new();
+        draw.line(Vector3::zero(), Vector3::unit_x(), red);
+        assert!(draw.is_empty());
+
+        draw.toggle();
+        draw.line(Vector3::zero(), Vector3::unit_x(), red);
+        draw.bounds(Vector3::new(-1.0, -2.0, -3.0), Vector3::new(1.0, 2.0, 3.0), red);
+        assert_eq!(2, draw.len());
+        let drawn = lines(&draw);
+        assert_eq!(1 + 12, drawn.len());
+        assert!(drawn.iter().all(|(_, _, colour)| *colour == red));
+
+        //box edges run along one axis each, between corners of the bounds
+        for (start, end, _) in &drawn[1..] {
+            let edge = end - start;
+            let axes = [edge.x, edge.y, edge.z].iter().filter(|d| d.abs() > 1e-6).count();
+            assert_eq!(1, axes);
+            assert!(start.x.abs() == 1.0 && start.y.abs() == 2.0 && start.z.abs() == 3.0);
+        }
+
+        //spheres keep to their radius, and capsules to within theirs of the segment
+        draw.clear();
+        draw.push(DebugCommand::Sphere { center: Vector3::unit_z(), radius: 2.0 }, red);
+        for (start, end, _) in lines(&draw) {
+            assert!(((start - Vector3::unit_z()).magnitude() - 2.0).abs() < 1e-4);
+            assert!(((end - Vector3::unit_z()).magnitude() - 2.0).abs() < 1e-4);
+        }
+        draw.clear();
+        draw.push(DebugCommand::Capsule {
+            center: Vector3::zero(),
+            rotation: Quaternion::from_angle_x(Rad(1.0)),
+            radius: 0.5,
+            half_height: 1.0,
+        }, red);
+        let axis = Quaternion::from_angle_x(Rad(1.0)).rotate_vector(Vector3::unit_z());
+        for (start, _, _) in lines(&draw) {
+            let along = start.dot(axis).clamp(-1.0, 1.0);
+            assert!(((start - axis * along).magnitude() - 0.5).abs() < 1e-4);
+        }
+
+        draw.toggle();
+        assert!(draw.is_empty());
+    }
+}
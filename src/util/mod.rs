@@ -1,2 +1,3 @@
 pub mod ringbuffer;
 pub mod saltybuffer;
+pub mod debug_draw;
@@ -2,6 +2,7 @@ use std::ops::Index;
 
 /// A fixed buffer intended for short-lived entries which do not need stable long term
 /// referencing.
+#[derive(Copy, Clone)]
 pub struct FixedRingBuffer<T: Copy, const N: usize> {
     pub items: [Option<T>; N],
     head: usize,
@@ -107,6 +108,18 @@ impl<T: Copy, const N: usize> FixedRingBuffer<T, { N }> {
         Some(i)
     }
 
+    pub fn clear(&mut self) {
+        self.items = [None; N];
+        self.head = 0;
+        self.count = 0;
+    }
+
+    /// Occupied entries from the oldest slot to the newest, which is the order they were pushed
+    /// in as long as none were removed in between.
+    pub fn iter(&self) -> impl Iterator<Item=&T> {
+        (0..N).filter_map(move |i| self.items[(self.head + i) % N].as_ref())
+    }
+
     pub fn remove(&mut self, i: usize) -> bool {
         if self.items[i].is_some() {
             self.items[i] = None;
@@ -162,6 +175,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_fixedringbuffer_double() {
         let mut buf = FixedRingBuffer::<u32, 2>::new();
         //['_,_]
@@ -249,4 +263,23 @@ mod tests {
         //[9,10,11,12,13,'1,5,7]
         assert_eq!(Some(1), buf[5]);
     }
+
+    #[test]
+    fn test_fixedringbuffer_iter() {
+        let mut buf = FixedRingBuffer::<u32, 4>::new();
+        buf.push(1);
+        buf.push(2);
+        buf.push(3);
+        //[1,2,3,'_]
+        assert_eq!(vec![1, 2, 3], buf.iter().copied().collect::<Vec<u32>>());
+
+        buf.push(4);
+        buf.push(5);
+        //[5,'2,3,4]
+        assert_eq!(vec![2, 3, 4, 5], buf.iter().copied().collect::<Vec<u32>>());
+
+        buf.clear();
+        assert_eq!(0, buf.count());
+        assert_eq!(None, buf.iter().next());
+    }
 }
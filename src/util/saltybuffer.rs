@@ -1,4 +1,4 @@
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[repr(C)]
 pub struct SaltyId {
     salt: u16,
@@ -19,6 +19,11 @@ impl SaltyId {
     pub fn none() -> SaltyId {
         NONE
     }
+
+    /// Slot in the buffer, for keying other fixed-size structures by the same id.
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
 }
 
 impl Default for SaltyId {
@@ -102,7 +107,7 @@ impl<T: Copy + Default, const N: usize> SaltyBuffer<T, { N }> {
     }
 
     pub fn remove(&mut self, id: SaltyId) -> Option<T> {
-        let mut slot = self.items[id.index as usize];
+        let slot = &mut self.items[id.index as usize];
         if slot.salt == id.salt && slot.salt != 0 {
             let data = slot.data;
             slot.data = T::default();
@@ -151,4 +156,22 @@ mod tests {
         assert_eq!(4, std::mem::size_of::<Salty<Example>>());
         assert_eq!(4100, std::mem::size_of::<SaltyBuffer<Example, 1024>>());
     }
+
+    #[test]
+    fn test_remove() {
+        let mut buf: SaltyBuffer<u32, 4> = SaltyBuffer::new();
+        let a = buf.add(1).unwrap();
+        let b = buf.add(2).unwrap();
+
+        assert_eq!(Some(1), buf.remove(a));
+        assert_eq!(None, buf.get(a));
+        assert_eq!(None, buf.remove(a));
+        assert_eq!(1, buf.count());
+        assert_eq!(Some(&2), buf.get(b));
+
+        //the freed slot is the first to be reused
+        let c = buf.add(3).unwrap();
+        assert_eq!(a.index(), c.index());
+        assert_eq!(2, buf.count());
+    }
 }